@@ -14,21 +14,19 @@ pub struct AlbumArt {
 }
 
 impl AlbumArt {
-    pub fn from_image_data(data: &[u8]) -> Option<Self> {
-        let img = image::load_from_memory(data).ok()?;
-        Some(Self::from_image(&img))
-    }
+    pub const DEFAULT_WIDTH: u32 = ART_WIDTH;
+    pub const DEFAULT_HEIGHT: u32 = ART_HEIGHT;
 
-    pub fn from_image(img: &DynamicImage) -> Self {
-        // We need ART_WIDTH columns and ART_HEIGHT * 2 pixel rows
+    pub fn from_image_sized(img: &DynamicImage, width: u32, height: u32) -> Self {
+        // We need `width` columns and `height` * 2 pixel rows
         // (each terminal cell holds 2 vertical pixels via half-blocks)
-        let pixel_rows = ART_HEIGHT * 2;
-        let resized = img.resize_exact(ART_WIDTH, pixel_rows, image::imageops::FilterType::Lanczos3);
+        let pixel_rows = height * 2;
+        let resized = img.resize_exact(width, pixel_rows, image::imageops::FilterType::Lanczos3);
 
-        let mut pixels = Vec::with_capacity(ART_HEIGHT as usize);
-        for row in 0..ART_HEIGHT {
-            let mut row_pixels = Vec::with_capacity(ART_WIDTH as usize);
-            for col in 0..ART_WIDTH {
+        let mut pixels = Vec::with_capacity(height as usize);
+        for row in 0..height {
+            let mut row_pixels = Vec::with_capacity(width as usize);
+            for col in 0..width {
                 let top_y = row * 2;
                 let bot_y = row * 2 + 1;
                 let top = resized.get_pixel(col, top_y);
@@ -40,8 +38,8 @@ impl AlbumArt {
 
         Self {
             pixels,
-            width: ART_WIDTH as u16,
-            height: ART_HEIGHT as u16,
+            width: width as u16,
+            height: height as u16,
         }
     }
 
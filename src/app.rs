@@ -1,14 +1,34 @@
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::{Receiver, Sender};
-use rand::seq::SliceRandom;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::albumart::AlbumArt;
+use crate::artloader::{ArtLoader, ArtSize};
 use crate::audio::{AudioCommand, AudioEvent};
-use crate::library::Track;
-use crate::metadata;
+use crate::browser::{self, BrowseEntry, BrowseView, Browser, EntryKind};
+use crate::chapters::{self, Chapter};
+use crate::columns::{self, Column, SortColumn};
+use crate::config::{self, Config};
+use crate::cover;
+use crate::desktop;
+use crate::duplicates::{self, MatchMode};
+use crate::history::History;
+use crate::library::{self, Track};
+use crate::metadata::{self, TagEdit};
+use crate::organize::{self, Template};
+use crate::playlist::{self, Playlist, PlaylistEntry, PlaylistFormat};
+use crate::plays::PlayCounts;
+use crate::positions::Positions;
+use crate::query::{self, Query, Stats};
+use crate::queue::Queue;
+use crate::ratings::{self, Ratings};
+use crate::session::Session;
+use crate::shuffle::{self, ShuffleMode};
+use crate::smart::{self, SmartPlaylist};
+use crate::tageditor::TagEditor;
 use crate::visualizer::Visualizer;
 
 /// Shared playback state for the remote control
@@ -22,13 +42,27 @@ pub struct PlaybackState {
     pub is_playing: bool,
     pub volume: f32,
     pub shuffle: bool,
+    pub shuffle_mode: String,
     pub repeat: String,
     pub theme: String,
     pub visualizer_mode: String,
     pub visualizer_bars: Vec<f32>,
+    pub queue: Vec<QueueEntry>,
+    /// Chapters of the playing file, empty when it has none
+    pub chapters: Vec<Chapter>,
+    /// Index into `chapters` of the one playing
+    pub chapter: Option<usize>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// One upcoming track, as the remote shows it
+#[derive(Clone, Serialize)]
+pub struct QueueEntry {
+    pub title: String,
+    pub artist: String,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RepeatMode {
     Off,
     All,
@@ -53,7 +87,7 @@ impl RepeatMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Theme {
     #[default]
     Default,
@@ -93,7 +127,7 @@ pub struct SleepTimer {
     pub duration_mins: u32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PlaybackSpeed {
     Slow50,
     Slow75,
@@ -149,6 +183,13 @@ impl PlaybackSpeed {
     }
 }
 
+/// `w` was pressed: the name being typed for the playlist `tracks` go to
+#[derive(Debug, Clone)]
+pub struct PlaylistPrompt {
+    pub name: String,
+    tracks: Vec<usize>,
+}
+
 pub struct App {
     pub library: Vec<Track>,
     pub filtered_indices: Vec<usize>,
@@ -157,18 +198,103 @@ pub struct App {
     pub is_playing: bool,
     pub volume: f32,
     pub shuffle: bool,
+    /// How the shuffle order is built while `shuffle` is on
+    pub shuffle_mode: ShuffleMode,
+    /// The mode `s` starts its cycle from, from the config
+    preferred_shuffle: ShuffleMode,
     pub repeat: RepeatMode,
     pub progress: f64,
     pub duration: f64,
     pub visualizer: Visualizer,
-    pub album_art: Option<AlbumArt>,
+    /// Background cover decoder shared by the now-playing panel and the grid
+    pub art: ArtLoader,
+    pub placeholder_art: AlbumArt,
     pub search_mode: bool,
     pub search_query: String,
+    /// The search as last parsed; kept while a half-typed query doesn't parse
+    pub search_filter: Query,
+    /// Why the search as typed doesn't parse
+    pub search_error: Option<String>,
     pub show_info: bool,
     pub scroll_offset: usize,
     pub should_quit: bool,
     pub error_message: Option<String>,
+    /// Short-lived confirmation shown in the footer, with when it was set
+    pub status_message: Option<(String, Instant)>,
     pub shuffle_order: Vec<usize>,
+    /// Where long files were left off, and which were finished
+    pub positions: Positions,
+    /// Files at least this many seconds long remember their position
+    resume_min_duration: f64,
+    /// The search the shuffle order was built from; a new search reshuffles
+    shuffle_query: String,
+
+    // Library browser: tabbed views with drill-down. `browse_filtered` holds
+    // indices into the current level's entries, like `filtered_indices` does
+    // for tracks.
+    pub browser: Browser,
+    pub browse_filtered: Vec<usize>,
+    /// Tracks to play before continuing in library order
+    pub queue: Queue,
+    /// The rest of the album or playlist being played through, which
+    /// plays before the queue
+    pub context: Vec<usize>,
+    /// Queue pane is open and has the keyboard
+    pub show_queue: bool,
+    pub queue_cursor: usize,
+    /// Chapters of the playing file, empty when it has none
+    pub chapters: Vec<Chapter>,
+    pub show_chapters: bool,
+    pub chapter_cursor: usize,
+    /// Tracks in the order they played, for prev/next and the Recent view
+    pub history: History,
+    /// Whether the playing track came from the queue
+    pub playing_from_queue: bool,
+    /// The last track played in library order, where playback continues
+    /// once the queue runs out
+    pub library_position: Option<usize>,
+    /// Lesser copies of tracks that exist more than once (by tags and duration)
+    pub duplicate_copies: HashSet<usize>,
+    /// Hide `duplicate_copies` everywhere except the duplicates view
+    pub hide_duplicates: bool,
+    /// Open tag edit form, drawn over the library
+    pub tag_editor: Option<TagEditor>,
+    /// The previous tags of the last saved edit batch, for undo
+    pub last_tag_edit: Vec<(usize, TagEdit)>,
+    /// Library folder, when a directory was opened; organizing needs it
+    pub library_root: Option<PathBuf>,
+    pub organize_template: String,
+    pub cover_files: Vec<String>,
+    /// Moves waiting for a second `O` press
+    pub pending_organize: Option<organize::Plan>,
+    /// Playlist files found under the library folder
+    pub playlists: Vec<PathBuf>,
+    pub playlist_prompt: Option<PlaylistPrompt>,
+    /// Smart playlists from the config, then those made with `W`
+    pub smart_playlists: Vec<SmartPlaylist>,
+    /// `W` was pressed: the `name = rules` being typed
+    pub smart_prompt: Option<String>,
+    pub ratings: Ratings,
+    /// Every track's play count and last play, for rules and columns
+    pub play_counts: PlayCounts,
+    /// Tracks marked with `x`, `V` or `*`, by library index so they stay put
+    /// while the search or the browser level changes
+    pub marks: HashSet<usize>,
+    /// Library index of the track where `V` was pressed; the rows from it to
+    /// the cursor count as marked until the range is fixed or dropped
+    pub visual_anchor: Option<usize>,
+    /// Columns of the track list, left to right
+    pub columns: Vec<&'static Column>,
+    /// Column track lists are sorted by, when one was picked
+    pub column_sort: Option<SortColumn>,
+    /// `L` was pressed: the column editor's cursor row
+    pub column_editor: Option<usize>,
+    /// `C` was pressed; the next key picks a cover action
+    pub cover_prompt: bool,
+    pub max_cover_kb: u64,
+    /// Album grid dimensions in tiles, refreshed from the terminal size each frame
+    pub grid_columns: usize,
+    pub grid_rows: usize,
 
     // New features
     pub theme: Theme,
@@ -185,33 +311,87 @@ pub struct App {
 impl App {
     pub fn new(
         library: Vec<Track>,
+        library_root: Option<PathBuf>,
+        config: &Config,
         cmd_tx: Sender<AudioCommand>,
         event_rx: Receiver<AudioEvent>,
         sample_rx: Receiver<Vec<f32>>,
     ) -> Self {
-        let num_tracks = library.len();
-        let filtered_indices: Vec<usize> = (0..num_tracks).collect();
+        let duplicate_copies =
+            duplicates::lesser_copies(&duplicates::find_duplicates(&library, MatchMode::Tags));
 
-        Self {
+        let mut app = Self {
             library,
-            filtered_indices,
+            filtered_indices: Vec::new(),
             selected_index: 0,
             playing_index: None,
             is_playing: false,
             volume: 0.8,
             shuffle: false,
+            shuffle_mode: config.shuffle_mode,
+            preferred_shuffle: config.shuffle_mode,
             repeat: RepeatMode::Off,
             progress: 0.0,
             duration: 0.0,
             visualizer: Visualizer::new(),
-            album_art: None,
+            art: ArtLoader::new(config.cover_files.clone()),
+            placeholder_art: AlbumArt::placeholder(),
             search_mode: false,
             search_query: String::new(),
+            search_filter: Query::default(),
+            search_error: None,
             show_info: false,
             scroll_offset: 0,
             should_quit: false,
             error_message: None,
+            status_message: None,
             shuffle_order: Vec::new(),
+            positions: Positions::load(),
+            resume_min_duration: config.resume_min_duration,
+            shuffle_query: String::new(),
+            browser: Browser::default(),
+            browse_filtered: Vec::new(),
+            queue: Queue::default(),
+            show_queue: false,
+            queue_cursor: 0,
+            chapters: Vec::new(),
+            show_chapters: false,
+            chapter_cursor: 0,
+            history: History::new(config.history_size),
+            playing_from_queue: false,
+            library_position: None,
+            context: Vec::new(),
+            duplicate_copies,
+            hide_duplicates: config.hide_duplicates,
+            tag_editor: None,
+            last_tag_edit: Vec::new(),
+            library_root,
+            organize_template: config.organize_template.clone(),
+            cover_files: config.cover_files.clone(),
+            pending_organize: None,
+            playlists: Vec::new(),
+            playlist_prompt: None,
+            smart_playlists: config
+                .smart_playlists
+                .iter()
+                .map(|p| SmartPlaylist {
+                    from_config: true,
+                    ..p.clone()
+                })
+                .chain(smart::saved())
+                .collect(),
+            smart_prompt: None,
+            ratings: Ratings::load(),
+            play_counts: PlayCounts::default(),
+            marks: HashSet::new(),
+            visual_anchor: None,
+            columns: columns::parse(&config.columns),
+            column_sort: SortColumn::parse(&config.column_sort),
+            column_editor: None,
+            cover_prompt: false,
+            max_cover_kb: config.max_cover_kb,
+            grid_columns: 1,
+            grid_rows: 1,
             theme: Theme::default(),
             sleep_timer: None,
             speed: PlaybackSpeed::Normal,
@@ -219,14 +399,49 @@ impl App {
             cmd_tx,
             event_rx,
             sample_rx,
-        }
+        };
+        app.queue = Queue::load(&app.library);
+        app.history = History::load(&app.library, config.history_size);
+        app.play_counts = PlayCounts::load(&app.library, &app.history);
+        app.update_filter();
+        app
+    }
+
+    /// Whether `index` is a lesser duplicate that is currently hidden
+    pub fn is_hidden(&self, index: usize) -> bool {
+        self.hide_duplicates && self.duplicate_copies.contains(&index)
+    }
+
+    /// Library indices shown outside the duplicates view, in library order
+    fn visible_indices(&self) -> Vec<usize> {
+        (0..self.library.len()).filter(|&i| !self.is_hidden(i)).collect()
+    }
+
+    pub fn toggle_hide_duplicates(&mut self) {
+        self.hide_duplicates = !self.hide_duplicates;
+        self.shuffle_order.clear();
+        self.set_view(self.browser.view);
     }
 
     pub fn play_selected(&mut self) {
+        if self.in_grid() {
+            self.play_all();
+            return;
+        }
+        if !self.browser.showing_tracks() {
+            self.browse_enter();
+            return;
+        }
         if self.filtered_indices.is_empty() {
             return;
         }
+        // A playlist plays on in its own order, not the library's
+        if self.browser.tracks.as_ref().is_some_and(|t| t.ordered) {
+            self.play_list(self.filtered_indices[self.selected_index..].to_vec());
+            return;
+        }
         let lib_index = self.filtered_indices[self.selected_index];
+        self.context.clear();
         self.play_track(lib_index);
     }
 
@@ -234,27 +449,112 @@ impl App {
         if index >= self.library.len() {
             return;
         }
+        self.history.record(index);
+        self.play_counts.record(&self.library[index]);
+        self.playing_from_queue = false;
+        self.resume_track(index);
+        if self.browser.view == BrowseView::Recent && !self.browser.showing_tracks() {
+            let selected = self.selected_index;
+            self.set_view(BrowseView::Recent);
+            self.selected_index = selected;
+        }
+    }
+
+    /// Start `index` without touching the history, picking a long file up
+    /// where it was left off
+    fn resume_track(&mut self, index: usize) {
+        self.start_track(index);
+        let track = &self.library[index];
+        // Positions are kept per file, which CUE tracks share
+        if track.duration < self.resume_min_duration || track.cue.is_some() {
+            return;
+        }
+        let path = track.path.clone();
+        let Some(position) = self.positions.get(&path).map(|p| p.position) else {
+            return;
+        };
+        if position < 1.0 {
+            return;
+        }
+        let _ = self.cmd_tx.send(AudioCommand::Seek(position));
+        self.progress = position;
+        let secs = position as u64;
+        self.set_status(format!(
+            "Resuming at {}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        ));
+    }
+
+    /// Save how far into the current track playback got, if it's long
+    /// enough to resume later
+    pub fn remember_position(&mut self) {
+        let Some(index) = self.playing_index else {
+            return;
+        };
+        let track = &self.library[index];
+        if track.duration >= self.resume_min_duration && track.cue.is_none() {
+            self.positions.record(&track.path, self.progress, track.duration);
+        }
+    }
+
+    /// Start playing `index` from the top without touching the history
+    fn start_track(&mut self, index: usize) {
+        self.remember_position();
         let path = self.library[index].path.clone();
+        let segment = self.library[index].segment();
         self.playing_index = Some(index);
         self.is_playing = true;
         self.progress = 0.0;
         self.duration = self.library[index].duration;
+        // A CUE track is already a slice of the file its chapters describe
+        self.chapters = match self.library[index].cue {
+            Some(_) => Vec::new(),
+            None => chapters::read_chapters(&path),
+        };
+        self.chapter_cursor = 0;
+
+        // Album art decodes in the background; the placeholder shows meanwhile
+        let key = self.library[index].album_key();
+        self.art.request(&key, &path, ArtSize::Full);
 
-        // Load album art
-        self.load_album_art(&path);
+        let _ = self.cmd_tx.send(AudioCommand::Play(path, segment));
+        // Each track gets a fresh sink, which starts at full volume and speed
+        let _ = self.cmd_tx.send(AudioCommand::SetVolume(self.volume));
+        let _ = self.cmd_tx.send(AudioCommand::SetSpeed(self.speed.as_f32()));
+    }
 
-        let _ = self.cmd_tx.send(AudioCommand::Play(path));
+    /// Cover for the now-playing panel, or the placeholder while loading or
+    /// when the album has none
+    pub fn current_album_art(&self) -> Option<&AlbumArt> {
+        let track = self.current_track()?;
+        let art = self.art.get(&track.album_key(), ArtSize::Full).flatten();
+        Some(art.unwrap_or(&self.placeholder_art))
     }
 
-    fn load_album_art(&mut self, path: &PathBuf) {
-        if let Ok(meta) = metadata::read_metadata(path) {
-            if let Some(art_data) = meta.album_art {
-                self.album_art = AlbumArt::from_image_data(&art_data);
-            } else {
-                self.album_art = Some(AlbumArt::placeholder());
-            }
-        } else {
-            self.album_art = Some(AlbumArt::placeholder());
+    /// Collect decoded covers and queue thumbnails for the visible grid tiles
+    pub fn update_art(&mut self) {
+        self.art.poll();
+
+        if self.browser.view != BrowseView::Grid || self.browser.showing_tracks() {
+            return;
+        }
+        let Some(level) = self.browser.current_level() else {
+            return;
+        };
+        let cols = self.grid_columns.max(1);
+        let first_row = (self.selected_index / cols).saturating_sub(self.grid_rows.saturating_sub(1));
+        // Prefetch one row past the bottom edge
+        let start = first_row * cols;
+        let end = (start + (self.grid_rows + 1) * cols).min(self.browse_filtered.len());
+        let wanted: Vec<(String, PathBuf)> = self.browse_filtered[start..end]
+            .iter()
+            .filter_map(|&i| level.entries[i].tracks.first())
+            .map(|&t| (self.library[t].album_key(), self.library[t].path.clone()))
+            .collect();
+        for (key, path) in wanted {
+            self.art.request(&key, &path, ArtSize::Thumb);
         }
     }
 
@@ -279,7 +579,6 @@ impl App {
         self.playing_index = None;
         self.progress = 0.0;
         self.duration = 0.0;
-        self.album_art = Some(AlbumArt::placeholder());
     }
 
     pub fn next_track(&mut self) {
@@ -287,21 +586,63 @@ impl App {
             return;
         }
 
+        // Inside a chaptered file, move by chapter until the last one
+        if let Some(next) = self.current_chapter().map(|c| c + 1) {
+            if let Some(chapter) = self.chapters.get(next) {
+                self.seek_to(chapter.start);
+                return;
+            }
+        }
+
+        // After stepping back, next retraces what already played
+        if let Some(index) = self.history.forward() {
+            self.resume_track(index);
+            return;
+        }
+
+        // The list being played through, then the queue
+        if !self.context.is_empty() {
+            let next = self.context.remove(0);
+            self.play_track(next);
+            self.playing_from_queue = true;
+            return;
+        }
+
+        if let Some(next) = self.queue.pop() {
+            if !self.playing_from_queue {
+                self.library_position = self.playing_index;
+            }
+            self.play_track(next);
+            self.playing_from_queue = true;
+            self.queue_cursor = self.queue_cursor.min(self.queue.len().saturating_sub(1));
+            return;
+        }
+
+        // Once the queue runs out, carry on from where library order left off
+        let position = if self.playing_from_queue {
+            self.library_position
+        } else {
+            self.playing_index
+        };
         let next_index = if self.shuffle {
-            self.get_shuffle_next()
-        } else if let Some(current) = self.playing_index {
-            let next = current + 1;
-            if next >= self.library.len() {
-                match self.repeat {
-                    RepeatMode::All => 0,
+            self.get_shuffle_next(position)
+        } else if let Some(current) = position {
+            match (current + 1..self.library.len()).find(|&i| !self.is_hidden(i)) {
+                Some(next) => next,
+                None => match self.repeat {
+                    RepeatMode::All => match self.visible_indices().first() {
+                        Some(&first) => first,
+                        None => return,
+                    },
                     RepeatMode::Off => return,
                     RepeatMode::One => current,
-                }
-            } else {
-                next
+                },
             }
         } else {
-            0
+            match self.visible_indices().first() {
+                Some(&first) => first,
+                None => return,
+            }
         };
 
         self.play_track(next_index);
@@ -312,28 +653,70 @@ impl App {
             return;
         }
 
+        // Inside a chaptered file: back to the start of this chapter, or to
+        // the previous one when already there
+        if let Some(current) = self.current_chapter() {
+            let start = self.chapters[current].start;
+            if self.progress - start > 3.0 {
+                self.seek_to(start);
+                return;
+            }
+            if current > 0 {
+                self.seek_to(self.chapters[current - 1].start);
+                return;
+            }
+        }
+
         // If we're more than 3 seconds in, restart the current track
         if self.progress > 3.0 {
             if let Some(idx) = self.playing_index {
-                self.play_track(idx);
+                self.start_track(idx);
                 return;
             }
         }
 
-        let prev_index = if let Some(current) = self.playing_index {
-            if current == 0 {
-                match self.repeat {
-                    RepeatMode::All => self.library.len() - 1,
-                    _ => 0,
-                }
-            } else {
-                current - 1
+        // Go back to what actually played before
+        if let Some(index) = self.history.back() {
+            self.resume_track(index);
+            return;
+        }
+
+        // Nothing earlier in the history: step back in library order, or
+        // restart when shuffling since there's no meaningful "previous"
+        let visible = self.visible_indices();
+        let prev_index = if self.shuffle {
+            match self.playing_index {
+                Some(current) => current,
+                None => return,
+            }
+        } else if let Some(current) = self.playing_index {
+            match visible.iter().rev().find(|&&i| i < current) {
+                Some(&prev) => prev,
+                None => match self.repeat {
+                    RepeatMode::All => visible.last().copied().unwrap_or(current),
+                    _ => visible.first().copied().unwrap_or(current),
+                },
             }
         } else {
-            0
+            match visible.first() {
+                Some(&first) => first,
+                None => return,
+            }
         };
 
-        self.play_track(prev_index);
+        if self.playing_index == Some(prev_index) {
+            self.start_track(prev_index);
+        } else {
+            self.history.step_earlier(prev_index);
+            self.resume_track(prev_index);
+        }
+    }
+
+    /// Jump to `position` seconds, updating the progress shown right away
+    pub fn seek_to(&mut self, position: f64) {
+        let position = position.clamp(0.0, self.duration.max(0.0));
+        let _ = self.cmd_tx.send(AudioCommand::Seek(position));
+        self.progress = position;
     }
 
     pub fn seek_forward(&mut self) {
@@ -356,11 +739,27 @@ impl App {
         let _ = self.cmd_tx.send(AudioCommand::SetVolume(self.volume));
     }
 
+    /// Turn shuffle on in the configured mode, or off
     pub fn toggle_shuffle(&mut self) {
         self.shuffle = !self.shuffle;
         if self.shuffle {
+            self.shuffle_mode = self.preferred_shuffle;
+            self.regenerate_shuffle();
+        }
+    }
+
+    /// Off, then each shuffle mode starting from the configured one, then off
+    pub fn cycle_shuffle(&mut self) {
+        if !self.shuffle {
+            self.toggle_shuffle();
+        } else if self.shuffle_mode.cycle() == self.preferred_shuffle {
+            self.shuffle = false;
+        } else {
+            self.shuffle_mode = self.shuffle_mode.cycle();
             self.regenerate_shuffle();
         }
+        let label = if self.shuffle { self.shuffle_mode.label() } else { "Off" };
+        self.set_status(format!("Shuffle: {}", label));
     }
 
     pub fn cycle_repeat(&mut self) {
@@ -368,203 +767,1438 @@ impl App {
     }
 
     pub fn move_selection_up(&mut self) {
-        if self.selected_index > 0 {
-            self.selected_index -= 1;
+        let step = self.row_step();
+        if self.selected_index >= step {
+            self.selected_index -= step;
         }
     }
 
     pub fn move_selection_down(&mut self) {
-        if self.selected_index + 1 < self.filtered_indices.len() {
-            self.selected_index += 1;
+        let step = self.row_step();
+        if self.selected_index + step < self.visible_len() {
+            self.selected_index += step;
         }
     }
 
-    pub fn toggle_search(&mut self) {
-        self.search_mode = !self.search_mode;
-        if !self.search_mode {
-            self.search_query.clear();
-            self.update_filter();
+    /// Grid-only horizontal movement
+    pub fn move_selection_left(&mut self) {
+        if self.in_grid() && self.selected_index > 0 {
+            self.selected_index -= 1;
         }
     }
 
-    pub fn search_input(&mut self, c: char) {
-        self.search_query.push(c);
-        self.update_filter();
+    pub fn move_selection_right(&mut self) {
+        if self.in_grid() && self.selected_index + 1 < self.visible_len() {
+            self.selected_index += 1;
+        }
     }
 
-    pub fn search_backspace(&mut self) {
-        self.search_query.pop();
-        self.update_filter();
+    pub fn in_grid(&self) -> bool {
+        self.browser.view == BrowseView::Grid && !self.browser.showing_tracks()
     }
 
-    fn update_filter(&mut self) {
-        if self.search_query.is_empty() {
-            self.filtered_indices = (0..self.library.len()).collect();
+    /// Up/down move a whole row of tiles in the grid, one line elsewhere
+    fn row_step(&self) -> usize {
+        if self.in_grid() {
+            self.grid_columns.max(1)
         } else {
-            let query = self.search_query.to_lowercase();
-            self.filtered_indices = self
-                .library
-                .iter()
-                .enumerate()
-                .filter(|(_, t)| {
-                    t.title.to_lowercase().contains(&query)
-                        || t.artist.to_lowercase().contains(&query)
-                })
-                .map(|(i, _)| i)
-                .collect();
+            1
         }
-        if self.selected_index >= self.filtered_indices.len() {
-            self.selected_index = self.filtered_indices.len().saturating_sub(1);
+    }
+
+    /// Number of rows in the library pane: tracks or browser entries
+    pub fn visible_len(&self) -> usize {
+        if self.browser.showing_tracks() {
+            self.filtered_indices.len()
+        } else {
+            self.browse_filtered.len()
         }
     }
 
-    fn regenerate_shuffle(&mut self) {
-        let mut rng = rand::thread_rng();
-        self.shuffle_order = (0..self.library.len()).collect();
-        self.shuffle_order.shuffle(&mut rng);
+    // === Library browser ===
+
+    pub fn next_view(&mut self) {
+        self.set_view(self.browser.view.cycle());
     }
 
-    fn get_shuffle_next(&mut self) -> usize {
-        if self.shuffle_order.is_empty() {
-            self.regenerate_shuffle();
-        }
+    pub fn prev_view(&mut self) {
+        self.set_view(self.browser.view.cycle_back());
+    }
 
-        if let Some(current) = self.playing_index {
-            if let Some(pos) = self.shuffle_order.iter().position(|&x| x == current) {
-                let next_pos = pos + 1;
-                if next_pos >= self.shuffle_order.len() {
-                    match self.repeat {
-                        RepeatMode::All => {
-                            self.regenerate_shuffle();
-                            self.shuffle_order[0]
-                        }
-                        RepeatMode::Off => current, // stay
-                        RepeatMode::One => current,
-                    }
-                } else {
-                    self.shuffle_order[next_pos]
-                }
-            } else {
-                self.shuffle_order[0]
-            }
+    pub fn set_view(&mut self, view: BrowseView) {
+        let visible = self.visible_indices();
+        let playlists = if view == BrowseView::Playlists {
+            self.playlist_entries()
         } else {
-            self.shuffle_order[0]
+            Vec::new()
+        };
+        self.browser.open(view, &self.library, &visible, &self.history, playlists);
+        self.selected_index = 0;
+        self.update_filter();
+    }
+
+    /// The browser entry under the cursor, when a group listing is shown
+    pub fn selected_entry(&self) -> Option<&BrowseEntry> {
+        if self.browser.showing_tracks() {
+            return None;
         }
+        let level = self.browser.current_level()?;
+        let idx = *self.browse_filtered.get(self.selected_index)?;
+        level.entries.get(idx)
     }
 
-    pub fn handle_track_finished(&mut self) {
-        match self.repeat {
-            RepeatMode::One => {
-                if let Some(idx) = self.playing_index {
-                    self.play_track(idx);
-                }
+    fn browse_enter(&mut self) {
+        let Some(mut entry) = self.selected_entry().cloned() else {
+            return;
+        };
+        // Smart playlists pick their tracks afresh each time they're opened
+        if let EntryKind::Smart(name) = &entry.kind {
+            if let Some(smart) = self.smart_playlists.iter().find(|p| &p.name == name) {
+                let stats = Stats::new(&self.play_counts, &self.ratings);
+                entry = browser::smart_entry(&self.library, &self.visible_indices(), smart, &stats);
             }
-            _ => {
-                self.next_track();
+        }
+        let entry_index = self.browse_filtered[self.selected_index];
+        if self.browser.enter(&entry, entry_index, &self.library) {
+            self.search_query.clear();
+            self.selected_index = 0;
+            self.update_filter();
+            if let EntryKind::Playlist(path) = &entry.kind {
+                self.warn_missing(path);
             }
+        } else if let Some(&track) = entry.tracks.first() {
+            self.play_track(track);
         }
     }
 
-    pub fn process_audio_events(&mut self) {
-        // Process all pending audio events
-        while let Ok(event) = self.event_rx.try_recv() {
-            match event {
-                AudioEvent::Playing { duration } => {
-                    if duration > 0.0 {
-                        self.duration = duration;
-                    }
-                }
-                AudioEvent::Progress(pos) => {
-                    self.progress = pos;
-                }
-                AudioEvent::TrackFinished => {
-                    self.handle_track_finished();
-                }
-                AudioEvent::Error(msg) => {
-                    self.error_message = Some(msg);
-                }
-                AudioEvent::AudioData(_) => {
-                    // Handled separately via sample_rx
-                }
-            }
+    /// Drop a visual range, then the marks, then a kept search, otherwise go
+    /// up one browser level, restoring the cursor where it was
+    pub fn browse_back(&mut self) {
+        if self.visual_anchor.take().is_some() {
+            return;
         }
-
-        // Process audio samples for visualizer
-        let mut latest_samples = None;
-        while let Ok(samples) = self.sample_rx.try_recv() {
-            latest_samples = Some(samples);
+        if !self.marks.is_empty() {
+            let count = self.marks.len();
+            self.marks.clear();
+            self.set_status(format!("Unmarked {} track(s)", count));
+            return;
         }
-        if let Some(samples) = latest_samples {
-            self.visualizer.process_samples(&samples);
-        } else if self.is_playing {
-            // Gentle decay when no new data
-        } else {
-            self.visualizer.decay();
+        // A kept search is dropped before leaving the level
+        if !self.search_query.is_empty() {
+            self.search_query.clear();
+            self.update_filter();
+            return;
+        }
+        if let Some(entry_index) = self.browser.back() {
+            self.search_query.clear();
+            self.update_filter();
+            self.selected_index = self
+                .browse_filtered
+                .iter()
+                .position(|&i| i == entry_index)
+                .unwrap_or(0);
         }
     }
 
-    pub fn current_track(&self) -> Option<&Track> {
-        self.playing_index.map(|i| &self.library[i])
+    /// Tracks the bulk actions apply to: the marked tracks, the selected
+    /// entry's tracks, or the whole visible track list
+    fn selection_tracks(&self) -> Vec<usize> {
+        let marked = self.marked_tracks();
+        if !marked.is_empty() {
+            marked
+        } else if self.browser.showing_tracks() {
+            self.filtered_indices.clone()
+        } else {
+            self.selected_entry()
+                .map(|e| e.tracks.clone())
+                .unwrap_or_default()
+        }
     }
 
-    // === New Feature Methods ===
+    pub fn play_all(&mut self) {
+        let tracks = self.selection_tracks();
+        self.play_list(tracks);
+    }
 
-    pub fn cycle_theme(&mut self) {
-        self.theme = self.theme.cycle();
+    pub fn shuffle_selection(&mut self) {
+        let tracks = self.selection_tracks();
+        let mode = if self.shuffle { self.shuffle_mode } else { self.preferred_shuffle };
+        let tracks = shuffle::order(mode, &tracks, &self.library, &self.play_counts, &self.ratings);
+        self.play_list(tracks);
     }
 
-    pub fn toggle_mini_mode(&mut self) {
-        self.mini_mode = !self.mini_mode;
+    /// The marked tracks, the single selected track in a track list, or the
+    /// selected entry's tracks
+    fn selected_tracks(&self) -> Vec<usize> {
+        let marked = self.marked_tracks();
+        if !marked.is_empty() {
+            marked
+        } else if self.browser.showing_tracks() {
+            self.filtered_indices
+                .get(self.selected_index)
+                .map(|&i| vec![i])
+                .unwrap_or_default()
+        } else {
+            self.selection_tracks()
+        }
     }
 
-    pub fn speed_up(&mut self) {
-        self.speed = self.speed.cycle_up();
-        let _ = self.cmd_tx.send(AudioCommand::SetSpeed(self.speed.as_f32()));
+    /// Queue the selected entry's tracks, or the single selected track
+    pub fn queue_selection(&mut self) {
+        let tracks = self.selected_tracks();
+        self.queue.add(&tracks);
+        self.set_status(format!("Added {} track(s) to the queue", tracks.len()));
     }
 
-    pub fn speed_down(&mut self) {
-        self.speed = self.speed.cycle_down();
-        let _ = self.cmd_tx.send(AudioCommand::SetSpeed(self.speed.as_f32()));
+    /// Put the selection at the front of the queue
+    pub fn play_selection_next(&mut self) {
+        let tracks = self.selected_tracks();
+        self.queue.play_next(&tracks);
+        self.set_status(format!("Playing {} track(s) next", tracks.len()));
     }
 
-    pub fn cycle_sleep_timer(&mut self) {
-        // Cycle through: Off -> 15min -> 30min -> 45min -> 60min -> Off
-        let new_duration = match &self.sleep_timer {
-            None => Some(15),
-            Some(t) => match t.duration_mins {
-                15 => Some(30),
-                30 => Some(45),
-                45 => Some(60),
-                _ => None,
-            },
+    /// Play the first track now and the rest after it, ahead of the queue
+    fn play_list(&mut self, tracks: Vec<usize>) {
+        let Some((&first, rest)) = tracks.split_first() else {
+            return;
         };
+        self.context = rest.to_vec();
+        self.play_track(first);
+        // Once the list is done, library order continues after its last track
+        self.playing_from_queue = true;
+        self.library_position = tracks.last().copied();
+    }
 
-        if let Some(mins) = new_duration {
-            let now = Instant::now();
-            let total_duration = Duration::from_secs(mins as u64 * 60);
-            let fade_duration = Duration::from_secs(60); // Last minute is fade
-            self.sleep_timer = Some(SleepTimer {
-                end_time: now + total_duration,
-                fade_start: now + total_duration - fade_duration,
-                original_volume: self.volume,
-                duration_mins: mins,
-            });
-        } else {
-            // Restore volume if we had a timer
-            if let Some(timer) = &self.sleep_timer {
-                self.volume = timer.original_volume;
-                let _ = self.cmd_tx.send(AudioCommand::SetVolume(self.volume));
-            }
-            self.sleep_timer = None;
+    // === Queue pane ===
+
+    pub fn toggle_queue(&mut self) {
+        self.show_queue = !self.show_queue;
+        self.show_chapters = false;
+        self.queue_cursor = self.queue_cursor.min(self.queue.len().saturating_sub(1));
+    }
+
+    pub fn queue_cursor_down(&mut self) {
+        if self.queue_cursor + 1 < self.queue.len() {
+            self.queue_cursor += 1;
         }
     }
 
-    pub fn update_sleep_timer(&mut self) {
-        if let Some(timer) = &self.sleep_timer {
-            let now = Instant::now();
+    pub fn queue_cursor_up(&mut self) {
+        self.queue_cursor = self.queue_cursor.saturating_sub(1);
+    }
 
-            if now >= timer.end_time {
-                // Timer expired - stop playback
+    /// Shift the entry under the cursor one place, keeping the cursor on it
+    pub fn queue_move_down(&mut self) {
+        if self.queue.move_to(self.queue_cursor, self.queue_cursor + 1) {
+            self.queue_cursor += 1;
+        }
+    }
+
+    pub fn queue_move_up(&mut self) {
+        if self.queue_cursor > 0 && self.queue.move_to(self.queue_cursor, self.queue_cursor - 1) {
+            self.queue_cursor -= 1;
+        }
+    }
+
+    pub fn queue_remove(&mut self, position: usize) {
+        self.queue.remove(position);
+        self.queue_cursor = self.queue_cursor.min(self.queue.len().saturating_sub(1));
+    }
+
+    pub fn queue_clear(&mut self) {
+        self.queue.clear();
+        self.queue_cursor = 0;
+    }
+
+    // === Playlists ===
+
+    /// Every playlist the Playlists view lists: those under the library
+    /// folder, then the ones saved in `~/.tunebox/playlists`
+    fn playlist_files(&self) -> Vec<PathBuf> {
+        let mut files = self.playlists.clone();
+        for saved in playlist::saved_playlists() {
+            if !files.contains(&saved) {
+                files.push(saved);
+            }
+        }
+        files
+    }
+
+    /// The Playlists view: smart playlists, then playlist files
+    fn playlist_entries(&self) -> Vec<BrowseEntry> {
+        let visible = self.visible_indices();
+        let stats = Stats::new(&self.play_counts, &self.ratings);
+        let smart = self
+            .smart_playlists
+            .iter()
+            .map(|p| browser::smart_entry(&self.library, &visible, p, &stats));
+        let files = self
+            .playlist_files()
+            .into_iter()
+            .map(|p| browser::playlist_entry(&self.library, &p));
+        smart.chain(files).collect()
+    }
+
+    /// Ask for the name of a playlist to save the queue (`from_queue`) or
+    /// the selection to
+    pub fn open_playlist_prompt(&mut self, from_queue: bool) {
+        let tracks = if from_queue {
+            self.queue.tracks().to_vec()
+        } else {
+            self.selection_tracks()
+        };
+        if tracks.is_empty() {
+            self.set_status("Nothing to save".to_string());
+            return;
+        }
+        self.playlist_prompt = Some(PlaylistPrompt {
+            name: String::new(),
+            tracks,
+        });
+    }
+
+    pub fn playlist_prompt_input(&mut self, c: char) {
+        if let Some(prompt) = self.playlist_prompt.as_mut() {
+            prompt.name.push(c);
+        }
+    }
+
+    pub fn playlist_prompt_backspace(&mut self) {
+        if let Some(prompt) = self.playlist_prompt.as_mut() {
+            prompt.name.pop();
+        }
+    }
+
+    /// Append the prompt's tracks to the playlist with the typed name, or
+    /// start one in `~/.tunebox/playlists`. A name ending in `.pls`,
+    /// `.xspf` or `.m3u` picks the format of a new playlist.
+    pub fn save_playlist(&mut self) {
+        let Some(prompt) = self.playlist_prompt.take() else {
+            return;
+        };
+        let name = prompt.name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if name.contains('/') {
+            self.error_message = Some("Playlist names can't contain '/'".to_string());
+            return;
+        }
+        let existing = self.playlist_files().into_iter().find(|p| {
+            let matches = |s: Option<&std::ffi::OsStr>| {
+                s.is_some_and(|s| s.to_string_lossy().eq_ignore_ascii_case(name))
+            };
+            matches(p.file_name()) || matches(p.file_stem())
+        });
+        let (mut list, created) = match existing {
+            Some(path) => match Playlist::load(&path) {
+                Ok(list) => (list, false),
+                Err(e) => {
+                    self.error_message = Some(format!("{:#}", e));
+                    return;
+                }
+            },
+            None => {
+                let Some(dir) = playlist::playlists_dir() else {
+                    return;
+                };
+                let file = match PlaylistFormat::from_path(Path::new(name)) {
+                    Some(_) => name.to_string(),
+                    None => format!("{}.{}", name, playlist::DEFAULT_EXTENSION),
+                };
+                (Playlist::new(dir.join(file)), true)
+            }
+        };
+        list.entries
+            .extend(prompt.tracks.iter().map(|&i| PlaylistEntry::from_track(&self.library[i])));
+        if let Err(e) = list.save() {
+            self.error_message = Some(format!("{:#}", e));
+            return;
+        }
+        let count = prompt.tracks.len();
+        self.set_status(if created {
+            format!("Saved {} track(s) to {}", count, list.path.display())
+        } else {
+            format!("Added {} track(s) to {}", count, list.name)
+        });
+        if self.browser.view == BrowseView::Playlists {
+            match self.browser.tracks.as_ref().and_then(|t| t.playlist.clone()) {
+                Some(open) => self.reload_playlist(&open),
+                None => self.refresh_view(),
+            }
+        }
+    }
+
+    /// Point out entries of a playlist just opened that aren't playable
+    fn warn_missing(&mut self, path: &Path) {
+        let Ok(list) = Playlist::load(path) else {
+            return;
+        };
+        let resolved = list.resolve(&self.library);
+        let missing: Vec<&PlaylistEntry> = list
+            .entries
+            .iter()
+            .zip(&resolved)
+            .filter(|(_, track)| track.is_none())
+            .map(|(entry, _)| entry)
+            .collect();
+        if let Some(first) = missing.first() {
+            self.set_status(format!(
+                "⚠ {} of {} entries not in the library, e.g. {}",
+                missing.len(),
+                list.entries.len(),
+                first.path.display()
+            ));
+        }
+    }
+
+    /// The open playlist and, for each row of its track list, the entry it
+    /// shows. Rows skip entries that aren't in the library.
+    fn open_playlist(&mut self) -> Option<(Playlist, Vec<usize>)> {
+        let path = self.browser.tracks.as_ref()?.playlist.clone()?;
+        if !self.search_query.is_empty() {
+            self.set_status("Clear the search to edit the playlist".to_string());
+            return None;
+        }
+        let list = match Playlist::load(&path) {
+            Ok(list) => list,
+            Err(e) => {
+                self.error_message = Some(format!("{:#}", e));
+                return None;
+            }
+        };
+        let rows = list
+            .resolve(&self.library)
+            .iter()
+            .enumerate()
+            .filter_map(|(entry, track)| track.map(|_| entry))
+            .collect();
+        Some((list, rows))
+    }
+
+    /// Drop the row under the cursor from the open playlist, or delete the
+    /// smart playlist under the cursor
+    pub fn playlist_remove(&mut self) {
+        if let Some(EntryKind::Smart(name)) = self.selected_entry().map(|e| e.kind.clone()) {
+            self.delete_smart_playlist(&name);
+            return;
+        }
+        let Some((mut list, rows)) = self.open_playlist() else {
+            return;
+        };
+        let Some(&entry) = rows.get(self.selected_index) else {
+            return;
+        };
+        list.entries.remove(entry);
+        self.write_playlist(&list);
+    }
+
+    /// Swap the row under the cursor with the one below (or above), keeping
+    /// the cursor on it
+    pub fn playlist_move(&mut self, down: bool) {
+        let Some((mut list, rows)) = self.open_playlist() else {
+            return;
+        };
+        let target = if down {
+            self.selected_index + 1
+        } else {
+            match self.selected_index.checked_sub(1) {
+                Some(target) => target,
+                None => return,
+            }
+        };
+        let (Some(&from), Some(&to)) = (rows.get(self.selected_index), rows.get(target)) else {
+            return;
+        };
+        list.entries.swap(from, to);
+        self.selected_index = target;
+        self.write_playlist(&list);
+    }
+
+    fn write_playlist(&mut self, list: &Playlist) {
+        if let Err(e) = list.save() {
+            self.error_message = Some(format!("{:#}", e));
+        }
+        self.reload_playlist(&list.path);
+    }
+
+    /// Re-read a playlist after it changed, in its listing and, when it's
+    /// open, its track list
+    fn reload_playlist(&mut self, path: &Path) {
+        let entry = browser::playlist_entry(&self.library, path);
+        if let Some(level) = self.browser.stack.last_mut() {
+            match level.entries.iter_mut().find(|e| e.kind == entry.kind) {
+                Some(listed) => *listed = entry.clone(),
+                None => level.entries.push(entry.clone()),
+            }
+        }
+        if let Some(scope) = self.browser.tracks.as_mut() {
+            if scope.playlist.as_deref() == Some(path) {
+                scope.indices = entry.tracks;
+            }
+        }
+        self.update_filter();
+    }
+
+    // === Smart playlists ===
+
+    /// Ask for a new smart playlist as `name = rules`, or to change the one
+    /// under the cursor
+    pub fn open_smart_prompt(&mut self) {
+        let text = match self.selected_entry().map(|e| &e.kind) {
+            Some(EntryKind::Smart(name)) => self
+                .smart_playlists
+                .iter()
+                .find(|p| &p.name == name)
+                .map(|p| format!("{} = {}", p.name, p.query))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        self.smart_prompt = Some(text);
+    }
+
+    pub fn smart_prompt_input(&mut self, c: char) {
+        if let Some(text) = self.smart_prompt.as_mut() {
+            text.push(c);
+        }
+    }
+
+    pub fn smart_prompt_backspace(&mut self) {
+        if let Some(text) = self.smart_prompt.as_mut() {
+            text.pop();
+        }
+    }
+
+    /// Create or replace the smart playlist typed into the prompt. The
+    /// prompt stays open when the rules don't parse.
+    pub fn save_smart_playlist(&mut self) {
+        let Some(text) = self.smart_prompt.take() else {
+            return;
+        };
+        let (name, query) = match text.split_once('=') {
+            Some((name, query)) if !name.trim().is_empty() && !query.trim().is_empty() => {
+                (name.trim().to_string(), query.trim().to_string())
+            }
+            _ => {
+                self.error_message = Some("Write it as: name = rules".to_string());
+                self.smart_prompt = Some(text);
+                return;
+            }
+        };
+        if let Err(e) = Query::parse(&query) {
+            self.error_message = Some(format!("{:#}", e));
+            self.smart_prompt = Some(text);
+            return;
+        }
+        self.error_message = None;
+        let existing = self.smart_playlists.iter_mut().find(|p| p.name == name);
+        match existing {
+            Some(p) if p.from_config => {
+                self.error_message = Some(format!("'{}' is defined in config.json", name));
+                return;
+            }
+            Some(p) => {
+                p.query = query;
+                self.set_status(format!("Updated smart playlist {}", name));
+            }
+            None => {
+                self.smart_playlists.push(SmartPlaylist {
+                    name: name.clone(),
+                    query,
+                    from_config: false,
+                });
+                self.set_status(format!("Created smart playlist {}", name));
+            }
+        }
+        if let Err(e) = smart::save(&self.smart_playlists) {
+            self.error_message = Some(format!("{:#}", e));
+        }
+        if self.browser.view == BrowseView::Playlists && self.browser.tracks.is_none() {
+            self.refresh_view();
+        }
+    }
+
+    fn delete_smart_playlist(&mut self, name: &str) {
+        let Some(pos) = self.smart_playlists.iter().position(|p| p.name == name) else {
+            return;
+        };
+        if self.smart_playlists[pos].from_config {
+            self.set_status(format!("'{}' is defined in config.json", name));
+            return;
+        }
+        self.smart_playlists.remove(pos);
+        if let Err(e) = smart::save(&self.smart_playlists) {
+            self.error_message = Some(format!("{:#}", e));
+        }
+        self.set_status(format!("Deleted smart playlist {}", name));
+        self.refresh_view();
+    }
+
+    // === Marks ===
+
+    /// Rows of the track list covered by the visual range, as display positions
+    pub fn visual_span(&self) -> Option<(usize, usize)> {
+        let anchor = self.visual_anchor?;
+        if !self.browser.showing_tracks() {
+            return None;
+        }
+        // The anchor may have been filtered out; the range then starts at the cursor
+        let start = self
+            .filtered_indices
+            .iter()
+            .position(|&i| i == anchor)
+            .unwrap_or(self.selected_index);
+        Some((start.min(self.selected_index), start.max(self.selected_index)))
+    }
+
+    fn visual_tracks(&self) -> Vec<usize> {
+        match self.visual_span() {
+            Some((start, end)) => self
+                .filtered_indices
+                .get(start..=end.min(self.filtered_indices.len().saturating_sub(1)))
+                .map(<[usize]>::to_vec)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Marked tracks plus the visual range: those on screen in list order,
+    /// then any hidden by the search or in other levels, in library order
+    pub fn marked_tracks(&self) -> Vec<usize> {
+        let range = self.visual_tracks();
+        if self.marks.is_empty() && range.is_empty() {
+            return Vec::new();
+        }
+        let shown: Vec<usize> = if self.browser.showing_tracks() {
+            self.filtered_indices.clone()
+        } else {
+            Vec::new()
+        };
+        let mut tracks: Vec<usize> = shown
+            .iter()
+            .copied()
+            .filter(|i| self.marks.contains(i) || range.contains(i))
+            .collect();
+        let on_screen: HashSet<usize> = tracks.iter().copied().collect();
+        let mut hidden: Vec<usize> = self
+            .marks
+            .iter()
+            .copied()
+            .filter(|i| !on_screen.contains(i))
+            .collect();
+        hidden.sort_unstable();
+        tracks.extend(hidden);
+        tracks
+    }
+
+    /// Toggle the mark on the track under the cursor and step down; in visual
+    /// mode, mark the range (or unmark it when it's all marked already)
+    pub fn toggle_mark(&mut self) {
+        if self.visual_anchor.is_some() {
+            let range = self.visual_tracks();
+            self.visual_anchor = None;
+            if range.iter().all(|i| self.marks.contains(i)) {
+                for i in &range {
+                    self.marks.remove(i);
+                }
+            } else {
+                self.marks.extend(range);
+            }
+            return;
+        }
+        if !self.browser.showing_tracks() {
+            // On a group, mark or unmark all of its tracks
+            let Some(entry) = self.selected_entry() else {
+                return;
+            };
+            let tracks = entry.tracks.clone();
+            if tracks.iter().all(|i| self.marks.contains(i)) {
+                for i in &tracks {
+                    self.marks.remove(i);
+                }
+            } else {
+                self.marks.extend(tracks);
+            }
+        } else if let Some(&i) = self.filtered_indices.get(self.selected_index) {
+            if !self.marks.remove(&i) {
+                self.marks.insert(i);
+            }
+        }
+        self.move_selection_down();
+    }
+
+    /// Start a visual range at the cursor, or fix the current one as marks
+    pub fn toggle_visual(&mut self) {
+        if self.visual_anchor.is_some() {
+            let range = self.visual_tracks();
+            self.marks.extend(range);
+            self.visual_anchor = None;
+        } else if !self.browser.showing_tracks() {
+            self.set_status("Open a track list for range selection".to_string());
+        } else if let Some(&i) = self.filtered_indices.get(self.selected_index) {
+            self.visual_anchor = Some(i);
+        }
+    }
+
+    /// Mark everything the search matches in the current list
+    pub fn mark_matching(&mut self) {
+        let before = self.marks.len();
+        if self.browser.showing_tracks() {
+            self.marks.extend(self.filtered_indices.iter().copied());
+        } else if let Some(level) = self.browser.current_level() {
+            for &e in &self.browse_filtered {
+                self.marks.extend(level.entries[e].tracks.iter().copied());
+            }
+        }
+        self.visual_anchor = None;
+        self.set_status(format!(
+            "Marked {} more track(s), {} in all",
+            self.marks.len() - before,
+            self.marks.len()
+        ));
+    }
+
+    /// Copy the selection's paths to the clipboard, one per line
+    pub fn copy_paths(&mut self) {
+        let tracks = self.selected_tracks();
+        if tracks.is_empty() {
+            return;
+        }
+        let mut paths: Vec<String> = tracks
+            .iter()
+            .map(|&i| self.library[i].path.display().to_string())
+            .collect();
+        // CUE tracks share their audio file
+        paths.dedup();
+        self.set_status(match desktop::copy(&paths.join("\n")) {
+            Ok(()) => format!("Copied {} path(s)", paths.len()),
+            Err(e) => format!("Copy failed: {}", e),
+        });
+    }
+
+    /// Open the folders holding the selection in the file manager
+    pub fn reveal_selection(&mut self) {
+        let tracks = self.selected_tracks();
+        let paths: Vec<PathBuf> = tracks.iter().map(|&i| self.library[i].path.clone()).collect();
+        if paths.is_empty() {
+            return;
+        }
+        self.set_status(match desktop::reveal(&paths) {
+            Ok(opened) => format!("Opened {} folder(s)", opened),
+            Err(e) => format!("{:#}", e),
+        });
+    }
+
+    // === Columns ===
+
+    /// The column sort, unless the list keeps its own order: playlists and
+    /// queries with `sort:`
+    pub fn applied_sort(&self) -> Option<SortColumn> {
+        let ordered = self.browser.tracks.as_ref().is_some_and(|t| t.ordered);
+        self.column_sort
+            .filter(|_| !ordered && !self.search_filter.has_sort())
+    }
+
+    /// Sort by `column`: ascending, then descending, then back to unsorted
+    pub fn sort_by_column(&mut self, column: &'static Column) {
+        self.column_sort = SortColumn::cycle(self.column_sort, column);
+        // Keep the cursor on the same track
+        let selected = self.filtered_indices.get(self.selected_index).copied();
+        self.update_filter();
+        if let Some(selected) = selected.filter(|_| self.browser.showing_tracks()) {
+            if let Some(pos) = self.filtered_indices.iter().position(|&i| i == selected) {
+                self.selected_index = pos;
+            }
+        }
+        self.save_columns();
+    }
+
+    /// Every column for the editor: those shown, in order, then the rest
+    pub fn column_editor_rows(&self) -> Vec<(&'static Column, bool)> {
+        let shown = self.columns.iter().map(|&c| (c, true));
+        let hidden = columns::ALL
+            .iter()
+            .filter(|c| !self.columns.contains(c))
+            .map(|c| (c, false));
+        shown.chain(hidden).collect()
+    }
+
+    pub fn open_column_editor(&mut self) {
+        self.column_editor = Some(0);
+    }
+
+    pub fn close_column_editor(&mut self) {
+        self.column_editor = None;
+    }
+
+    pub fn column_editor_move(&mut self, down: bool) {
+        let rows = columns::ALL.len();
+        if let Some(cursor) = &mut self.column_editor {
+            *cursor = if down {
+                (*cursor + 1).min(rows - 1)
+            } else {
+                cursor.saturating_sub(1)
+            };
+        }
+    }
+
+    fn column_editor_selected(&self) -> Option<&'static Column> {
+        let cursor = self.column_editor?;
+        self.column_editor_rows().get(cursor).map(|&(c, _)| c)
+    }
+
+    /// Point the editor's cursor at `column` after the rows moved
+    fn column_editor_follow(&mut self, column: &'static Column) {
+        self.column_editor = self
+            .column_editor_rows()
+            .iter()
+            .position(|&(c, _)| c == column);
+    }
+
+    /// Show or hide the column under the editor's cursor
+    pub fn column_editor_toggle(&mut self) {
+        let Some(column) = self.column_editor_selected() else {
+            return;
+        };
+        if let Some(pos) = self.columns.iter().position(|&c| c == column) {
+            if self.columns.len() == 1 {
+                self.set_status("The track list needs a column".to_string());
+                return;
+            }
+            self.columns.remove(pos);
+        } else {
+            self.columns.push(column);
+        }
+        self.column_editor_follow(column);
+        self.save_columns();
+    }
+
+    /// Move the shown column under the editor's cursor left or right
+    pub fn column_editor_reorder(&mut self, later: bool) {
+        let Some(column) = self.column_editor_selected() else {
+            return;
+        };
+        let Some(pos) = self.columns.iter().position(|&c| c == column) else {
+            return;
+        };
+        let to = if later { pos + 1 } else { pos.wrapping_sub(1) };
+        if to < self.columns.len() {
+            self.columns.swap(pos, to);
+            self.column_editor_follow(column);
+            self.save_columns();
+        }
+    }
+
+    pub fn column_editor_sort(&mut self) {
+        if let Some(column) = self.column_editor_selected() {
+            self.sort_by_column(column);
+        }
+    }
+
+    /// Write the column layout and sort to the config file
+    fn save_columns(&mut self) {
+        let names: Vec<&str> = self.columns.iter().map(|c| c.name).collect();
+        let sort = self.column_sort.map(SortColumn::to_config).unwrap_or_default();
+        let saved = config::update("columns", serde_json::json!(names))
+            .and_then(|()| config::update("column_sort", serde_json::json!(sort)));
+        if let Err(e) = saved {
+            self.set_status(format!("Couldn't save columns: {:#}", e));
+        }
+    }
+
+    // === Ratings ===
+
+    /// Give the selection `stars` (0 clears the rating)
+    pub fn rate_selection(&mut self, stars: u8) {
+        let tracks = self.selected_tracks();
+        if tracks.is_empty() {
+            return;
+        }
+        for &i in &tracks {
+            self.ratings.set(&self.library[i], stars);
+        }
+        self.ratings.save();
+        self.set_status(if stars == 0 {
+            format!("Cleared the rating of {} track(s)", tracks.len())
+        } else {
+            format!("Rated {} track(s) {}", tracks.len(), ratings::stars(stars))
+        });
+    }
+
+    // === Chapter pane ===
+
+    /// Index of the chapter playing, when the file has chapters
+    pub fn current_chapter(&self) -> Option<usize> {
+        chapters::chapter_at(&self.chapters, self.progress)
+    }
+
+    /// Open or close the chapter list, with the cursor on the chapter playing
+    pub fn toggle_chapters(&mut self) {
+        self.show_chapters = !self.show_chapters;
+        self.show_queue = false;
+        if self.show_chapters && self.chapters.is_empty() {
+            self.set_status("No chapters in this file".to_string());
+        }
+        self.chapter_cursor = self.current_chapter().unwrap_or(0);
+    }
+
+    pub fn chapter_cursor_down(&mut self) {
+        if self.chapter_cursor + 1 < self.chapters.len() {
+            self.chapter_cursor += 1;
+        }
+    }
+
+    pub fn chapter_cursor_up(&mut self) {
+        self.chapter_cursor = self.chapter_cursor.saturating_sub(1);
+    }
+
+    pub fn play_chapter(&mut self, index: usize) {
+        if let Some(chapter) = self.chapters.get(index) {
+            self.seek_to(chapter.start);
+        }
+    }
+
+    /// Play the entry under the cursor now, dropping the entries before it
+    pub fn queue_play(&mut self, position: usize) {
+        if position >= self.queue.len() {
+            return;
+        }
+        let rest = self.queue.tracks()[position + 1..].to_vec();
+        let track = self.queue.tracks()[position];
+        if !self.playing_from_queue {
+            self.library_position = self.playing_index;
+        }
+        self.queue.set(rest);
+        self.play_track(track);
+        self.playing_from_queue = true;
+        self.queue_cursor = 0;
+    }
+
+    // === Tag editing ===
+
+    /// Edit the track shown in the info panel, otherwise the selection: the
+    /// selected track in a track list, or every track of the selected entry
+    pub fn open_tag_editor(&mut self) {
+        let tracks = if self.show_info {
+            self.playing_index.into_iter().collect()
+        } else {
+            self.selected_tracks()
+        };
+        if tracks.iter().any(|&i| self.library[i].cue.is_some()) {
+            self.set_status("CUE sheet tracks take their tags from the .cue file".to_string());
+            return;
+        }
+        if !tracks.is_empty() {
+            self.tag_editor = Some(TagEditor::new(&self.library, tracks));
+        }
+    }
+
+    /// Write the form's changes to every track in it, keeping the old values
+    /// for undo
+    pub fn save_tag_editor(&mut self) {
+        let Some(editor) = self.tag_editor.as_ref() else {
+            return;
+        };
+        let edit = editor.edit();
+        if let Err(e) = edit.validate() {
+            self.error_message = Some(e.to_string());
+            return;
+        }
+        let tracks = editor.tracks.clone();
+        self.tag_editor = None;
+        if edit.is_empty() {
+            return;
+        }
+
+        let mut undo = Vec::new();
+        let mut failed = 0;
+        for &i in &tracks {
+            let path = &self.library[i].path;
+            let previous = metadata::read_metadata(path).unwrap_or_default();
+            match metadata::write_tags(path, &edit) {
+                Ok(()) => {
+                    undo.push((i, edit.undo_for(&previous)));
+                    self.library[i].apply_edit(&edit);
+                }
+                Err(e) => {
+                    failed += 1;
+                    self.error_message = Some(e.to_string());
+                }
+            }
+        }
+        let saved = undo.len();
+        self.last_tag_edit = undo;
+        self.after_tag_write(&tracks);
+        self.set_status(match failed {
+            0 => format!("Saved tags for {} track(s) · u to undo", saved),
+            _ => format!("Saved tags for {} track(s), {} failed", saved, failed),
+        });
+    }
+
+    /// Put back the tags the last saved edit replaced
+    pub fn undo_tag_edit(&mut self) {
+        let batch = std::mem::take(&mut self.last_tag_edit);
+        if batch.is_empty() {
+            return;
+        }
+        let mut restored = Vec::new();
+        for (i, edit) in batch {
+            match metadata::write_tags(&self.library[i].path, &edit) {
+                Ok(()) => {
+                    self.library[i].apply_edit(&edit);
+                    restored.push(i);
+                }
+                Err(e) => self.error_message = Some(e.to_string()),
+            }
+        }
+        self.after_tag_write(&restored);
+        self.set_status(format!("Restored tags for {} track(s)", restored.len()));
+    }
+
+    /// Persist edited tracks to the library cache and rebuild whatever was
+    /// grouped by the old tags
+    fn after_tag_write(&mut self, tracks: &[usize]) {
+        let edited: Vec<&Track> = tracks.iter().map(|&i| &self.library[i]).collect();
+        library::update_cache(&edited);
+        self.duplicate_copies =
+            duplicates::lesser_copies(&duplicates::find_duplicates(&self.library, MatchMode::Tags));
+        self.refresh_view();
+    }
+
+    /// Rebuild the current view after tracks changed under it
+    fn refresh_view(&mut self) {
+        if self.browser.view == BrowseView::Tracks {
+            self.update_filter();
+        } else {
+            // Groupings may have changed; reopen the view at its root
+            let selected = self.selected_index;
+            self.set_view(self.browser.view);
+            self.selected_index = selected.min(self.visible_len().saturating_sub(1));
+        }
+    }
+
+    // === Organizing ===
+
+    /// First press: plan moving the selection into the template layout and
+    /// ask for confirmation. Second press: carry it out.
+    pub fn organize_selection(&mut self) {
+        if let Some(plan) = self.pending_organize.take() {
+            self.run_organize(&plan);
+            return;
+        }
+        let Some(root) = self.library_root.clone() else {
+            self.set_status("Organizing needs a library folder".to_string());
+            return;
+        };
+        let template = match Template::parse(&self.organize_template) {
+            Ok(t) => t,
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+                return;
+            }
+        };
+        // CUE-split files are left out by the plan; they stay by their sheet
+        let tracks = self.selected_tracks();
+        let plan = organize::plan(&self.library, &tracks, &root, &template, &self.cover_files);
+        if plan.moves.is_empty() {
+            self.set_status(format!("{} track(s) already in place", plan.unchanged));
+            return;
+        }
+        self.set_status(format!(
+            "Move {} file(s) to {}? Press O again to confirm",
+            plan.moves.len(),
+            plan.template
+        ));
+        self.pending_organize = Some(plan);
+    }
+
+    fn run_organize(&mut self, plan: &organize::Plan) {
+        let outcome = organize::execute(plan);
+        let mut moved = Vec::new();
+        for (i, from, to) in outcome.moved {
+            self.library[i].path = to.clone();
+            moved.push((from, to));
+        }
+        library::rename_in_cache(&moved);
+        self.ratings.rename(&moved);
+        self.ratings.save();
+        self.play_counts.rename(&moved);
+        let playlists: Vec<PathBuf> = self
+            .playlists
+            .iter()
+            .cloned()
+            .chain(playlist::saved_playlists())
+            .collect();
+        if let Err(e) = playlist::rename_entries(&playlists, &moved) {
+            self.error_message = Some(format!("{:#}", e));
+        }
+        self.refresh_view();
+        match outcome.errors.first() {
+            Some((file, error)) => {
+                self.error_message = Some(format!("{}: {}", file.display(), error));
+                self.set_status(format!(
+                    "Moved {} file(s), {} failed",
+                    moved.len(),
+                    outcome.errors.len()
+                ));
+            }
+            None => self.set_status(format!("Moved {} file(s)", moved.len())),
+        }
+    }
+
+    // === Cover art ===
+
+    pub fn open_cover_prompt(&mut self) {
+        self.cover_prompt = true;
+        self.set_status(
+            "Cover: e embed folder image · x extract to cover.jpg · s strip oversized art · Esc cancel"
+                .to_string(),
+        );
+    }
+
+    /// Run the cover action picked after `C` on the selection
+    pub fn cover_action(&mut self, action: char) {
+        self.cover_prompt = false;
+        self.status_message = None;
+        let mut tracks = self.selected_tracks();
+        // CUE tracks of one file only need the file changed once
+        tracks.dedup_by(|a, b| self.library[*a].path == self.library[*b].path);
+        if tracks.is_empty() {
+            return;
+        }
+        match action {
+            'e' => self.embed_folder_cover(&tracks),
+            'x' => self.extract_covers(&tracks),
+            's' => self.strip_covers(&tracks),
+            _ => {}
+        }
+    }
+
+    /// Embed the sidecar image from the first track's folder into every track
+    fn embed_folder_cover(&mut self, tracks: &[usize]) {
+        let first = &self.library[tracks[0]].path;
+        let dir = first.parent().unwrap_or(first);
+        let Some(image) = cover::find_sidecar(dir, &self.cover_files) else {
+            self.set_status(format!("No cover image in {}", dir.display()));
+            return;
+        };
+        let picture = match std::fs::read(&image)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| metadata::cover_picture(&data))
+        {
+            Ok(picture) => picture,
+            Err(e) => {
+                self.error_message = Some(format!("{}: {:#}", image.display(), e));
+                return;
+            }
+        };
+        let mut embedded = 0;
+        for &i in tracks {
+            match metadata::embed_cover(&self.library[i].path, &picture) {
+                Ok(()) => embedded += 1,
+                Err(e) => self.error_message = Some(format!("{:#}", e)),
+            }
+        }
+        self.refresh_art(tracks);
+        let name = image.file_name().unwrap_or_default().to_string_lossy();
+        self.set_status(format!("Embedded {} into {} track(s)", name, embedded));
+    }
+
+    /// Write `cover.jpg` into each folder of the selection that lacks one
+    fn extract_covers(&mut self, tracks: &[usize]) {
+        let mut folders: Vec<(PathBuf, Vec<&Path>)> = Vec::new();
+        for &i in tracks {
+            let path = self.library[i].path.as_path();
+            let Some(dir) = path.parent() else {
+                continue;
+            };
+            match folders.iter_mut().find(|(d, _)| d == dir) {
+                Some((_, files)) => files.push(path),
+                None => folders.push((dir.to_path_buf(), vec![path])),
+            }
+        }
+        let mut written = 0;
+        let mut error = None;
+        for (dir, files) in &folders {
+            if dir.join(cover::EXTRACTED_COVER).exists() {
+                continue;
+            }
+            match cover::extract_cover(files, false) {
+                Ok(Some(_)) => written += 1,
+                Ok(None) => {}
+                Err(e) => error = Some(format!("{:#}", e)),
+            }
+        }
+        if error.is_some() {
+            self.error_message = error;
+        }
+        self.set_status(format!("Extracted {} cover(s)", written));
+    }
+
+    fn strip_covers(&mut self, tracks: &[usize]) {
+        let max_bytes = self.max_cover_kb as usize * 1024;
+        let mut removed = 0;
+        for &i in tracks {
+            match metadata::strip_large_pictures(&self.library[i].path, max_bytes) {
+                Ok(n) => removed += n,
+                Err(e) => self.error_message = Some(format!("{:#}", e)),
+            }
+        }
+        self.refresh_art(tracks);
+        self.set_status(format!(
+            "Removed {} picture(s) over {} KB",
+            removed, self.max_cover_kb
+        ));
+    }
+
+    /// Forget cached art for the albums of `tracks` so the new cover shows
+    fn refresh_art(&mut self, tracks: &[usize]) {
+        let keys: HashSet<String> = tracks.iter().map(|&i| self.library[i].album_key()).collect();
+        for key in keys {
+            self.art.invalidate(&key);
+        }
+        if let Some(i) = self.playing_index {
+            let track = &self.library[i];
+            self.art.request(&track.album_key(), &track.path, ArtSize::Full);
+        }
+    }
+
+    pub fn set_status(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    pub fn toggle_search(&mut self) {
+        self.search_mode = !self.search_mode;
+        if !self.search_mode {
+            self.search_query.clear();
+            self.update_filter();
+        }
+    }
+
+    /// Leave search input but keep the results filtered, so shuffle and
+    /// play-all work on what matched
+    pub fn finish_search(&mut self) {
+        self.search_mode = false;
+    }
+
+    pub fn search_input(&mut self, c: char) {
+        self.search_query.push(c);
+        self.update_filter();
+    }
+
+    pub fn search_backspace(&mut self) {
+        self.search_query.pop();
+        self.update_filter();
+    }
+
+    /// Apply the search to the current scope. Track lists come out best
+    /// match first; a query that doesn't parse yet leaves the last one in force.
+    fn update_filter(&mut self) {
+        match Query::parse(&self.search_query) {
+            Ok(query) => {
+                self.search_filter = query;
+                self.search_error = None;
+            }
+            Err(e) => self.search_error = Some(format!("{:#}", e)),
+        }
+        let scope: Vec<usize> = match self.browser.track_scope() {
+            Some(indices) => indices.to_vec(),
+            None => self.visible_indices(),
+        };
+        let stats = Stats::new(&self.play_counts, &self.ratings);
+        self.filtered_indices = self.search_filter.run(&self.library, &scope, &stats);
+        if let Some(sort) = self.applied_sort() {
+            query::sort_by_field(
+                sort.column.name,
+                sort.descending,
+                &self.library,
+                &mut self.filtered_indices,
+                &stats,
+            );
+        }
+
+        self.browse_filtered = match self.browser.current_level() {
+            Some(level) => level
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| {
+                    let text = format!("{} {}", e.label, e.detail);
+                    self.search_filter
+                        .matches_entry(&text, &e.tracks, &self.library, &stats)
+                })
+                .map(|(i, _)| i)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if self.selected_index >= self.visible_len() {
+            self.selected_index = self.visible_len().saturating_sub(1);
+        }
+    }
+
+    /// Shuffle what the search matches, or the whole library without one
+    fn regenerate_shuffle(&mut self) {
+        let pool = if self.search_query.is_empty() || self.filtered_indices.is_empty() {
+            self.visible_indices()
+        } else {
+            self.filtered_indices.clone()
+        };
+        self.shuffle_query = self.search_query.clone();
+        self.shuffle_order = shuffle::order(
+            self.shuffle_mode,
+            &pool,
+            &self.library,
+            &self.play_counts,
+            &self.ratings,
+        );
+    }
+
+    fn get_shuffle_next(&mut self, position: Option<usize>) -> usize {
+        if self.shuffle_order.is_empty() || self.shuffle_query != self.search_query {
+            self.regenerate_shuffle();
+        }
+
+        if let Some(current) = position {
+            if let Some(pos) = self.shuffle_order.iter().position(|&x| x == current) {
+                let next_pos = pos + 1;
+                if next_pos >= self.shuffle_order.len() {
+                    match self.repeat {
+                        RepeatMode::All => {
+                            self.regenerate_shuffle();
+                            self.shuffle_order[0]
+                        }
+                        RepeatMode::Off => current, // stay
+                        RepeatMode::One => current,
+                    }
+                } else {
+                    self.shuffle_order[next_pos]
+                }
+            } else {
+                self.shuffle_order[0]
+            }
+        } else {
+            self.shuffle_order[0]
+        }
+    }
+
+    pub fn handle_track_finished(&mut self) {
+        // Played to the end, so a long file is remembered as finished
+        if let Some(index) = self.playing_index {
+            self.progress = self.library[index].duration;
+        }
+        match self.repeat {
+            RepeatMode::One => {
+                if let Some(idx) = self.playing_index {
+                    self.play_track(idx);
+                }
+            }
+            _ => {
+                self.next_track();
+            }
+        }
+    }
+
+    pub fn process_audio_events(&mut self) {
+        // Process all pending audio events
+        while let Ok(event) = self.event_rx.try_recv() {
+            match event {
+                AudioEvent::Playing { duration } => {
+                    if duration > 0.0 {
+                        self.duration = duration;
+                    }
+                }
+                AudioEvent::Progress(pos) => {
+                    self.progress = pos;
+                }
+                AudioEvent::TrackFinished => {
+                    self.handle_track_finished();
+                }
+                AudioEvent::Error(msg) => {
+                    self.error_message = Some(msg);
+                }
+                AudioEvent::AudioData(_) => {
+                    // Handled separately via sample_rx
+                }
+            }
+        }
+
+        // Process audio samples for visualizer
+        let mut latest_samples = None;
+        while let Ok(samples) = self.sample_rx.try_recv() {
+            latest_samples = Some(samples);
+        }
+        if let Some(samples) = latest_samples {
+            self.visualizer.process_samples(&samples);
+        } else if self.is_playing {
+            // Gentle decay when no new data
+        } else {
+            self.visualizer.decay();
+        }
+    }
+
+    pub fn current_track(&self) -> Option<&Track> {
+        self.playing_index.map(|i| &self.library[i])
+    }
+
+    // === New Feature Methods ===
+
+    pub fn cycle_theme(&mut self) {
+        self.theme = self.theme.cycle();
+    }
+
+    pub fn toggle_mini_mode(&mut self) {
+        self.mini_mode = !self.mini_mode;
+    }
+
+    pub fn speed_up(&mut self) {
+        self.speed = self.speed.cycle_up();
+        let _ = self.cmd_tx.send(AudioCommand::SetSpeed(self.speed.as_f32()));
+    }
+
+    pub fn speed_down(&mut self) {
+        self.speed = self.speed.cycle_down();
+        let _ = self.cmd_tx.send(AudioCommand::SetSpeed(self.speed.as_f32()));
+    }
+
+    pub fn cycle_sleep_timer(&mut self) {
+        // Cycle through: Off -> 15min -> 30min -> 45min -> 60min -> Off
+        let new_duration = match &self.sleep_timer {
+            None => Some(15),
+            Some(t) => match t.duration_mins {
+                15 => Some(30),
+                30 => Some(45),
+                45 => Some(60),
+                _ => None,
+            },
+        };
+
+        if let Some(mins) = new_duration {
+            let now = Instant::now();
+            let total_duration = Duration::from_secs(mins as u64 * 60);
+            let fade_duration = Duration::from_secs(60); // Last minute is fade
+            self.sleep_timer = Some(SleepTimer {
+                end_time: now + total_duration,
+                fade_start: now + total_duration - fade_duration,
+                original_volume: self.volume,
+                duration_mins: mins,
+            });
+        } else {
+            // Restore volume if we had a timer
+            if let Some(timer) = &self.sleep_timer {
+                self.volume = timer.original_volume;
+                let _ = self.cmd_tx.send(AudioCommand::SetVolume(self.volume));
+            }
+            self.sleep_timer = None;
+        }
+    }
+
+    pub fn update_sleep_timer(&mut self) {
+        if let Some(timer) = &self.sleep_timer {
+            let now = Instant::now();
+
+            if now >= timer.end_time {
+                // Timer expired - stop playback
                 let _ = self.cmd_tx.send(AudioCommand::Pause);
                 self.is_playing = false;
                 self.volume = timer.original_volume;
@@ -593,6 +2227,63 @@ impl App {
         })
     }
 
+    /// Snapshot of the player for `--resume`
+    pub fn session(&self) -> Session {
+        Session {
+            track: self.current_track().map(|t| t.key()),
+            position: self.progress,
+            volume: self.volume,
+            speed: self.speed,
+            repeat: self.repeat,
+            shuffle: self.shuffle,
+            shuffle_mode: self.shuffle_mode,
+            theme: self.theme,
+            visualizer: self.visualizer.mode,
+            hide_duplicates: self.hide_duplicates,
+            show_info: self.show_info,
+            show_queue: self.show_queue,
+            mini_mode: self.mini_mode,
+            view: self.browser.view,
+            search_query: self.search_query.clone(),
+            selected: self.selected_index,
+        }
+    }
+
+    /// Put back a saved session. The saved track is loaded paused at its
+    /// position; it's skipped if the file has left the library.
+    pub fn restore_session(&mut self, session: &Session) {
+        self.volume = session.volume.clamp(0.0, 1.0);
+        self.speed = session.speed;
+        self.repeat = session.repeat;
+        self.shuffle = session.shuffle;
+        self.shuffle_mode = session.shuffle_mode;
+        self.shuffle_order.clear();
+        self.theme = session.theme;
+        self.visualizer.mode = session.visualizer;
+        self.hide_duplicates = session.hide_duplicates;
+        self.show_info = session.show_info;
+        self.show_queue = session.show_queue;
+        self.mini_mode = session.mini_mode;
+
+        self.set_view(session.view);
+        self.search_query = session.search_query.clone();
+        self.update_filter();
+        self.selected_index = session.selected.min(self.visible_len().saturating_sub(1));
+
+        let index = session
+            .track
+            .as_ref()
+            .and_then(|key| self.library.iter().position(|t| &t.key() == key));
+        if let Some(index) = index {
+            self.start_track(index);
+            let position = session.position.clamp(0.0, self.duration.max(0.0));
+            let _ = self.cmd_tx.send(AudioCommand::Seek(position));
+            let _ = self.cmd_tx.send(AudioCommand::Pause);
+            self.progress = position;
+            self.is_playing = false;
+        }
+    }
+
     /// Get current playback state for the remote control
     pub fn playback_state(&self) -> PlaybackState {
         let (title, artist, album) = if let Some(track) = self.current_track() {
@@ -614,10 +2305,26 @@ impl App {
             is_playing: self.is_playing,
             volume: self.volume,
             shuffle: self.shuffle,
+            shuffle_mode: self.shuffle_mode.label().to_string(),
             repeat: self.repeat.label().to_string(),
             theme: self.theme.name().to_string(),
             visualizer_mode: self.visualizer.mode.label().to_string(),
             visualizer_bars: self.visualizer.bars.clone(),
+            queue: self
+                .queue
+                .tracks()
+                .iter()
+                .map(|&i| {
+                    let t = &self.library[i];
+                    QueueEntry {
+                        title: t.title.clone(),
+                        artist: t.artist.clone(),
+                        duration: t.duration,
+                    }
+                })
+                .collect(),
+            chapters: self.chapters.clone(),
+            chapter: self.current_chapter(),
         }
     }
 }
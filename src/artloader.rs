@@ -0,0 +1,236 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::albumart::AlbumArt;
+use crate::config;
+use crate::cover;
+
+/// Terminal cells for a grid thumbnail (each cell holds 2 vertical pixels)
+pub const THUMB_WIDTH: u32 = 12;
+pub const THUMB_HEIGHT: u32 = 6;
+
+/// Thumbnails kept in memory. Past this the least recently requested are
+/// dropped; they come back quickly from the disk cache.
+const MAX_THUMBS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtSize {
+    /// Now-playing panel
+    Full,
+    /// Album grid tile
+    Thumb,
+}
+
+impl ArtSize {
+    /// Size in terminal cells
+    fn cells(self) -> (u32, u32) {
+        match self {
+            Self::Full => (AlbumArt::DEFAULT_WIDTH, AlbumArt::DEFAULT_HEIGHT),
+            Self::Thumb => (THUMB_WIDTH, THUMB_HEIGHT),
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Thumb => "thumb",
+        }
+    }
+}
+
+struct ArtRequest {
+    key: String,
+    path: PathBuf,
+    size: ArtSize,
+}
+
+struct ArtResult {
+    key: String,
+    size: ArtSize,
+    art: Option<AlbumArt>,
+}
+
+struct CachedArt {
+    art: Option<AlbumArt>,
+    /// `ArtLoader::clock` when it was last requested
+    last_used: u64,
+}
+
+/// Decodes cover art on a background thread and caches the results by album
+/// key, so neither track changes nor grid scrolling block the UI on image
+/// decoding. Downscaled covers are also written to `~/.tunebox/art/`, so a
+/// multi-megabyte source image is only decoded once per album and size, for
+/// as long as the picture stays the same.
+pub struct ArtLoader {
+    req_tx: Sender<ArtRequest>,
+    res_rx: Receiver<ArtResult>,
+    cache: HashMap<(String, ArtSize), CachedArt>,
+    pending: HashSet<(String, ArtSize)>,
+    /// Counts requests, to tell which thumbnails were used least recently
+    clock: u64,
+}
+
+impl ArtLoader {
+    pub fn new(cover_files: Vec<String>) -> Self {
+        let (req_tx, req_rx) = unbounded::<ArtRequest>();
+        let (res_tx, res_rx) = unbounded::<ArtResult>();
+
+        std::thread::spawn(move || {
+            while let Ok(first) = req_rx.recv() {
+                // Serve the newest requests first: while scrolling, the
+                // tiles requested last are the ones on screen.
+                let mut batch = vec![first];
+                batch.extend(req_rx.try_iter());
+                for req in batch.into_iter().rev() {
+                    let art = load(&req, &cover_files);
+                    if res_tx
+                        .send(ArtResult {
+                            key: req.key,
+                            size: req.size,
+                            art,
+                        })
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self {
+            req_tx,
+            res_rx,
+            cache: HashMap::new(),
+            pending: HashSet::new(),
+            clock: 0,
+        }
+    }
+
+    /// Queue a decode unless the art is already cached or in flight
+    pub fn request(&mut self, key: &str, path: &Path, size: ArtSize) {
+        let cache_key = (key.to_string(), size);
+        self.clock += 1;
+        if let Some(cached) = self.cache.get_mut(&cache_key) {
+            cached.last_used = self.clock;
+            return;
+        }
+        if self.pending.contains(&cache_key) {
+            return;
+        }
+        self.pending.insert(cache_key);
+        let _ = self.req_tx.send(ArtRequest {
+            key: key.to_string(),
+            path: path.to_path_buf(),
+            size,
+        });
+    }
+
+    /// Move finished decodes into the cache
+    pub fn poll(&mut self) {
+        let mut added = false;
+        while let Ok(result) = self.res_rx.try_recv() {
+            added = true;
+            let cache_key = (result.key, result.size);
+            self.pending.remove(&cache_key);
+            let cached = CachedArt {
+                art: result.art,
+                last_used: self.clock,
+            };
+            self.cache.insert(cache_key, cached);
+        }
+        if added {
+            self.evict_thumbs();
+        }
+    }
+
+    /// Drop the least recently requested thumbnails past `MAX_THUMBS`
+    fn evict_thumbs(&mut self) {
+        let mut thumbs: Vec<(u64, String)> = self
+            .cache
+            .iter()
+            .filter(|((_, size), _)| *size == ArtSize::Thumb)
+            .map(|((key, _), cached)| (cached.last_used, key.clone()))
+            .collect();
+        if thumbs.len() <= MAX_THUMBS {
+            return;
+        }
+        thumbs.sort_unstable();
+        let excess = thumbs.len() - MAX_THUMBS;
+        for (_, key) in thumbs.into_iter().take(excess) {
+            self.cache.remove(&(key, ArtSize::Thumb));
+        }
+    }
+
+    /// Drop memory and disk copies of an album's art after its cover changed
+    pub fn invalidate(&mut self, key: &str) {
+        for size in [ArtSize::Full, ArtSize::Thumb] {
+            self.cache.remove(&(key.to_string(), size));
+        }
+        invalidate_disk(key);
+    }
+
+    /// `None` while loading, `Some(None)` when the album has no art
+    pub fn get(&self, key: &str, size: ArtSize) -> Option<Option<&AlbumArt>> {
+        self.cache
+            .get(&(key.to_string(), size))
+            .map(|cached| cached.art.as_ref())
+    }
+}
+
+fn load(req: &ArtRequest, cover_files: &[String]) -> Option<AlbumArt> {
+    let (width, height) = req.size.cells();
+    let cache_file = disk_cache_path(&req.key, req.size);
+
+    // Reading the picture is cheap next to decoding it; its hash tells
+    // whether the cached copy was made from this one
+    let data = cover::resolve_cover(&req.path, cover_files)?;
+    let source = format!("{:016x}", fnv1a(&data));
+    if let Some(path) = &cache_file {
+        let fresh = std::fs::read_to_string(path.with_extension("src")).is_ok_and(|s| s == source);
+        if let Some(img) = fresh.then(|| image::open(path).ok()).flatten() {
+            return Some(AlbumArt::from_image_sized(&img, width, height));
+        }
+    }
+
+    let img = image::load_from_memory(&data).ok()?;
+    // Each cell holds two vertical pixels
+    let small = img.resize_exact(width, height * 2, image::imageops::FilterType::Lanczos3);
+
+    if let Some(path) = cache_file {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if small.to_rgb8().save(&path).is_ok() {
+            let _ = std::fs::write(path.with_extension("src"), source);
+        }
+    }
+
+    Some(AlbumArt::from_image_sized(&small, width, height))
+}
+
+/// Delete the downscaled copies of an album's art in `~/.tunebox/art/`,
+/// for when its cover changed outside the player
+pub fn invalidate_disk(key: &str) {
+    for size in [ArtSize::Full, ArtSize::Thumb] {
+        if let Some(path) = disk_cache_path(key, size) {
+            let _ = std::fs::remove_file(path.with_extension("src"));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn disk_cache_path(key: &str, size: ArtSize) -> Option<PathBuf> {
+    config::data_dir().map(|d| {
+        d.join("art")
+            .join(format!("{:016x}-{}.png", fnv1a(key.as_bytes()), size.suffix()))
+    })
+}
+
+/// Stable across builds, unlike `DefaultHasher`, so cache file names survive upgrades
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
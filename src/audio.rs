@@ -1,17 +1,44 @@
 use std::fs::File;
 use std::io::BufReader;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::{Receiver, Sender};
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 
-/// Commands sent from TUI to audio thread
+use crate::codecs;
+
+/// How long playback runs on past the end of a segment waiting for the next
+/// one before it stops
+const SEGMENT_GRACE: Duration = Duration::from_millis(300);
+
+/// Segment starts closer than this to where the last one ended carry on
+/// without reopening the file
+const SEGMENT_JOIN_TOLERANCE: f64 = 0.01;
+
+/// Part of a file to play; the whole file by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Segment {
+    /// Seconds from the start of the file
+    pub start: f64,
+    /// Where to stop, or `None` for the end of the file
+    pub end: Option<f64>,
+}
+
+impl Segment {
+    /// Length in seconds, given the length of the whole file
+    fn length(&self, file_duration: f64) -> f64 {
+        (self.end.unwrap_or(file_duration) - self.start).max(0.0)
+    }
+}
+
+/// Commands sent from TUI to audio thread. Positions are relative to the
+/// segment being played.
 #[derive(Debug)]
 pub enum AudioCommand {
-    Play(PathBuf),
+    Play(PathBuf, Segment),
     Pause,
     Resume,
     Stop,
@@ -120,11 +147,60 @@ impl<S: Source<Item = f32>> Source for CaptureSource<S> {
     }
 
     fn try_seek(&mut self, pos: Duration) -> Result<(), rodio::source::SeekError> {
-        // Reset progress counter to the seek position
+        self.inner.try_seek(pos)?;
+        // Move the progress counter only once the audio has moved with it
         let sample_pos = (pos.as_secs_f64() * self.sample_rate as f64 * self.channels as f64) as u64;
         self.progress_counter.store(sample_pos, Ordering::Relaxed);
-        self.inner.try_seek(pos)
+        Ok(())
+    }
+}
+
+/// Open `path` for decoding, with this crate's decoders for the formats
+/// symphonia lacks and rodio's for the rest
+pub fn open_decoder(path: &Path) -> Result<Box<dyn Source<Item = i16> + Send>, String> {
+    if let Some(codec) = codecs::detect(path) {
+        return codecs::open(path, codec);
+    }
+    let file = File::open(path).map_err(|e| format!("unreadable: {e}"))?;
+    let decoder = Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    Ok(Box::new(decoder))
+}
+
+/// Decode just `segment` of `path` outside the playback thread
+pub fn open_segment(path: &Path, segment: Segment) -> Result<Box<dyn Source<Item = i16> + Send>, String> {
+    let mut decoder = open_decoder(path)?;
+    if segment.start > 0.0 {
+        decoder
+            .try_seek(Duration::from_secs_f64(segment.start))
+            .map_err(|e| e.to_string())?;
     }
+    Ok(match segment.end {
+        Some(end) => Box::new(decoder.take_duration(Duration::from_secs_f64(segment.length(end)))),
+        None => Box::new(decoder),
+    })
+}
+
+/// Check that `path` decodes, without playing it: build a decoder and
+/// decode its first packet, which catches files whose headers parse but
+/// whose audio doesn't. Returns the duration the decoder reports, if any.
+pub fn probe_decoder(path: &Path) -> Result<Option<f64>, String> {
+    let mut decoder = open_decoder(path)?;
+    let duration = decoder.total_duration().map(|d| d.as_secs_f64());
+    if decoder.next().is_none() {
+        return Err("no audio could be decoded".to_string());
+    }
+    Ok(duration)
+}
+
+/// The track the engine is playing
+struct Playback {
+    sink: Sink,
+    path: PathBuf,
+    segment: Segment,
+    /// Length of the whole file, 0 if unknown
+    file_duration: f64,
+    sample_rate: u32,
+    channels: u16,
 }
 
 pub struct AudioEngine {
@@ -172,7 +248,7 @@ impl AudioEngine {
         let is_finished = Arc::new(AtomicBool::new(false));
         let mut current_sample_rate: u32 = 44100;
         let mut current_channels: u16 = 2;
-        let mut last_progress_send = std::time::Instant::now();
+        let mut last_progress_send = Instant::now();
 
         loop {
             // Check for track finished
@@ -186,39 +262,39 @@ impl AudioEngine {
                 let samples = progress_counter.load(Ordering::Relaxed);
                 let position = samples as f64 / (current_sample_rate as f64 * current_channels as f64);
                 let _ = self.event_tx.send(AudioEvent::Progress(position));
-                last_progress_send = std::time::Instant::now();
+                last_progress_send = Instant::now();
             }
 
             // Process commands (non-blocking with timeout)
             match self.cmd_rx.recv_timeout(Duration::from_millis(16)) {
                 Ok(cmd) => match cmd {
-                    AudioCommand::Play(path) => {
+                    AudioCommand::Play(path, segment) => {
                         sink.stop();
                         progress_counter.store(0, Ordering::Relaxed);
                         is_finished.store(false, Ordering::Relaxed);
 
                         match Self::load_track(
                             &stream_handle,
-                            &path,
+                            path.clone(),
+                            segment,
                             self.sample_tx.clone(),
                             progress_counter.clone(),
                             is_finished.clone(),
                         ) {
-                            Ok((new_sink, duration, sr, ch)) => {
+                            Ok(playback) => {
                                 // We need to replace sink - but sink is not mut.
                                 // Instead, let's restructure to create a new sink each time.
                                 // For now, use the returned sink.
-                                current_sample_rate = sr;
-                                current_channels = ch;
+                                current_sample_rate = playback.sample_rate;
+                                current_channels = playback.channels;
+                                let duration = playback.segment.length(playback.file_duration);
                                 let _ = self.event_tx.send(AudioEvent::Playing { duration });
 
                                 // We'll run a sub-loop for this track
                                 self.run_track_playback(
-                                    new_sink,
+                                    playback,
                                     &progress_counter,
                                     &is_finished,
-                                    current_sample_rate,
-                                    current_channels,
                                     &stream_handle,
                                 );
                             }
@@ -254,55 +330,92 @@ impl AudioEngine {
 
     fn run_track_playback(
         &self,
-        sink: Sink,
+        playback: Playback,
         progress_counter: &Arc<AtomicU64>,
         is_finished: &Arc<AtomicBool>,
-        sample_rate: u32,
-        channels: u16,
         stream_handle: &OutputStreamHandle,
     ) {
-        let mut last_progress_send = std::time::Instant::now();
+        let Playback {
+            sink,
+            path,
+            mut segment,
+            file_duration,
+            sample_rate,
+            channels,
+        } = playback;
+        let mut last_progress_send = Instant::now();
+        // When playback passed the end of the segment. The file keeps playing
+        // for a moment so the next segment can follow without a gap.
+        let mut segment_ended: Option<Instant> = None;
 
         loop {
             // Check for track finished
             if is_finished.load(Ordering::Relaxed) && sink.empty() {
                 is_finished.store(false, Ordering::Relaxed);
+                // A segment that ran to the end of the file already said so
+                if segment_ended.is_none() {
+                    let _ = self.event_tx.send(AudioEvent::TrackFinished);
+                }
+                return;
+            }
+
+            let samples = progress_counter.load(Ordering::Relaxed);
+            let position = samples as f64 / (sample_rate as f64 * channels as f64);
+            if segment_ended.is_none() && segment.end.is_some_and(|end| position >= end) {
+                segment_ended = Some(Instant::now());
                 let _ = self.event_tx.send(AudioEvent::TrackFinished);
+            }
+            if segment_ended.is_some_and(|at| at.elapsed() >= SEGMENT_GRACE) {
+                sink.stop();
+                progress_counter.store(0, Ordering::Relaxed);
                 return;
             }
 
             // Send progress updates at ~30fps
             if last_progress_send.elapsed() >= Duration::from_millis(33) {
-                let samples = progress_counter.load(Ordering::Relaxed);
-                let position = samples as f64 / (sample_rate as f64 * channels as f64);
-                let _ = self.event_tx.send(AudioEvent::Progress(position));
-                last_progress_send = std::time::Instant::now();
+                let relative = (position - segment.start).max(0.0);
+                let _ = self.event_tx.send(AudioEvent::Progress(relative));
+                last_progress_send = Instant::now();
             }
 
             // Process commands
             match self.cmd_rx.recv_timeout(Duration::from_millis(16)) {
                 Ok(cmd) => match cmd {
-                    AudioCommand::Play(path) => {
+                    AudioCommand::Play(next_path, next_segment) => {
+                        // The following track of the same file: keep going
+                        if segment_ended.is_some()
+                            && next_path == path
+                            && segment
+                                .end
+                                .is_some_and(|end| (end - next_segment.start).abs() < SEGMENT_JOIN_TOLERANCE)
+                        {
+                            segment = next_segment;
+                            segment_ended = None;
+                            let duration = segment.length(file_duration);
+                            let _ = self.event_tx.send(AudioEvent::Playing { duration });
+                            continue;
+                        }
+
                         sink.stop();
                         progress_counter.store(0, Ordering::Relaxed);
                         is_finished.store(false, Ordering::Relaxed);
 
                         match Self::load_track(
                             stream_handle,
-                            &path,
+                            next_path,
+                            next_segment,
                             self.sample_tx.clone(),
                             progress_counter.clone(),
                             is_finished.clone(),
                         ) {
-                            Ok((new_sink, duration, sr, ch)) => {
+                            Ok(playback) => {
+                                let duration = playback.segment.length(playback.file_duration);
                                 let _ = self.event_tx.send(AudioEvent::Playing { duration });
                                 // Recurse with the new sink
                                 self.run_track_playback(
-                                    new_sink,
+                                    playback,
                                     progress_counter,
                                     is_finished,
-                                    sr,
-                                    ch,
                                     stream_handle,
                                 );
                                 return;
@@ -323,11 +436,12 @@ impl AudioEngine {
                         return;
                     }
                     AudioCommand::Seek(pos) => {
-                        let seek_duration = Duration::from_secs_f64(pos.max(0.0));
-                        let _ = sink.try_seek(seek_duration);
-                        let sample_pos =
-                            (pos * sample_rate as f64 * channels as f64) as u64;
-                        progress_counter.store(sample_pos, Ordering::Relaxed);
+                        let pos = segment.start + pos.max(0.0);
+                        // `CaptureSource::try_seek` moves the progress counter
+                        // when the seek works
+                        if sink.try_seek(Duration::from_secs_f64(pos)).is_ok() {
+                            segment_ended = None;
+                        }
                     }
                     AudioCommand::SetVolume(vol) => {
                         sink.set_volume(vol);
@@ -344,18 +458,23 @@ impl AudioEngine {
 
     fn load_track(
         stream_handle: &OutputStreamHandle,
-        path: &std::path::Path,
+        path: PathBuf,
+        segment: Segment,
         sample_tx: Sender<Vec<f32>>,
         progress_counter: Arc<AtomicU64>,
         is_finished: Arc<AtomicBool>,
-    ) -> anyhow::Result<(Sink, f64, u32, u16)> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let decoder = Decoder::new(reader)?;
+    ) -> anyhow::Result<Playback> {
+        let mut decoder = open_decoder(&path).map_err(anyhow::Error::msg)?;
+        if segment.start > 0.0 {
+            // Playing from the file's start would give the wrong track's audio
+            decoder
+                .try_seek(Duration::from_secs_f64(segment.start))
+                .map_err(|e| anyhow::anyhow!("can't seek to {:.1}s: {e}", segment.start))?;
+        }
 
         let sample_rate = decoder.sample_rate();
         let channels = decoder.channels();
-        let total_duration = decoder
+        let file_duration = decoder
             .total_duration()
             .map(|d| d.as_secs_f64())
             .unwrap_or(0.0);
@@ -363,12 +482,21 @@ impl AudioEngine {
         // Convert to f32 source
         let source = decoder.convert_samples::<f32>();
 
-        // Wrap in capture source
+        // Wrap in capture source, counting from where the decoder now is
+        let start_samples = (segment.start * sample_rate as f64 * channels as f64) as u64;
+        progress_counter.store(start_samples, Ordering::Relaxed);
         let capture = CaptureSource::new(source, sample_tx, progress_counter, is_finished);
 
         let sink = Sink::try_new(stream_handle)?;
         sink.append(capture);
 
-        Ok((sink, total_duration, sample_rate, channels))
+        Ok(Playback {
+            sink,
+            path,
+            segment,
+            file_duration,
+            sample_rate,
+            channels,
+        })
     }
 }
@@ -0,0 +1,518 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::duplicates::{self, MatchMode};
+use crate::history::History;
+use crate::library::Track;
+use crate::playlist::Playlist;
+use crate::query::{Query, Stats};
+use crate::smart::SmartPlaylist;
+
+/// Top-level tabs of the library pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BrowseView {
+    #[default]
+    Tracks,
+    Artists,
+    Albums,
+    Genres,
+    Folders,
+    Grid,
+    Duplicates,
+    Recent,
+    Playlists,
+}
+
+impl BrowseView {
+    pub const ALL: [BrowseView; 9] = [
+        Self::Tracks,
+        Self::Artists,
+        Self::Albums,
+        Self::Grid,
+        Self::Genres,
+        Self::Folders,
+        Self::Duplicates,
+        Self::Recent,
+        Self::Playlists,
+    ];
+
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Tracks => Self::Artists,
+            Self::Artists => Self::Albums,
+            Self::Albums => Self::Grid,
+            Self::Grid => Self::Genres,
+            Self::Genres => Self::Folders,
+            Self::Folders => Self::Duplicates,
+            Self::Duplicates => Self::Recent,
+            Self::Recent => Self::Playlists,
+            Self::Playlists => Self::Tracks,
+        }
+    }
+
+    pub fn cycle_back(self) -> Self {
+        match self {
+            Self::Tracks => Self::Playlists,
+            Self::Artists => Self::Tracks,
+            Self::Albums => Self::Artists,
+            Self::Grid => Self::Albums,
+            Self::Genres => Self::Grid,
+            Self::Folders => Self::Genres,
+            Self::Duplicates => Self::Folders,
+            Self::Recent => Self::Duplicates,
+            Self::Playlists => Self::Recent,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tracks => "Tracks",
+            Self::Artists => "Artists",
+            Self::Albums => "Albums",
+            Self::Genres => "Genres",
+            Self::Folders => "Folders",
+            Self::Grid => "Grid",
+            Self::Duplicates => "Duplicates",
+            Self::Recent => "Recent",
+            Self::Playlists => "Playlists",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Artist,
+    Album,
+    Genre,
+    Folder(PathBuf),
+    Duplicates,
+    Playlist(PathBuf),
+    /// A smart playlist, by name
+    Smart(String),
+    Track,
+}
+
+/// One row in a group listing; `tracks` holds library indices in library order
+#[derive(Debug, Clone)]
+pub struct BrowseEntry {
+    pub label: String,
+    pub detail: String,
+    pub kind: EntryKind,
+    pub tracks: Vec<usize>,
+}
+
+impl BrowseEntry {
+    /// Whether Enter opens a deeper listing rather than a track list
+    pub fn has_children(&self) -> bool {
+        matches!(
+            self.kind,
+            EntryKind::Artist | EntryKind::Genre | EntryKind::Folder(_)
+        )
+    }
+}
+
+/// A group listing on the drill-down stack, with the cursor it was left at
+#[derive(Debug, Clone)]
+pub struct BrowseLevel {
+    pub title: String,
+    pub entries: Vec<BrowseEntry>,
+    pub selected: usize,
+}
+
+/// Drill-down state for the library pane. When `tracks` is set the pane shows
+/// a plain track list (rendered by `draw_library`); otherwise it shows the
+/// entries of the top level on the stack.
+#[derive(Debug, Clone, Default)]
+pub struct Browser {
+    pub view: BrowseView,
+    pub stack: Vec<BrowseLevel>,
+    pub tracks: Option<TrackScope>,
+}
+
+/// The track list reached by drilling into an album (or a leaf folder)
+#[derive(Debug, Clone)]
+pub struct TrackScope {
+    pub title: String,
+    pub indices: Vec<usize>,
+    pub parent_selected: usize,
+    /// Set when the list is a playlist file's, which edits write back to
+    pub playlist: Option<PathBuf>,
+    /// Plays on in list order rather than library order, as playlists do
+    pub ordered: bool,
+}
+
+impl Browser {
+    /// Reset to the root listing of `view`, built from the `visible` library
+    /// indices. The duplicates view always lists every track; the playlists
+    /// view lists the `playlists` entries, which the caller builds.
+    pub fn open(
+        &mut self,
+        view: BrowseView,
+        library: &[Track],
+        visible: &[usize],
+        history: &History,
+        playlists: Vec<BrowseEntry>,
+    ) {
+        self.view = view;
+        self.tracks = None;
+        self.stack.clear();
+        let (title, entries) = match view {
+            BrowseView::Tracks => return,
+            BrowseView::Artists => ("Artists".to_string(), artist_entries(library, visible)),
+            BrowseView::Albums | BrowseView::Grid => {
+                ("Albums".to_string(), album_entries(library, visible, true))
+            }
+            BrowseView::Genres => ("Genres".to_string(), genre_entries(library, visible)),
+            BrowseView::Folders => {
+                let root = common_root(library);
+                (
+                    display_folder(&root),
+                    folder_entries(library, visible, &root),
+                )
+            }
+            BrowseView::Duplicates => ("Duplicates".to_string(), duplicate_entries(library)),
+            BrowseView::Recent => ("Recently played".to_string(), recent_entries(library, history)),
+            BrowseView::Playlists => ("Playlists".to_string(), playlists),
+        };
+        self.stack.push(BrowseLevel {
+            title,
+            entries,
+            selected: 0,
+        });
+    }
+
+    /// True when the pane is showing a track list rather than group entries
+    pub fn showing_tracks(&self) -> bool {
+        self.view == BrowseView::Tracks || self.tracks.is_some()
+    }
+
+    pub fn current_level(&self) -> Option<&BrowseLevel> {
+        self.stack.last()
+    }
+
+    /// Library indices the track list is drawn from (None = whole library)
+    pub fn track_scope(&self) -> Option<&[usize]> {
+        self.tracks.as_ref().map(|t| t.indices.as_slice())
+    }
+
+    /// Descend into `entry`. Returns false for track entries, which the caller plays.
+    pub fn enter(&mut self, entry: &BrowseEntry, selected: usize, library: &[Track]) -> bool {
+        if let Some(level) = self.stack.last_mut() {
+            level.selected = selected;
+        }
+        let children = match &entry.kind {
+            EntryKind::Track => return false,
+            EntryKind::Album
+            | EntryKind::Duplicates
+            | EntryKind::Playlist(_)
+            | EntryKind::Smart(_) => None,
+            EntryKind::Artist | EntryKind::Genre => {
+                Some(album_entries(library, &entry.tracks, false))
+            }
+            EntryKind::Folder(path) => Some(folder_entries(library, &entry.tracks, path)),
+        };
+        match children {
+            Some(entries) => self.stack.push(BrowseLevel {
+                title: entry.label.clone(),
+                entries,
+                selected: 0,
+            }),
+            None => {
+                self.tracks = Some(TrackScope {
+                    title: entry.label.clone(),
+                    indices: entry.tracks.clone(),
+                    parent_selected: selected,
+                    playlist: match &entry.kind {
+                        EntryKind::Playlist(path) => Some(path.clone()),
+                        _ => None,
+                    },
+                    ordered: matches!(entry.kind, EntryKind::Playlist(_) | EntryKind::Smart(_)),
+                })
+            }
+        }
+        true
+    }
+
+    /// Go up one level. Returns the cursor position to restore, or None at the root.
+    pub fn back(&mut self) -> Option<usize> {
+        if let Some(scope) = self.tracks.take() {
+            return Some(scope.parent_selected);
+        }
+        if self.stack.len() > 1 {
+            self.stack.pop();
+            return self.stack.last().map(|l| l.selected);
+        }
+        None
+    }
+
+    /// Breadcrumb for the pane title, e.g. "Artists › Radiohead › OK Computer"
+    pub fn breadcrumb(&self) -> String {
+        let mut parts: Vec<&str> = self.stack.iter().map(|l| l.title.as_str()).collect();
+        if let Some(ref scope) = self.tracks {
+            parts.push(&scope.title);
+        }
+        parts.join(" › ")
+    }
+}
+
+fn artist_entries(library: &[Track], indices: &[usize]) -> Vec<BrowseEntry> {
+    let mut groups: BTreeMap<String, (String, Vec<usize>)> = BTreeMap::new();
+    for &i in indices {
+        let name = library[i].album_artist_or_artist();
+        groups
+            .entry(name.to_lowercase())
+            .or_insert_with(|| (name.to_string(), Vec::new()))
+            .1
+            .push(i);
+    }
+    groups
+        .into_values()
+        .map(|(name, tracks)| {
+            let albums = album_entries(library, &tracks, false).len();
+            BrowseEntry {
+                detail: format!(
+                    "{} album{} · {} tracks",
+                    albums,
+                    if albums == 1 { "" } else { "s" },
+                    tracks.len()
+                ),
+                label: name,
+                kind: EntryKind::Artist,
+                tracks,
+            }
+        })
+        .collect()
+}
+
+/// Group tracks into albums by `Track::album_key`, in library order
+fn album_entries(library: &[Track], indices: &[usize], with_artist: bool) -> Vec<BrowseEntry> {
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for &i in indices {
+        let key = library[i].album_key();
+        if !groups.contains_key(&key) {
+            order.push(key.clone());
+        }
+        groups.entry(key).or_default().push(i);
+    }
+
+    order
+        .into_iter()
+        .map(|key| {
+            let tracks = groups.remove(&key).unwrap_or_default();
+            let first = &library[tracks[0]];
+            let mut detail = Vec::new();
+            if with_artist {
+                detail.push(first.album_artist_or_artist().to_string());
+            }
+            if let Some(year) = first.year {
+                detail.push(year.to_string());
+            }
+            detail.push(format!("{} tracks", tracks.len()));
+            BrowseEntry {
+                label: first.album.clone(),
+                detail: detail.join(" · "),
+                kind: EntryKind::Album,
+                tracks,
+            }
+        })
+        .collect()
+}
+
+fn genre_entries(library: &[Track], indices: &[usize]) -> Vec<BrowseEntry> {
+    let mut groups: BTreeMap<String, (String, Vec<usize>)> = BTreeMap::new();
+    for &i in indices {
+        let name = library[i].genre.as_deref().unwrap_or("Unknown Genre");
+        groups
+            .entry(name.to_lowercase())
+            .or_insert_with(|| (name.to_string(), Vec::new()))
+            .1
+            .push(i);
+    }
+    groups
+        .into_values()
+        .map(|(name, tracks)| BrowseEntry {
+            detail: format!("{} tracks", tracks.len()),
+            label: name,
+            kind: EntryKind::Genre,
+            tracks,
+        })
+        .collect()
+}
+
+/// One entry per group of copies, best copy first within each group
+fn duplicate_entries(library: &[Track]) -> Vec<BrowseEntry> {
+    duplicates::find_duplicates(library, MatchMode::Tags)
+        .into_iter()
+        .map(|group| {
+            let best = &library[group.best()];
+            BrowseEntry {
+                label: format!("{} – {}", best.artist, best.title),
+                detail: format!("{} copies · keep {}", group.tracks.len(), best.format),
+                kind: EntryKind::Duplicates,
+                tracks: group.tracks,
+            }
+        })
+        .collect()
+}
+
+/// A playlist file, its tracks in playlist order. Entries that aren't in
+/// the library are counted in the detail rather than failing the load.
+pub fn playlist_entry(library: &[Track], path: &Path) -> BrowseEntry {
+    let kind = EntryKind::Playlist(path.to_path_buf());
+    match Playlist::load(path) {
+        Ok(playlist) => {
+            let resolved = playlist.resolve(library);
+            let tracks: Vec<usize> = resolved.iter().flatten().copied().collect();
+            let missing = resolved.len() - tracks.len();
+            let mut detail = format!("{} tracks", tracks.len());
+            if missing > 0 {
+                detail.push_str(&format!(" · ⚠ {} missing", missing));
+            }
+            BrowseEntry {
+                label: playlist.name,
+                detail,
+                kind,
+                tracks,
+            }
+        }
+        Err(e) => BrowseEntry {
+            label: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            detail: format!("⚠ {:#}", e),
+            kind,
+            tracks: Vec::new(),
+        },
+    }
+}
+
+/// The `visible` tracks a smart playlist's query picks, in its order
+pub fn smart_entry(
+    library: &[Track],
+    visible: &[usize],
+    smart: &SmartPlaylist,
+    stats: &Stats,
+) -> BrowseEntry {
+    let kind = EntryKind::Smart(smart.name.clone());
+    let (detail, tracks) = match Query::parse(&smart.query) {
+        Ok(query) => {
+            let tracks = query.run(library, visible, stats);
+            (format!("smart · {} tracks", tracks.len()), tracks)
+        }
+        Err(e) => (format!("⚠ {:#}", e), Vec::new()),
+    };
+    BrowseEntry {
+        label: smart.name.clone(),
+        detail,
+        kind,
+        tracks,
+    }
+}
+
+/// One entry per play, newest first
+fn recent_entries(library: &[Track], history: &History) -> Vec<BrowseEntry> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    history
+        .recent()
+        .map(|play| {
+            let t = &library[play.track];
+            BrowseEntry {
+                label: format!("{} – {}", t.title, t.artist),
+                detail: time_ago(now.saturating_sub(play.played_at)),
+                kind: EntryKind::Track,
+                tracks: vec![play.track],
+            }
+        })
+        .collect()
+}
+
+/// "just now", "5m ago", "3h ago", "2d ago"
+fn time_ago(secs: u64) -> String {
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Subfolders of `dir` (first), then the tracks that sit directly inside it
+fn folder_entries(library: &[Track], indices: &[usize], dir: &Path) -> Vec<BrowseEntry> {
+    let mut folders: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut files = Vec::new();
+    for &i in indices {
+        let Ok(rel) = library[i].path.strip_prefix(dir) else {
+            continue;
+        };
+        let mut components = rel.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(first)), Some(_)) => {
+                folders
+                    .entry(first.to_string_lossy().into_owned())
+                    .or_default()
+                    .push(i);
+            }
+            _ => files.push(i),
+        }
+    }
+
+    let mut entries: Vec<BrowseEntry> = folders
+        .into_iter()
+        .map(|(name, tracks)| BrowseEntry {
+            detail: format!("{} tracks", tracks.len()),
+            kind: EntryKind::Folder(dir.join(&name)),
+            label: name,
+            tracks,
+        })
+        .collect();
+    entries.extend(files.into_iter().map(|i| {
+        let t = &library[i];
+        // Tracks cut from one file by a CUE sheet would all show its name
+        let label = match &t.cue {
+            Some(cue) => format!("{:02}. {}", cue.number, t.title),
+            None => t
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| t.title.clone()),
+        };
+        BrowseEntry {
+            label,
+            detail: t.artist.clone(),
+            kind: EntryKind::Track,
+            tracks: vec![i],
+        }
+    }));
+    entries
+}
+
+/// Deepest directory containing every track
+fn common_root(library: &[Track]) -> PathBuf {
+    let mut root: Option<PathBuf> = None;
+    for track in library {
+        let parent = track.path.parent().unwrap_or(Path::new("/"));
+        root = Some(match root {
+            None => parent.to_path_buf(),
+            Some(r) => r
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    root.unwrap_or_default()
+}
+
+fn display_folder(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
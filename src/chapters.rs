@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::ItemKey;
+use serde::Serialize;
+
+/// A named section of an audiobook, mix or other long file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Seconds from the start of the file
+    pub start: f64,
+}
+
+/// Chapters of `path`, from ID3 CHAP frames, MP4 chapter tracks or Nero
+/// `chpl` atoms, or Vorbis `CHAPTERxx` comments, in start order. Empty when
+/// the file has fewer than two.
+pub fn read_chapters(path: &Path) -> Vec<Chapter> {
+    let Ok(mut file) = File::open(path) else {
+        return Vec::new();
+    };
+    let mut magic = [0u8; 8];
+    if file.read_exact(&mut magic).is_err() {
+        return Vec::new();
+    }
+    let mut chapters = if magic.starts_with(b"ID3") {
+        id3_chapters(&mut file).unwrap_or_default()
+    } else if &magic[4..8] == b"ftyp" {
+        mp4_chapters(&mut file).unwrap_or_default()
+    } else if magic.starts_with(b"fLaC") || magic.starts_with(b"OggS") {
+        vorbis_chapters(path)
+    } else {
+        Vec::new()
+    };
+
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    chapters.dedup_by(|a, b| a.start == b.start);
+    for (i, chapter) in chapters.iter_mut().enumerate() {
+        if chapter.title.trim().is_empty() {
+            chapter.title = format!("Chapter {}", i + 1);
+        }
+    }
+    if chapters.len() < 2 {
+        return Vec::new();
+    }
+    chapters
+}
+
+/// Index of the chapter playing at `position`
+pub fn chapter_at(chapters: &[Chapter], position: f64) -> Option<usize> {
+    chapters.iter().rposition(|c| c.start <= position)
+}
+
+// === ID3v2 ===
+
+fn id3_chapters(file: &mut File) -> Option<Vec<Chapter>> {
+    let mut header = [0u8; 10];
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_exact(&mut header).ok()?;
+    let version = header[3];
+    // CHAP arrived with 2.3; 2.2 has three-letter frame IDs and no chapters
+    if version < 3 {
+        return None;
+    }
+    // A size running past the end means a truncated or corrupt tag; read
+    // what there is rather than allocating what the header claims
+    let len = file.metadata().ok()?.len().saturating_sub(10);
+    let size = (syncsafe(&header[6..10]) as u64).min(len) as usize;
+    let mut tag = vec![0u8; size];
+    file.read_exact(&mut tag).ok()?;
+
+    let mut pos = 0;
+    if header[5] & 0x40 != 0 {
+        // Extended header: 2.4 counts its own size field, 2.3 doesn't
+        let ext = tag.get(0..4)?;
+        pos = if version == 4 {
+            syncsafe(ext) as usize
+        } else {
+            u32::from_be_bytes(ext.try_into().ok()?) as usize + 4
+        };
+    }
+
+    let mut chapters = Vec::new();
+    for (id, body) in id3_frames(tag.get(pos..)?, version) {
+        if id == b"CHAP" {
+            chapters.extend(id3_chap(body, version));
+        }
+    }
+    Some(chapters)
+}
+
+/// Frame IDs and bodies of an ID3v2.3/2.4 frame area
+fn id3_frames(data: &[u8], version: u8) -> Vec<(&[u8], &[u8])> {
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos + 10 <= data.len() {
+        let id = &data[pos..pos + 4];
+        if id[0] == 0 {
+            break;
+        }
+        let size_bytes = &data[pos + 4..pos + 8];
+        let size = if version == 4 {
+            syncsafe(size_bytes)
+        } else {
+            u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]])
+        } as usize;
+        let Some(body) = data.get(pos + 10..pos + 10 + size) else {
+            break;
+        };
+        frames.push((id, body));
+        pos += 10 + size;
+    }
+    frames
+}
+
+/// CHAP body: element ID, start and end in milliseconds, byte offsets, then
+/// sub-frames, of which TIT2 holds the title
+fn id3_chap(body: &[u8], version: u8) -> Option<Chapter> {
+    let id_end = body.iter().position(|&b| b == 0)?;
+    let times = body.get(id_end + 1..id_end + 17)?;
+    let start_ms = u32::from_be_bytes(times[0..4].try_into().ok()?);
+    let title = id3_frames(&body[id_end + 17..], version)
+        .into_iter()
+        .find(|(id, _)| *id == b"TIT2")
+        .map(|(_, text)| id3_text(text))
+        .unwrap_or_default();
+    Some(Chapter {
+        title,
+        start: start_ms as f64 / 1000.0,
+    })
+}
+
+/// Text frame body: an encoding byte, then Latin-1, UTF-16 or UTF-8
+fn id3_text(body: &[u8]) -> String {
+    let Some((&encoding, text)) = body.split_first() else {
+        return String::new();
+    };
+    let decoded = match encoding {
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|c| {
+                    if big_endian {
+                        u16::from_be_bytes([c[0], c[1]])
+                    } else {
+                        u16::from_le_bytes([c[0], c[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => text.iter().map(|&b| b as char).collect(),
+    };
+    decoded.trim_end_matches('\0').trim().to_string()
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, &b| (acc << 7) | (b & 0x7F) as u32)
+}
+
+// === MP4 ===
+
+/// Chapter samples read at most, so a corrupt sample table can't make
+/// us allocate without bound
+const MAX_CHAPTERS: usize = 10_000;
+
+fn mp4_chapters(file: &mut File) -> Option<Vec<Chapter>> {
+    let moov = read_top_atom(file, b"moov")?;
+    if let Some(chapters) = quicktime_chapters(file, &moov) {
+        return Some(chapters);
+    }
+    find_atom(&moov, &[b"udta", b"chpl"]).and_then(nero_chapters)
+}
+
+/// Load a top-level atom's body, skipping over the others (`mdat` can be
+/// gigabytes)
+fn read_top_atom(file: &mut File, name: &[u8; 4]) -> Option<Vec<u8>> {
+    let len = file.metadata().ok()?.len();
+    let mut pos = 0u64;
+    while pos + 8 <= len {
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let mut size = u32::from_be_bytes(header[0..4].try_into().ok()?) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            file.read_exact(&mut header[8..16]).ok()?;
+            size = u64::from_be_bytes(header[8..16].try_into().ok()?);
+            header_len = 16;
+        } else if size == 0 {
+            size = len - pos;
+        }
+        // A size running past the end means a truncated or corrupt file
+        if size < header_len || size > len - pos {
+            return None;
+        }
+        if &header[4..8] == name {
+            let mut body = vec![0u8; (size - header_len) as usize];
+            file.read_exact(&mut body).ok()?;
+            return Some(body);
+        }
+        pos += size;
+    }
+    None
+}
+
+/// Child atoms of an atom body as (name, body) pairs
+fn atoms(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut children = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let mut size = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let mut header_len = 8;
+        if size == 1 {
+            let Some(large) = data.get(pos + 8..pos + 16) else {
+                break;
+            };
+            size = u64::from_be_bytes(large.try_into().unwrap_or_default()) as usize;
+            header_len = 16;
+        } else if size == 0 {
+            size = data.len() - pos;
+        }
+        if size < header_len || pos + size > data.len() {
+            break;
+        }
+        children.push((&data[pos + 4..pos + 8], &data[pos + header_len..pos + size]));
+        pos += size;
+    }
+    children
+}
+
+fn find_atom<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    let (first, rest) = path.split_first()?;
+    let (_, body) = atoms(data).into_iter().find(|(name, _)| name == first)?;
+    if rest.is_empty() {
+        Some(body)
+    } else {
+        find_atom(body, rest)
+    }
+}
+
+/// Nero `chpl`: version, flags, (v1: 4 reserved bytes), a count, then
+/// entries of a start time in 100ns units and a length-prefixed title
+fn nero_chapters(data: &[u8]) -> Option<Vec<Chapter>> {
+    let version = *data.first()?;
+    let mut pos = if version == 1 { 8 } else { 4 };
+    let count = *data.get(pos)?;
+    pos += 1;
+    let mut chapters = Vec::new();
+    for _ in 0..count {
+        let start = u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?);
+        let len = *data.get(pos + 8)? as usize;
+        let title = String::from_utf8_lossy(data.get(pos + 9..pos + 9 + len)?).into_owned();
+        chapters.push(Chapter {
+            title,
+            start: start as f64 / 10_000_000.0,
+        });
+        pos += 9 + len;
+    }
+    Some(chapters)
+}
+
+/// QuickTime chapters: a text track named by another track's `tref/chap`,
+/// one sample per chapter
+fn quicktime_chapters(file: &mut File, moov: &[u8]) -> Option<Vec<Chapter>> {
+    let traks: Vec<&[u8]> = atoms(moov)
+        .into_iter()
+        .filter(|(name, _)| *name == b"trak")
+        .map(|(_, body)| body)
+        .collect();
+    let chapter_id = traks
+        .iter()
+        .find_map(|trak| find_atom(trak, &[b"tref", b"chap"]))
+        .and_then(|chap| chap.get(0..4))
+        .map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]]))?;
+    let trak = traks.into_iter().find(|trak| track_id(trak) == Some(chapter_id))?;
+
+    let mdhd = find_atom(trak, &[b"mdia", b"mdhd"])?;
+    let timescale = if mdhd.first() == Some(&1) {
+        u32::from_be_bytes(mdhd.get(20..24)?.try_into().ok()?)
+    } else {
+        u32::from_be_bytes(mdhd.get(12..16)?.try_into().ok()?)
+    };
+    if timescale == 0 {
+        return None;
+    }
+    let stbl = find_atom(trak, &[b"mdia", b"minf", b"stbl"])?;
+    let starts = sample_starts(find_atom(stbl, &[b"stts"])?);
+    let offsets = sample_offsets(stbl)?;
+
+    let mut chapters = Vec::new();
+    for (start, offset) in starts.into_iter().zip(offsets) {
+        // Each sample is a 16-bit length and the title text
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut len = [0u8; 2];
+        file.read_exact(&mut len).ok()?;
+        let mut text = vec![0u8; u16::from_be_bytes(len) as usize];
+        file.read_exact(&mut text).ok()?;
+        let title = match text.as_slice() {
+            [0xFE, 0xFF, rest @ ..] => {
+                let units: Vec<u16> = rest.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                String::from_utf16_lossy(&units)
+            }
+            _ => String::from_utf8_lossy(&text).into_owned(),
+        };
+        chapters.push(Chapter {
+            title,
+            start: start as f64 / timescale as f64,
+        });
+    }
+    Some(chapters)
+}
+
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let tkhd = find_atom(trak, &[b"tkhd"])?;
+    let at = if tkhd.first() == Some(&1) { 20 } else { 12 };
+    Some(u32::from_be_bytes(tkhd.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Start time of every sample, in the track's timescale, from `stts` runs
+fn sample_starts(stts: &[u8]) -> Vec<u64> {
+    let count = be_u32(stts, 4).unwrap_or(0) as usize;
+    let mut starts = Vec::new();
+    let mut time = 0u64;
+    for i in 0..count {
+        let (Some(samples), Some(delta)) = (be_u32(stts, 8 + i * 8), be_u32(stts, 12 + i * 8)) else {
+            break;
+        };
+        for _ in 0..samples {
+            if starts.len() >= MAX_CHAPTERS {
+                return starts;
+            }
+            starts.push(time);
+            time += delta as u64;
+        }
+    }
+    starts
+}
+
+/// File offset of every sample, from chunk offsets (`stco`/`co64`), the
+/// samples-per-chunk runs (`stsc`) and sample sizes (`stsz`)
+fn sample_offsets(stbl: &[u8]) -> Option<Vec<u64>> {
+    let chunks: Vec<u64> = if let Some(stco) = find_atom(stbl, &[b"stco"]) {
+        (0..be_u32(stco, 4)? as usize)
+            .map_while(|i| be_u32(stco, 8 + i * 4).map(u64::from))
+            .collect()
+    } else {
+        let co64 = find_atom(stbl, &[b"co64"])?;
+        (0..be_u32(co64, 4)? as usize)
+            .map_while(|i| Some(u64::from_be_bytes(co64.get(8 + i * 8..16 + i * 8)?.try_into().ok()?)))
+            .collect()
+    };
+    let stsz = find_atom(stbl, &[b"stsz"])?;
+    let uniform = be_u32(stsz, 4)?;
+    let sample_count = (be_u32(stsz, 8)? as usize).min(MAX_CHAPTERS);
+    let size_of = |i: usize| -> u64 {
+        if uniform != 0 {
+            uniform as u64
+        } else {
+            be_u32(stsz, 12 + i * 4).unwrap_or(0) as u64
+        }
+    };
+    let stsc = find_atom(stbl, &[b"stsc"])?;
+    let runs: Vec<(u32, u32)> = (0..be_u32(stsc, 4)? as usize)
+        .map_while(|i| Some((be_u32(stsc, 8 + i * 12)?, be_u32(stsc, 12 + i * 12)?)))
+        .collect();
+
+    let mut offsets = Vec::with_capacity(sample_count);
+    let mut sample = 0;
+    for (chunk_index, &chunk_offset) in chunks.iter().enumerate() {
+        // stsc chunk numbers are 1-based; the last run that starts at or
+        // before this chunk applies
+        let per_chunk = runs
+            .iter()
+            .rev()
+            .find(|(first, _)| *first as usize <= chunk_index + 1)
+            .map_or(1, |&(_, n)| n);
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            if sample >= sample_count {
+                return Some(offsets);
+            }
+            offsets.push(offset);
+            offset += size_of(sample);
+            sample += 1;
+        }
+    }
+    Some(offsets)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+// === Vorbis comments ===
+
+/// `CHAPTER001=00:01:02.500` and `CHAPTER001NAME=Title` pairs
+fn vorbis_chapters(path: &Path) -> Vec<Chapter> {
+    let Ok(tagged_file) = Probe::open(path).and_then(|p| p.read()) else {
+        return Vec::new();
+    };
+    let mut starts: BTreeMap<String, f64> = BTreeMap::new();
+    let mut names: BTreeMap<String, String> = BTreeMap::new();
+    for tag in tagged_file.tags() {
+        for item in tag.items() {
+            let ItemKey::Unknown(key) = item.key() else {
+                continue;
+            };
+            let key = key.to_uppercase();
+            let Some(rest) = key.strip_prefix("CHAPTER") else {
+                continue;
+            };
+            let Some(value) = item.value().text() else {
+                continue;
+            };
+            if let Some(number) = rest.strip_suffix("NAME") {
+                names.insert(number.to_string(), value.to_string());
+            } else if rest.chars().all(|c| c.is_ascii_digit()) {
+                if let Some(start) = parse_timestamp(value) {
+                    starts.insert(rest.to_string(), start);
+                }
+            }
+        }
+    }
+    starts
+        .into_iter()
+        .map(|(number, start)| Chapter {
+            title: names.remove(&number).unwrap_or_default(),
+            start,
+        })
+        .collect()
+}
+
+/// `HH:MM:SS.mmm`, also accepting `MM:SS` and plain seconds
+fn parse_timestamp(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .split(':')
+        .try_fold(0.0, |acc, part| Some(acc * 60.0 + part.parse::<f64>().ok()?))
+}
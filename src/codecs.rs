@@ -0,0 +1,472 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use ape_decoder::ApeDecoder;
+use opus_decoder::OpusMultistreamDecoder;
+use oxideav_wavpack::StreamIndex;
+use rodio::source::SeekError;
+use rodio::Source;
+use symphonia::core::codecs::CODEC_TYPE_OPUS;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::default::formats::OggReader;
+
+/// Opus decodes at 48 kHz whatever the input rate was
+const OPUS_RATE: u32 = 48_000;
+
+/// Audio decoded ahead of a seek target so the Opus decoder's state has
+/// settled by the time the target plays (RFC 7845 recommends 80 ms)
+const OPUS_PREROLL: u64 = 3840;
+
+/// Longest Opus packet, 120 ms, in samples per channel
+const OPUS_MAX_PACKET: usize = 5760;
+
+/// Formats decoded here rather than by rodio, which only plays what
+/// symphonia decodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Opus,
+    WavPack,
+    Ape,
+}
+
+/// The codec `path` needs from this module, judged from its leading bytes
+pub fn detect(path: &Path) -> Option<Codec> {
+    let mut head = [0u8; 64];
+    let mut file = File::open(path).ok()?;
+    let len = read_up_to(&mut file, &mut head).ok()?;
+    let head = skip_id3v2(&mut file, &head[..len])?;
+    if head.starts_with(b"OggS") && head.get(28..36) == Some(b"OpusHead") {
+        Some(Codec::Opus)
+    } else if head.starts_with(b"wvpk") {
+        Some(Codec::WavPack)
+    } else if head.starts_with(b"MAC ") {
+        Some(Codec::Ape)
+    } else {
+        None
+    }
+}
+
+/// `head`, or the bytes after an ID3v2 tag at its start, which APE and
+/// WavPack files sometimes carry
+fn skip_id3v2(file: &mut File, head: &[u8]) -> Option<Vec<u8>> {
+    use std::io::{Seek, SeekFrom};
+
+    if !head.starts_with(b"ID3") || head.len() < 10 {
+        return Some(head.to_vec());
+    }
+    // Syncsafe size: 7 bits per byte, excluding the 10-byte header
+    let size = head[6..10]
+        .iter()
+        .fold(0u64, |n, b| (n << 7) | (*b & 0x7f) as u64);
+    file.seek(SeekFrom::Start(10 + size)).ok()?;
+    let mut after = [0u8; 64];
+    let len = read_up_to(file, &mut after).ok()?;
+    Some(after[..len].to_vec())
+}
+
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+/// Open `path` with the decoder for `codec`
+pub fn open(path: &Path, codec: Codec) -> Result<Box<dyn Source<Item = i16> + Send>, String> {
+    Ok(match codec {
+        Codec::Opus => Box::new(BlockSource::new(Opus::open(path)?)),
+        Codec::WavPack => Box::new(BlockSource::new(WavPack::open(path)?)),
+        Codec::Ape => Box::new(BlockSource::new(Ape::open(path)?)),
+    })
+}
+
+/// A decoder that produces interleaved samples a block at a time
+trait Blocks: Send {
+    fn channels(&self) -> u16;
+    fn sample_rate(&self) -> u32;
+    /// Length in frames, if known
+    fn frames(&self) -> Option<u64>;
+    /// Append the next block to `out`, leaving it untouched at the end
+    fn next_block(&mut self, out: &mut Vec<i16>) -> Result<(), String>;
+    /// Make the next block start exactly at `frame`
+    fn seek(&mut self, frame: u64) -> Result<(), String>;
+}
+
+/// Plays `Blocks` as a rodio source
+struct BlockSource<B> {
+    blocks: B,
+    buffer: Vec<i16>,
+    pos: usize,
+}
+
+impl<B: Blocks> BlockSource<B> {
+    fn new(blocks: B) -> Self {
+        Self {
+            blocks,
+            buffer: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<B: Blocks> Iterator for BlockSource<B> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        while self.pos >= self.buffer.len() {
+            self.buffer.clear();
+            self.pos = 0;
+            // A corrupt block ends the track, as rodio's own decoders do
+            if self.blocks.next_block(&mut self.buffer).is_err() || self.buffer.is_empty() {
+                return None;
+            }
+        }
+        self.pos += 1;
+        Some(self.buffer[self.pos - 1])
+    }
+}
+
+impl<B: Blocks> Source for BlockSource<B> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.blocks.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.blocks.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.blocks.frames()?;
+        Some(Duration::from_secs_f64(
+            frames as f64 / self.sample_rate() as f64,
+        ))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let frame = (pos.as_secs_f64() * self.sample_rate() as f64) as u64;
+        let frame = match self.blocks.frames() {
+            Some(frames) => frame.min(frames),
+            None => frame,
+        };
+        self.blocks
+            .seek(frame)
+            .map_err(|e| SeekError::Other(Box::new(std::io::Error::other(e))))?;
+        self.buffer.clear();
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+/// Ogg Opus: symphonia demuxes the pages, opus-decoder decodes the packets
+struct Opus {
+    format: OggReader,
+    track: u32,
+    decoder: OpusMultistreamDecoder,
+    channels: u16,
+    /// Samples at the start that only prime the decoder
+    pre_skip: u64,
+    /// Granule position one past the last sample, if known
+    end: Option<u64>,
+    /// Output before this granule position is dropped
+    from: u64,
+    scratch: Vec<i16>,
+}
+
+impl Opus {
+    fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("unreadable: {e}"))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let format =
+            OggReader::try_new(stream, &FormatOptions::default()).map_err(|e| e.to_string())?;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec == CODEC_TYPE_OPUS)
+            .ok_or("no Opus stream")?;
+        let params = &track.codec_params;
+        let head = params.extra_data.as_deref().ok_or("missing Opus header")?;
+        let (channels, decoder) = opus_decoder(head)?;
+        let pre_skip = params.delay.unwrap_or(0) as u64;
+        // Without gapless trimming the reader counts the final page's
+        // discarded samples into the length and reports them as padding
+        let end = params
+            .n_frames
+            .map(|n| params.start_ts + n - params.padding.unwrap_or(0) as u64);
+
+        Ok(Self {
+            track: track.id,
+            end,
+            format,
+            decoder,
+            channels,
+            pre_skip,
+            from: pre_skip,
+            scratch: vec![0; OPUS_MAX_PACKET * channels as usize],
+        })
+    }
+}
+
+/// A decoder for the streams an OpusHead packet describes
+fn opus_decoder(head: &[u8]) -> Result<(u16, OpusMultistreamDecoder), String> {
+    if head.len() < 19 || !head.starts_with(b"OpusHead") {
+        return Err("bad Opus header".to_string());
+    }
+    let channels = head[9];
+    let (streams, coupled, mapping) = match head[18] {
+        // Mono or stereo in a single stream
+        0 => (
+            1,
+            (channels == 2) as usize,
+            (0..channels).collect::<Vec<u8>>(),
+        ),
+        _ => {
+            let mapping = head
+                .get(21..21 + channels as usize)
+                .ok_or("bad Opus channel mapping")?;
+            (head[19] as usize, head[20] as usize, mapping.to_vec())
+        }
+    };
+    let decoder =
+        OpusMultistreamDecoder::new(OPUS_RATE, channels as usize, streams, coupled, &mapping)
+            .map_err(|e| e.to_string())?;
+    Ok((channels as u16, decoder))
+}
+
+impl Blocks for Opus {
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        OPUS_RATE
+    }
+
+    fn frames(&self) -> Option<u64> {
+        self.end.map(|end| end.saturating_sub(self.pre_skip))
+    }
+
+    fn next_block(&mut self, out: &mut Vec<i16>) -> Result<(), String> {
+        let channels = self.channels as usize;
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(())
+                }
+                Err(e) => return Err(e.to_string()),
+            };
+            if packet.track_id() != self.track {
+                continue;
+            }
+            let decoded = self
+                .decoder
+                .decode(&packet.data, &mut self.scratch, false)
+                .map_err(|e| e.to_string())?;
+
+            // Keep only the part between the seek target and the end
+            let start = packet.ts;
+            let stop = start + decoded as u64;
+            let stop = self.end.map_or(stop, |end| stop.min(end));
+            let keep_from = self.from.clamp(start, stop.max(start));
+            if keep_from < stop {
+                let (a, b) = ((keep_from - start) as usize, (stop - start) as usize);
+                out.extend_from_slice(&self.scratch[a * channels..b * channels]);
+                return Ok(());
+            }
+            if self.end.is_some_and(|end| start >= end) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        let target = self.pre_skip + frame;
+        self.format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp {
+                    ts: target.saturating_sub(OPUS_PREROLL),
+                    track_id: self.track,
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        self.decoder.reset();
+        self.from = target;
+        Ok(())
+    }
+}
+
+/// WavPack, read into memory whole: the decoder works on a byte buffer
+struct WavPack {
+    bytes: Vec<u8>,
+    index: StreamIndex,
+    channels: u16,
+    sample_rate: u32,
+    /// Bytes per sample of the stored audio, or `None` for 32-bit float
+    width: Option<u8>,
+    /// Next frame to decode
+    position: u64,
+}
+
+impl WavPack {
+    fn open(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("unreadable: {e}"))?;
+        let index = StreamIndex::scan(&bytes).map_err(|e| e.to_string())?;
+        if !index.is_seekable() {
+            return Err("broken WavPack block sequence".to_string());
+        }
+        let block = oxideav_wavpack::first_audio_block(&bytes)
+            .map_err(|e| e.to_string())?
+            .ok_or("no audio in WavPack file")?;
+        let width = (!block.is_float()).then(|| block.flags().bytes_per_sample());
+        let sample_rate = block
+            .sample_rate()
+            .map_err(|e| e.to_string())?
+            .ok_or("unknown WavPack sample rate")?;
+        let channels = index.channels() as u16;
+        let position = index.first_frame();
+
+        Ok(Self {
+            bytes,
+            index,
+            channels,
+            sample_rate,
+            width,
+            position,
+        })
+    }
+}
+
+impl Blocks for WavPack {
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn frames(&self) -> Option<u64> {
+        Some(self.index.frame_count())
+    }
+
+    fn next_block(&mut self, out: &mut Vec<i16>) -> Result<(), String> {
+        // One block set at a time: the smallest unit that decodes alone
+        let Some(set) = self.index.set_for_frame(self.position) else {
+            return Ok(());
+        };
+        let frames = set.end_frame() - self.position;
+        let pcm = oxideav_wavpack::decode_range(&self.bytes, &self.index, self.position, frames)
+            .map_err(|e| e.to_string())?;
+        self.position += frames;
+        out.extend(pcm.into_iter().map(|s| match self.width {
+            Some(width) => scale_int(s, width),
+            None => scale_float(f32::from_bits(s as u32)),
+        }));
+        Ok(())
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        self.position = self.index.first_frame() + frame;
+        Ok(())
+    }
+}
+
+/// Monkey's Audio, whose frames decode independently
+struct Ape {
+    decoder: ApeDecoder<BufReader<File>>,
+    /// Next frame to decode
+    frame: u32,
+    /// Samples per channel to drop from the next frame after a seek
+    skip: usize,
+}
+
+impl Ape {
+    fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("unreadable: {e}"))?;
+        let decoder = ApeDecoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+        Ok(Self {
+            decoder,
+            frame: 0,
+            skip: 0,
+        })
+    }
+}
+
+impl Blocks for Ape {
+    fn channels(&self) -> u16 {
+        self.decoder.info().channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.info().sample_rate
+    }
+
+    fn frames(&self) -> Option<u64> {
+        Some(self.decoder.info().total_samples)
+    }
+
+    fn next_block(&mut self, out: &mut Vec<i16>) -> Result<(), String> {
+        if self.frame >= self.decoder.total_frames() {
+            return Ok(());
+        }
+        let bytes = self
+            .decoder
+            .decode_frame(self.frame)
+            .map_err(|e| e.to_string())?;
+        self.frame += 1;
+
+        let info = self.decoder.info();
+        let width = info.bytes_per_sample.max(1) as usize;
+        let skip = std::mem::take(&mut self.skip) * info.block_align as usize;
+        out.extend(
+            bytes
+                .get(skip..)
+                .unwrap_or_default()
+                .chunks_exact(width)
+                .map(|b| {
+                    match width {
+                        // 8-bit PCM is unsigned
+                        1 => ((b[0] as i16) - 128) << 8,
+                        // Little-endian; the top two bytes carry what i16 holds
+                        _ => i16::from_le_bytes([b[width - 2], b[width - 1]]),
+                    }
+                }),
+        );
+        Ok(())
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        let pos = self.decoder.seek(frame).map_err(|e| e.to_string())?;
+        self.frame = pos.frame_index;
+        self.skip = pos.skip_samples as usize;
+        Ok(())
+    }
+}
+
+/// A sample stored `width` bytes wide, as 16 bits
+fn scale_int(sample: i32, width: u8) -> i16 {
+    match width {
+        1 => (sample << 8) as i16,
+        2 => sample as i16,
+        w => (sample >> (8 * (w as u32 - 2))) as i16,
+    }
+}
+
+fn scale_float(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
@@ -0,0 +1,154 @@
+/// A column of the library's track list. `name` is the query field it
+/// shows and sorts by.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Column {
+    pub name: &'static str,
+    pub header: &'static str,
+    pub width: Width,
+    /// Numbers line up on the right
+    pub right_align: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Width {
+    Fixed(u16),
+    /// A share of the space the fixed columns leave, up to a maximum
+    Share { weight: u16, max: u16 },
+}
+
+const fn text(name: &'static str, header: &'static str, weight: u16, max: u16) -> Column {
+    Column {
+        name,
+        header,
+        width: Width::Share { weight, max },
+        right_align: false,
+    }
+}
+
+const fn fixed(name: &'static str, header: &'static str, width: u16, right_align: bool) -> Column {
+    Column {
+        name,
+        header,
+        width: Width::Fixed(width),
+        right_align,
+    }
+}
+
+/// Every column that can be shown, in the order the column editor lists them
+pub const ALL: [Column; 16] = [
+    fixed("track", "#", 3, true),
+    text("title", "Title", 5, 50),
+    text("artist", "Artist", 3, 30),
+    text("album", "Album", 3, 25),
+    text("albumartist", "Album artist", 3, 25),
+    text("composer", "Composer", 2, 20),
+    text("genre", "Genre", 2, 16),
+    fixed("year", "Year", 4, true),
+    fixed("disc", "Disc", 4, true),
+    fixed("format", "Format", 6, false),
+    fixed("bitrate", "Bitrate", 8, true),
+    fixed("samplerate", "Rate", 6, true),
+    fixed("plays", "Plays", 5, true),
+    fixed("rating", "Rating", 6, false),
+    fixed("lastplayed", "Played", 8, true),
+    fixed("duration", "Time", 7, true),
+];
+
+/// Columns shown until the config says otherwise
+pub const DEFAULT: [&str; 4] = ["title", "artist", "album", "duration"];
+
+pub fn find(name: &str) -> Option<&'static Column> {
+    ALL.iter().find(|c| c.name == name)
+}
+
+/// The configured column names that exist, in order; the defaults when
+/// none do
+pub fn parse(names: &[String]) -> Vec<&'static Column> {
+    let mut columns: Vec<&'static Column> = Vec::new();
+    for column in names.iter().filter_map(|n| find(n)) {
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    if columns.is_empty() {
+        columns = DEFAULT.iter().filter_map(|n| find(n)).collect();
+    }
+    columns
+}
+
+/// The column the track list is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortColumn {
+    pub column: &'static Column,
+    pub descending: bool,
+}
+
+impl SortColumn {
+    /// "year", or "-year" for descending, as in `--sort`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (name, descending) = match text.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (text, false),
+        };
+        Some(Self {
+            column: find(name)?,
+            descending,
+        })
+    }
+
+    pub fn to_config(self) -> String {
+        format!("{}{}", if self.descending { "-" } else { "" }, self.column.name)
+    }
+
+    /// Ascending, then descending, then unsorted: what a click on `column`'s
+    /// header does after `current`
+    pub fn cycle(current: Option<Self>, column: &'static Column) -> Option<Self> {
+        match current {
+            Some(sort) if sort.column == column && sort.descending => None,
+            Some(sort) if sort.column == column => Some(Self {
+                column,
+                descending: true,
+            }),
+            _ => Some(Self {
+                column,
+                descending: false,
+            }),
+        }
+    }
+}
+
+/// Widths of `columns` in `width` cells, leaving one cell between columns.
+/// Shared columns too narrow to read get 0.
+pub fn layout(columns: &[&'static Column], width: u16) -> Vec<u16> {
+    let gaps = columns.len().saturating_sub(1) as u16;
+    let fixed: u16 = columns
+        .iter()
+        .map(|c| match c.width {
+            Width::Fixed(w) => w,
+            Width::Share { .. } => 0,
+        })
+        .sum();
+    let free = width.saturating_sub(fixed + gaps) as u32;
+    let weights: u32 = columns
+        .iter()
+        .map(|c| match c.width {
+            Width::Share { weight, .. } => weight as u32,
+            Width::Fixed(_) => 0,
+        })
+        .sum();
+    columns
+        .iter()
+        .map(|c| match c.width {
+            Width::Fixed(w) => w,
+            Width::Share { weight, max } => {
+                let share = (free * weight as u32 / weights.max(1)) as u16;
+                let share = share.min(max);
+                if share > 5 {
+                    share
+                } else {
+                    0
+                }
+            }
+        })
+        .collect()
+}
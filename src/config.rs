@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::columns;
+use crate::shuffle::ShuffleMode;
+use crate::smart::SmartPlaylist;
+
+/// User settings read from `~/.tunebox/config.json`. Every field has a
+/// default, so the file only needs the keys being changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Sidecar image names tried when a track has no embedded art, in order.
+    /// Matched case-insensitively; `*` as the extension matches any image type.
+    pub cover_files: Vec<String>,
+    /// Additional file extensions to consider when scanning (e.g. "m4r", "mka").
+    /// Files are still sniffed, so only decodable content is added.
+    pub extra_extensions: Vec<String>,
+    /// Gitignore-style globs, relative to the library root, to skip when scanning
+    pub exclude: Vec<String>,
+    /// Hide tracks shorter than this many seconds (0 = show everything)
+    pub min_duration: f64,
+    /// Hide lesser copies of duplicated tracks (same artist, title and
+    /// duration), keeping the lossless or highest-bitrate one
+    pub hide_duplicates: bool,
+    /// Layouts, relative to the library root, that fill in tags a file is
+    /// missing. The first pattern that fits wins. Fields: %artist%, %album%,
+    /// %albumartist%, %title%, %track%, %disc%, %year%, %genre%, %ignore%.
+    pub path_patterns: Vec<String>,
+    /// Layout used by `tunebox organize` and the `O` key, relative to the
+    /// library root. Fields: {albumartist}, {artist}, {album}, {title},
+    /// {track}, {disc}, {year}, {genre}, {composer}, {ext}; `{track:02}`
+    /// zero-pads.
+    pub organize_template: String,
+    /// Embedded pictures larger than this many kilobytes count as oversized
+    /// for `tunebox cover strip` and the strip action in the player
+    pub max_cover_kb: u64,
+    /// Plays kept in `~/.tunebox/history.json` for previous/next and the
+    /// Recent view
+    pub history_size: usize,
+    /// Shuffle mode used by `--shuffle` and the remote, and the first one `s`
+    /// switches to: "tracks", "albums", "artist-spread" or "weighted"
+    pub shuffle_mode: ShuffleMode,
+    /// Restore the last session on startup, as if `--resume` were given
+    pub resume: bool,
+    /// Files at least this many seconds long (audiobooks, podcasts, mixes)
+    /// remember where they were stopped and pick up there next time
+    pub resume_min_duration: f64,
+    /// Playlists of whatever matches a query, listed in the Playlists view,
+    /// e.g. `{"name": "Old jazz", "query": "genre:jazz year:<1970 sort:random limit:50"}`
+    pub smart_playlists: Vec<SmartPlaylist>,
+    /// Columns of the library's track list, left to right: "track", "title",
+    /// "artist", "album", "albumartist", "composer", "genre", "year", "disc",
+    /// "format", "bitrate", "samplerate", "plays", "rating", "lastplayed",
+    /// "duration". Saved here when changed with `L`.
+    pub columns: Vec<String>,
+    /// Column the track list is sorted by, with a leading `-` for descending
+    /// (e.g. "-year"); empty keeps the library order
+    pub column_sort: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cover_files: vec![
+                "cover.*".to_string(),
+                "folder.*".to_string(),
+                "front.*".to_string(),
+                "album.*".to_string(),
+                "albumart.*".to_string(),
+            ],
+            extra_extensions: Vec::new(),
+            exclude: Vec::new(),
+            min_duration: 0.0,
+            hide_duplicates: false,
+            path_patterns: vec![
+                "%artist%/%album%/%track% - %title%".to_string(),
+                "%artist%/%album%/%title%".to_string(),
+                "%artist% - %title%".to_string(),
+            ],
+            organize_template: "{albumartist}/{year} - {album}/{disc}{track:02} {title}.{ext}"
+                .to_string(),
+            max_cover_kb: 512,
+            history_size: 500,
+            shuffle_mode: ShuffleMode::Tracks,
+            resume: false,
+            resume_min_duration: 1200.0,
+            smart_playlists: Vec::new(),
+            columns: columns::DEFAULT.iter().map(|c| c.to_string()).collect(),
+            column_sort: String::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults when it is missing.
+    /// A malformed file is reported and ignored rather than aborting startup.
+    pub fn load() -> Self {
+        let Some(path) = config_path() else {
+            return Self::default();
+        };
+        let Ok(data) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&data) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Ignoring {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Set one key of the config file, keeping the rest of it as written
+pub fn update(key: &str, value: serde_json::Value) -> Result<()> {
+    let path = config_path().context("No home directory")?;
+    let mut config = match std::fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data)
+            .with_context(|| format!("{} isn't valid JSON", path.display()))?,
+        Err(_) => serde_json::Value::Object(Default::default()),
+    };
+    let object = config
+        .as_object_mut()
+        .with_context(|| format!("{} isn't a JSON object", path.display()))?;
+    object.insert(key.to_string(), value);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(&config)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Directory for tunebox's config, caches and saved state
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".tunebox"))
+}
+
+fn config_path() -> Option<PathBuf> {
+    data_dir().map(|d| d.join("config.json"))
+}
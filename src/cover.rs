@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::metadata;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif", "bmp"];
+
+/// Name of the sidecar written by `extract_cover`
+pub const EXTRACTED_COVER: &str = "cover.jpg";
+
+/// Resolve cover art for a track: embedded front cover, then any embedded
+/// picture (both via `read_metadata`), then the first sidecar image in the
+/// track's folder matching `patterns`.
+pub fn resolve_cover(track_path: &Path, patterns: &[String]) -> Option<Vec<u8>> {
+    if let Some(data) = metadata::read_metadata(track_path)
+        .ok()
+        .and_then(|m| m.album_art)
+    {
+        return Some(data);
+    }
+    let dir = track_path.parent()?;
+    let sidecar = find_sidecar(dir, patterns)?;
+    std::fs::read(sidecar).ok()
+}
+
+/// First image in `dir` matching one of `patterns`, honouring pattern order
+pub fn find_sidecar(dir: &Path, patterns: &[String]) -> Option<PathBuf> {
+    let images = images_in(dir);
+    patterns
+        .iter()
+        .find_map(|pattern| images.iter().find(|p| matches_pattern(p, pattern)))
+        .cloned()
+}
+
+/// Every image in `dir` matching any of `patterns`
+pub fn find_sidecars(dir: &Path, patterns: &[String]) -> Vec<PathBuf> {
+    images_in(dir)
+        .into_iter()
+        .filter(|p| patterns.iter().any(|pattern| matches_pattern(p, pattern)))
+        .collect()
+}
+
+/// Save the first embedded cover among `tracks` as `cover.jpg` in the first
+/// track's folder, converting other image types to JPEG. Returns the file
+/// written, or None when no track has embedded art.
+pub fn extract_cover(tracks: &[&Path], overwrite: bool) -> Result<Option<PathBuf>> {
+    let Some(dir) = tracks.first().and_then(|t| t.parent()) else {
+        return Ok(None);
+    };
+    let target = dir.join(EXTRACTED_COVER);
+    if target.exists() && !overwrite {
+        bail!("{} already exists", target.display());
+    }
+    let Some(data) = tracks
+        .iter()
+        .find_map(|t| metadata::read_metadata(t).ok().and_then(|m| m.album_art))
+    else {
+        return Ok(None);
+    };
+
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        std::fs::write(&target, &data)
+    } else {
+        let img = image::load_from_memory(&data).context("Embedded cover can't be decoded")?;
+        img.to_rgb8()
+            .save_with_format(&target, image::ImageFormat::Jpeg)
+            .map_err(std::io::Error::other)
+    }
+    .with_context(|| format!("Failed to write {}", target.display()))?;
+    Ok(Some(target))
+}
+
+fn images_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && is_image(p))
+        .collect();
+    images.sort();
+    images
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// "cover.jpg" matches exactly; "cover.*" matches any image extension
+fn matches_pattern(path: &Path, pattern: &str) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let name = name.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_suffix(".*") {
+        Some(stem) => path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .is_some_and(|s| s.eq_ignore_ascii_case(stem)),
+        None => name == pattern,
+    }
+}
@@ -0,0 +1,372 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// CUE sheet times count frames, 75 to the second as on a CD
+const FRAMES_PER_SECOND: f64 = 75.0;
+
+/// Where a virtual track sits in the file it was cut from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CueRange {
+    /// The `.cue` file the track was read from
+    pub sheet: PathBuf,
+    /// Track number in the sheet
+    pub number: u32,
+    /// Seconds from the start of the file
+    pub start: f64,
+    /// Where the next track of the same file begins; `None` runs to the end
+    pub end: Option<f64>,
+}
+
+/// One `TRACK` of a sheet
+#[derive(Debug, Clone)]
+pub struct CueTrack {
+    /// The audio file it plays from
+    pub file: PathBuf,
+    pub range: CueRange,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+}
+
+/// A parsed `.cue` file. Sheet-level fields describe the album.
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub date: Option<String>,
+    pub genre: Option<String>,
+    pub disc_number: Option<u32>,
+    pub tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// Tracks cut from `file`, in start order
+    pub fn tracks_for<'a>(&'a self, file: &'a Path) -> impl Iterator<Item = &'a CueTrack> {
+        self.tracks.iter().filter(move |t| t.file == file)
+    }
+}
+
+/// Parse the sheet at `path`. `FILE` entries are resolved against its
+/// folder; one that doesn't exist is matched to an audio file in
+/// `candidates` with the same stem, since rippers often name the image
+/// `CDImage.wav` and it is converted to FLAC later.
+pub fn read_cue(path: &Path, candidates: &[PathBuf]) -> Result<CueSheet> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut sheet = parse(&decode_text(&bytes), dir)?;
+    for track in &mut sheet.tracks {
+        track.range.sheet = path.to_path_buf();
+        if !track.file.exists() {
+            if let Some(found) = same_stem(&track.file, candidates) {
+                track.file = found;
+            }
+        }
+    }
+    Ok(sheet)
+}
+
+/// Sheets and playlists are usually UTF-8 (maybe with a BOM) but older
+/// ones are often Latin-1; any invalid UTF-8 is taken as the latter
+pub fn decode_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+fn same_stem(file: &Path, candidates: &[PathBuf]) -> Option<PathBuf> {
+    let stem = file.file_stem()?;
+    let dir = file.parent()?;
+    candidates
+        .iter()
+        .find(|c| c.parent() == Some(dir) && c.file_stem() == Some(stem))
+        .cloned()
+}
+
+fn parse(text: &str, dir: &Path) -> Result<CueSheet> {
+    let mut sheet = CueSheet::default();
+    let mut file: Option<PathBuf> = None;
+    // Index 01 may be missing while a track is being read; it's required
+    // by the time the next one starts
+    let mut current: Option<(CueTrack, Option<f64>)> = None;
+
+    fn finish(sheet: &mut CueSheet, current: Option<(CueTrack, Option<f64>)>) -> Result<()> {
+        if let Some((mut track, start)) = current {
+            let Some(start) = start else {
+                bail!("track {} has no INDEX 01", track.range.number);
+            };
+            track.range.start = start;
+            sheet.tracks.push(track);
+        }
+        Ok(())
+    }
+
+    for (line_no, line) in text.lines().enumerate() {
+        let words = split_words(line);
+        let Some(command) = words.first() else {
+            continue;
+        };
+        let arg = |i: usize| words.get(i).cloned().filter(|w| !w.is_empty());
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                let name = arg(1).with_context(|| format!("line {}: FILE without a name", line_no + 1))?;
+                file = Some(dir.join(name));
+            }
+            "TRACK" => {
+                finish(&mut sheet, current.take())?;
+                let Some(file) = file.clone() else {
+                    bail!("line {}: TRACK before any FILE", line_no + 1);
+                };
+                let number = arg(1)
+                    .and_then(|n| n.parse().ok())
+                    .with_context(|| format!("line {}: bad track number", line_no + 1))?;
+                // Data tracks on mixed-mode CDs aren't audio
+                let audio = arg(2).is_none_or(|t| t.eq_ignore_ascii_case("AUDIO"));
+                if audio {
+                    current = Some((
+                        CueTrack {
+                            file,
+                            range: CueRange {
+                                sheet: PathBuf::new(),
+                                number,
+                                start: 0.0,
+                                end: None,
+                            },
+                            title: None,
+                            performer: None,
+                            songwriter: None,
+                        },
+                        None,
+                    ));
+                }
+            }
+            "INDEX" => {
+                if let Some((_, start)) = current.as_mut() {
+                    // Index 00 is the pregap, which belongs to the previous track
+                    if arg(1).and_then(|n| n.parse::<u32>().ok()) == Some(1) {
+                        let time = arg(2).as_deref().and_then(parse_time).with_context(|| {
+                            format!("line {}: bad INDEX time", line_no + 1)
+                        })?;
+                        *start = Some(time);
+                    }
+                }
+            }
+            "TITLE" | "PERFORMER" | "SONGWRITER" => {
+                let value = arg(1);
+                let field = match (current.as_mut(), command.to_ascii_uppercase().as_str()) {
+                    (Some((t, _)), "TITLE") => &mut t.title,
+                    (Some((t, _)), "PERFORMER") => &mut t.performer,
+                    (Some((t, _)), _) => &mut t.songwriter,
+                    (None, "TITLE") => &mut sheet.title,
+                    (None, "PERFORMER") => &mut sheet.performer,
+                    (None, _) => &mut sheet.songwriter,
+                };
+                *field = value;
+            }
+            "REM" if current.is_none() => {
+                let value = arg(2);
+                match arg(1).map(|k| k.to_ascii_uppercase()).as_deref() {
+                    Some("DATE") => sheet.date = value,
+                    Some("GENRE") => sheet.genre = value,
+                    Some("DISCNUMBER") => sheet.disc_number = value.and_then(|v| v.parse().ok()),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    finish(&mut sheet, current)?;
+
+    if sheet.tracks.is_empty() {
+        bail!("no audio tracks");
+    }
+    // Each track ends where the next one of the same file starts
+    sheet.tracks.sort_by(|a, b| {
+        a.file
+            .cmp(&b.file)
+            .then_with(|| a.range.start.total_cmp(&b.range.start))
+    });
+    for i in 1..sheet.tracks.len() {
+        if sheet.tracks[i].file == sheet.tracks[i - 1].file {
+            sheet.tracks[i - 1].range.end = Some(sheet.tracks[i].range.start);
+        }
+    }
+    sheet.tracks.sort_by_key(|t| t.range.number);
+    Ok(sheet)
+}
+
+/// Split a line into words, keeping "quoted strings" together
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            words.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// `mm:ss:ff` to seconds
+fn parse_time(time: &str) -> Option<f64> {
+    let mut parts = time.split(':').map(|p| p.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames as f64 >= FRAMES_PER_SECOND {
+        return None;
+    }
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / FRAMES_PER_SECOND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM GENRE Jazz
+REM DATE 1959
+PERFORMER "Miles Davis"
+TITLE "Kind of Blue"
+FILE "image.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "So What"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Freddie Freeloader"
+    PERFORMER "Miles Davis Sextet"
+    INDEX 00 09:20:00
+    INDEX 01 09:22:37
+  TRACK 03 AUDIO
+    INDEX 01 19:00:00
+"#;
+
+    #[test]
+    fn parse_time_counts_frames() {
+        assert_eq!(parse_time("00:00:00"), Some(0.0));
+        assert_eq!(parse_time("01:02:00"), Some(62.0));
+        assert_eq!(parse_time("00:00:15"), Some(0.2));
+        // Minutes may run past 59 on long images
+        assert_eq!(parse_time("75:00:00"), Some(4500.0));
+        assert_eq!(parse_time("00:60:00"), None);
+        assert_eq!(parse_time("00:00:75"), None);
+        assert_eq!(parse_time("00:00"), None);
+        assert_eq!(parse_time("00:00:00:00"), None);
+        assert_eq!(parse_time("aa:00:00"), None);
+    }
+
+    #[test]
+    fn pregap_belongs_to_the_previous_track() {
+        let sheet = parse(SHEET, Path::new("/music")).unwrap();
+        let ranges: Vec<(u32, f64, Option<f64>)> = sheet
+            .tracks
+            .iter()
+            .map(|t| (t.range.number, t.range.start, t.range.end))
+            .collect();
+        let second = 9.0 * 60.0 + 22.0 + 37.0 / 75.0;
+        assert_eq!(
+            ranges,
+            [
+                (1, 0.0, Some(second)),
+                (2, second, Some(1140.0)),
+                (3, 1140.0, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn sheet_and_track_fields() {
+        let sheet = parse(SHEET, Path::new("/music")).unwrap();
+        assert_eq!(sheet.title.as_deref(), Some("Kind of Blue"));
+        assert_eq!(sheet.performer.as_deref(), Some("Miles Davis"));
+        assert_eq!(sheet.date.as_deref(), Some("1959"));
+        assert_eq!(sheet.genre.as_deref(), Some("Jazz"));
+        assert_eq!(
+            sheet.tracks[1].performer.as_deref(),
+            Some("Miles Davis Sextet")
+        );
+        assert_eq!(sheet.tracks[2].title, None);
+        assert_eq!(sheet.tracks[0].file, Path::new("/music/image.flac"));
+    }
+
+    #[test]
+    fn each_file_ends_on_its_own() {
+        let text = "FILE a.wav WAVE\n TRACK 1 AUDIO\n  INDEX 01 00:00:00\n TRACK 2 AUDIO\n  INDEX 01 01:00:00\n\
+                    FILE b.wav WAVE\n TRACK 3 AUDIO\n  INDEX 01 00:00:00\n";
+        let sheet = parse(text, Path::new("")).unwrap();
+        let ends: Vec<Option<f64>> = sheet.tracks.iter().map(|t| t.range.end).collect();
+        assert_eq!(ends, [Some(60.0), None, None]);
+        assert_eq!(sheet.tracks_for(Path::new("b.wav")).count(), 1);
+    }
+
+    #[test]
+    fn data_tracks_are_skipped() {
+        let text = "FILE a.bin BINARY\n TRACK 01 MODE1/2352\n  INDEX 01 00:00:00\n\
+                    TRACK 02 AUDIO\n  INDEX 01 02:00:00\n";
+        let sheet = parse(text, Path::new("")).unwrap();
+        assert_eq!(sheet.tracks.len(), 1);
+        assert_eq!(sheet.tracks[0].range.number, 2);
+    }
+
+    #[test]
+    fn broken_sheets_are_errors() {
+        assert!(parse("TRACK 01 AUDIO\n INDEX 01 00:00:00\n", Path::new("")).is_err());
+        assert!(parse(
+            "FILE a.wav WAVE\n TRACK 01 AUDIO\n INDEX 00 00:00:00\n",
+            Path::new("")
+        )
+        .is_err());
+        assert!(parse("FILE a.wav WAVE\n TRACK xx AUDIO\n", Path::new("")).is_err());
+        assert!(parse(
+            "FILE a.wav WAVE\n TRACK 01 AUDIO\n INDEX 01 00:61:00\n",
+            Path::new("")
+        )
+        .is_err());
+        assert!(parse("REM only a comment\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn latin1_and_bom() {
+        assert_eq!(
+            decode_text(b"\xef\xbb\xbfTITLE \"Caf\xc3\xa9\""),
+            "TITLE \"Café\""
+        );
+        assert_eq!(decode_text(b"TITLE \"Caf\xe9\""), "TITLE \"Café\"");
+    }
+
+    #[test]
+    fn split_words_keeps_quoted_names() {
+        assert_eq!(
+            split_words("  FILE \"My Album.flac\" WAVE"),
+            ["FILE", "My Album.flac", "WAVE"]
+        );
+        assert_eq!(split_words("TITLE \"\""), ["TITLE", ""]);
+    }
+
+    #[test]
+    fn missing_file_matched_by_stem() {
+        let candidates = vec![
+            PathBuf::from("/music/CDImage.flac"),
+            PathBuf::from("/other/CDImage.wav"),
+        ];
+        assert_eq!(
+            same_stem(Path::new("/music/CDImage.wav"), &candidates),
+            Some(PathBuf::from("/music/CDImage.flac"))
+        );
+        assert_eq!(same_stem(Path::new("/music/Other.wav"), &candidates), None);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+
+/// Folders opened at most by one reveal, so a big selection doesn't open
+/// a window per album
+pub const MAX_REVEAL: usize = 5;
+
+/// Put `text` on the clipboard with the OSC 52 escape, which terminals
+/// pass to the system clipboard, also over SSH
+pub fn copy(text: &str) -> Result<()> {
+    let mut out = std::io::stdout();
+    write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Show the folders holding `paths` in the file manager, selecting the
+/// file where the platform can. Returns how many were opened.
+pub fn reveal(paths: &[PathBuf]) -> Result<usize> {
+    let folders: BTreeSet<&Path> = paths.iter().filter_map(|p| p.parent()).collect();
+    let mut opened = 0;
+    for folder in folders.into_iter().take(MAX_REVEAL) {
+        let file = paths.iter().find(|p| p.parent() == Some(folder));
+        let mut command = opener(folder, file.map(PathBuf::as_path));
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Couldn't open {}", folder.display()))?;
+        // Reap it in the background rather than leaving a zombie
+        std::thread::spawn(move || child.wait());
+        opened += 1;
+    }
+    Ok(opened)
+}
+
+#[cfg(target_os = "macos")]
+fn opener(folder: &Path, file: Option<&Path>) -> Command {
+    let mut command = Command::new("open");
+    match file {
+        Some(file) => command.arg("-R").arg(file),
+        None => command.arg(folder),
+    };
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn opener(folder: &Path, file: Option<&Path>) -> Command {
+    let mut command = Command::new("explorer");
+    match file {
+        Some(file) => command.arg(format!("/select,{}", file.display())),
+        None => command.arg(folder),
+    };
+    command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn opener(folder: &Path, _file: Option<&Path>) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(folder);
+    command
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::audio;
+use crate::cover;
+use crate::library::{self, ScanOptions};
+use crate::metadata::{self, TrackMetadata};
+
+/// A problem with a single file
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileIssue {
+    Undecodable { reason: String },
+    UnreadableTags { error: String },
+    ZeroDuration,
+    MissingTitle,
+    MissingArtist,
+    MissingAlbum,
+}
+
+impl fmt::Display for FileIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undecodable { reason } => write!(f, "can't be decoded: {}", reason),
+            Self::UnreadableTags { error } => write!(f, "tags can't be read: {}", error),
+            Self::ZeroDuration => write!(f, "duration is zero or unknown"),
+            Self::MissingTitle => write!(f, "missing title"),
+            Self::MissingArtist => write!(f, "missing artist"),
+            Self::MissingAlbum => write!(f, "missing album"),
+        }
+    }
+}
+
+/// A problem shared by the tracks of one album
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlbumIssue {
+    InconsistentAlbumArtist { values: Vec<String> },
+    InconsistentYear { values: Vec<u32> },
+    MissingTrackNumbers { files: Vec<PathBuf> },
+    DuplicateTrackNumber { disc: u32, track: u32, files: Vec<PathBuf> },
+    MissingCover,
+}
+
+impl fmt::Display for AlbumIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InconsistentAlbumArtist { values } => {
+                write!(f, "inconsistent album artist: {}", values.join(", "))
+            }
+            Self::InconsistentYear { values } => {
+                let years: Vec<String> = values.iter().map(|y| y.to_string()).collect();
+                write!(f, "inconsistent year: {}", years.join(", "))
+            }
+            Self::MissingTrackNumbers { files } => {
+                write!(f, "{} track(s) without a track number", files.len())
+            }
+            Self::DuplicateTrackNumber { disc, track, files } => write!(
+                f,
+                "disc {} track {} used by {} files",
+                disc,
+                track,
+                files.len()
+            ),
+            Self::MissingCover => write!(f, "no embedded or sidecar cover"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub issues: Vec<FileIssue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumReport {
+    pub album: String,
+    pub directory: PathBuf,
+    pub issues: Vec<AlbumIssue>,
+}
+
+/// Everything `tunebox doctor` found. Only files and albums with at least
+/// one issue are listed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub root: PathBuf,
+    pub files_checked: usize,
+    pub albums_checked: usize,
+    pub files: Vec<FileReport>,
+    pub albums: Vec<AlbumReport>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.files.is_empty() && self.albums.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Checked {} files in {} albums under {}",
+            self.files_checked,
+            self.albums_checked,
+            self.root.display()
+        )?;
+        if self.is_clean() {
+            return writeln!(f, "No problems found.");
+        }
+        if !self.files.is_empty() {
+            writeln!(f, "\nFiles ({}):", self.files.len())?;
+            for file in &self.files {
+                writeln!(f, "  {}", file.path.display())?;
+                for issue in &file.issues {
+                    writeln!(f, "    - {}", issue)?;
+                }
+            }
+        }
+        if !self.albums.is_empty() {
+            writeln!(f, "\nAlbums ({}):", self.albums.len())?;
+            for album in &self.albums {
+                writeln!(f, "  {} ({})", album.album, album.directory.display())?;
+                for issue in &album.issues {
+                    writeln!(f, "    - {}", issue)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tags of one readable file, kept for the album checks
+struct Entry {
+    path: PathBuf,
+    meta: TrackMetadata,
+    has_art: bool,
+}
+
+/// Check every audio file under `dir`, using the same walk, ignore files and
+/// excludes as a library scan
+pub fn check(dir: &Path, options: &ScanOptions, cover_files: &[String]) -> Result<Report> {
+    let paths = library::audio_files(dir, options)?;
+    let mut files = Vec::new();
+    let mut entries = Vec::new();
+
+    for path in &paths {
+        let (issues, meta) = check_file(path);
+        if !issues.is_empty() {
+            files.push(FileReport {
+                path: path.clone(),
+                issues,
+            });
+        }
+        if let Some(mut meta) = meta {
+            // Drop the image bytes; a large library would otherwise hold
+            // every cover in memory until the album checks run
+            let has_art = meta.album_art.take().is_some();
+            entries.push(Entry {
+                path: path.clone(),
+                meta,
+                has_art,
+            });
+        }
+    }
+
+    // Albums are grouped per folder, so two releases sharing a title
+    // ("Greatest Hits") aren't reported as one inconsistent album
+    let mut groups: BTreeMap<(PathBuf, String), Vec<&Entry>> = BTreeMap::new();
+    for entry in &entries {
+        let Some(album) = entry.meta.album.as_deref() else {
+            continue;
+        };
+        let dir = entry.path.parent().unwrap_or(Path::new("")).to_path_buf();
+        groups
+            .entry((dir, album.to_lowercase()))
+            .or_default()
+            .push(entry);
+    }
+
+    let albums_checked = groups.len();
+    let albums = groups
+        .into_iter()
+        .filter_map(|((directory, _), tracks)| {
+            let issues = check_album(&directory, &tracks, cover_files);
+            (!issues.is_empty()).then(|| AlbumReport {
+                album: tracks[0].meta.album.clone().unwrap_or_default(),
+                directory,
+                issues,
+            })
+        })
+        .collect();
+
+    Ok(Report {
+        root: dir.to_path_buf(),
+        files_checked: paths.len(),
+        albums_checked,
+        files,
+        albums,
+    })
+}
+
+/// Returns the file's issues, plus its tags when they could be read
+fn check_file(path: &Path) -> (Vec<FileIssue>, Option<TrackMetadata>) {
+    let mut issues = Vec::new();
+
+    if let Err(reason) = library::sniff_format(path) {
+        issues.push(FileIssue::Undecodable { reason });
+        return (issues, None);
+    }
+
+    let meta = match metadata::read_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) => {
+            issues.push(FileIssue::UnreadableTags {
+                error: e.to_string(),
+            });
+            // Tags are gone but the audio may still be fine
+            check_decoder(path, None, &mut issues);
+            return (issues, None);
+        }
+    };
+
+    check_decoder(path, meta.duration.map(|d| d.as_secs_f64()), &mut issues);
+    if meta.title.is_none() {
+        issues.push(FileIssue::MissingTitle);
+    }
+    if meta.artist.is_none() {
+        issues.push(FileIssue::MissingArtist);
+    }
+    if meta.album.is_none() {
+        issues.push(FileIssue::MissingAlbum);
+    }
+
+    (issues, Some(meta))
+}
+
+/// Decode the start of `path` and flag it if that fails, or if neither the
+/// decoder nor the tags (`tag_duration`) give it a length
+fn check_decoder(path: &Path, tag_duration: Option<f64>, issues: &mut Vec<FileIssue>) {
+    match audio::probe_decoder(path) {
+        Ok(duration) => {
+            if !duration.or(tag_duration).is_some_and(|secs| secs > 0.0) {
+                issues.push(FileIssue::ZeroDuration);
+            }
+        }
+        Err(reason) => issues.push(FileIssue::Undecodable { reason }),
+    }
+}
+
+fn check_album(dir: &Path, tracks: &[&Entry], cover_files: &[String]) -> Vec<AlbumIssue> {
+    let mut issues = Vec::new();
+
+    let album_artists: BTreeSet<&str> = tracks
+        .iter()
+        .map(|t| t.meta.album_artist.as_deref().unwrap_or(""))
+        .collect();
+    if album_artists.len() > 1 {
+        issues.push(AlbumIssue::InconsistentAlbumArtist {
+            values: album_artists
+                .into_iter()
+                .map(|a| if a.is_empty() { "(none)" } else { a }.to_string())
+                .collect(),
+        });
+    }
+
+    let years: BTreeSet<u32> = tracks.iter().filter_map(|t| t.meta.year).collect();
+    if years.len() > 1 {
+        issues.push(AlbumIssue::InconsistentYear {
+            values: years.into_iter().collect(),
+        });
+    }
+
+    let unnumbered: Vec<PathBuf> = tracks
+        .iter()
+        .filter(|t| t.meta.track_number.is_none())
+        .map(|t| t.path.clone())
+        .collect();
+    if !unnumbered.is_empty() {
+        issues.push(AlbumIssue::MissingTrackNumbers { files: unnumbered });
+    }
+
+    let mut numbers: BTreeMap<(u32, u32), Vec<PathBuf>> = BTreeMap::new();
+    for t in tracks {
+        if let Some(track) = t.meta.track_number {
+            let disc = t.meta.disc_number.unwrap_or(1);
+            numbers.entry((disc, track)).or_default().push(t.path.clone());
+        }
+    }
+    for ((disc, track), files) in numbers {
+        if files.len() > 1 {
+            issues.push(AlbumIssue::DuplicateTrackNumber { disc, track, files });
+        }
+    }
+
+    let has_cover = tracks.iter().any(|t| t.has_art)
+        || cover::find_sidecar(dir, cover_files).is_some();
+    if !has_cover {
+        issues.push(AlbumIssue::MissingCover);
+    }
+
+    issues
+}
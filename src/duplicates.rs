@@ -0,0 +1,352 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::hash::{DefaultHasher, Hasher};
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use rodio::Source;
+use serde::Serialize;
+
+use crate::audio;
+use crate::library::Track;
+
+/// Durations further apart than this are never the same recording
+const DURATION_TOLERANCE: f64 = 2.0;
+/// Seconds of audio compared by the fingerprint
+const FINGERPRINT_SECONDS: u32 = 30;
+/// Envelope windows per second
+const FINGERPRINT_RATE: u32 = 10;
+/// Windows the envelopes may be shifted by, for rips with different lead-in
+const FINGERPRINT_MAX_LAG: usize = 5;
+/// Minimum envelope correlation for two tracks to count as one recording
+const FINGERPRINT_THRESHOLD: f64 = 0.95;
+
+/// How tracks are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum MatchMode {
+    /// Same normalized artist and title, and similar duration
+    #[default]
+    Tags,
+    /// Identical decoded audio, whatever the tags say
+    Hash,
+    /// Similar loudness envelope over the first seconds, so a FLAC and an
+    /// MP3 of the same rip match
+    Fingerprint,
+}
+
+/// Copies of one recording, as library indices. The copy to keep comes first.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub tracks: Vec<usize>,
+}
+
+impl DuplicateGroup {
+    pub fn best(&self) -> usize {
+        self.tracks[0]
+    }
+
+    /// Every copy but the best one
+    pub fn lesser(&self) -> &[usize] {
+        &self.tracks[1..]
+    }
+}
+
+/// Group copies of the same recording. Groups come out in library order of
+/// their first member; single tracks are left out.
+pub fn find_duplicates(library: &[Track], mode: MatchMode) -> Vec<DuplicateGroup> {
+    let candidates: Vec<Vec<usize>> = match mode {
+        MatchMode::Tags => {
+            let mut by_tags: HashMap<String, Vec<usize>> = HashMap::new();
+            for (i, track) in library.iter().enumerate() {
+                by_tags.entry(tag_key(track)).or_default().push(i);
+            }
+            by_tags
+                .into_values()
+                .flat_map(|indices| cluster_by_duration(library, indices))
+                .collect()
+        }
+        MatchMode::Hash => cluster_by_duration(library, (0..library.len()).collect())
+            .into_iter()
+            .flat_map(|cluster| split_by_hash(library, cluster))
+            .collect(),
+        MatchMode::Fingerprint => cluster_by_duration(library, (0..library.len()).collect())
+            .into_iter()
+            .flat_map(|cluster| split_by_fingerprint(library, cluster))
+            .collect(),
+    };
+
+    let mut groups: Vec<DuplicateGroup> = candidates
+        .into_iter()
+        .filter(|tracks| tracks.len() > 1)
+        .map(|mut tracks| {
+            tracks.sort_by(|&a, &b| {
+                quality_rank(&library[b])
+                    .cmp(&quality_rank(&library[a]))
+                    .then(a.cmp(&b))
+            });
+            DuplicateGroup { tracks }
+        })
+        .collect();
+    groups.sort_by_key(|g| g.tracks.iter().copied().min());
+    groups
+}
+
+/// One copy in a `Report`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportCopy {
+    pub path: PathBuf,
+    pub format: String,
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u8>,
+    pub duration: f64,
+    pub keep: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportGroup {
+    pub artist: String,
+    pub title: String,
+    pub copies: Vec<ReportCopy>,
+}
+
+/// What `tunebox duplicates` prints
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub tracks_checked: usize,
+    pub groups: Vec<ReportGroup>,
+}
+
+impl Report {
+    pub fn new(library: &[Track], groups: &[DuplicateGroup]) -> Self {
+        let groups = groups
+            .iter()
+            .map(|group| {
+                let best = &library[group.best()];
+                ReportGroup {
+                    artist: best.artist.clone(),
+                    title: best.title.clone(),
+                    copies: group
+                        .tracks
+                        .iter()
+                        .map(|&i| {
+                            let t = &library[i];
+                            ReportCopy {
+                                path: t.path.clone(),
+                                format: t.format.clone(),
+                                bitrate: t.bitrate,
+                                sample_rate: t.sample_rate,
+                                bit_depth: t.bit_depth,
+                                duration: t.duration,
+                                keep: i == group.best(),
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        Self {
+            tracks_checked: library.len(),
+            groups,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let extra: usize = self.groups.iter().map(|g| g.copies.len() - 1).sum();
+        writeln!(
+            f,
+            "Found {} duplicate group(s), {} extra copies, in {} tracks",
+            self.groups.len(),
+            extra,
+            self.tracks_checked
+        )?;
+        for group in &self.groups {
+            writeln!(f, "\n{} – {}", group.artist, group.title)?;
+            for copy in &group.copies {
+                let mut quality = vec![copy.format.clone()];
+                if let Some(depth) = copy.bit_depth {
+                    quality.push(format!("{}-bit", depth));
+                }
+                if let Some(rate) = copy.sample_rate {
+                    quality.push(format!("{} Hz", rate));
+                }
+                if let Some(bitrate) = copy.bitrate {
+                    quality.push(format!("{} kbps", bitrate));
+                }
+                writeln!(
+                    f,
+                    "  {} {}  [{}]",
+                    if copy.keep { "*" } else { " " },
+                    copy.path.display(),
+                    quality.join(", ")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Library indices of every copy that isn't the best of its group
+pub fn lesser_copies(groups: &[DuplicateGroup]) -> HashSet<usize> {
+    groups
+        .iter()
+        .flat_map(|g| g.lesser().iter().copied())
+        .collect()
+}
+
+pub fn is_lossless(track: &Track) -> bool {
+    matches!(
+        track.format.as_str(),
+        "FLAC" | "WAV" | "AIFF" | "ALAC"
+    )
+}
+
+/// "Prefer lossless, then highest resolution, then highest bitrate":
+/// larger ranks are better copies
+pub fn quality_rank(track: &Track) -> (bool, u8, u32, u32) {
+    (
+        is_lossless(track),
+        track.bit_depth.unwrap_or(0),
+        track.sample_rate.unwrap_or(0),
+        track.bitrate.unwrap_or(0),
+    )
+}
+
+/// Lowercased alphanumerics with single spaces, so "Don't Stop (Live)" and
+/// "dont stop live" compare equal
+fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.replace('\'', ""))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn tag_key(track: &Track) -> String {
+    format!("{}\u{1f}{}", normalize(&track.artist), normalize(&track.title))
+}
+
+/// Split `indices` into runs whose neighbouring durations are within the
+/// tolerance. Tracks with an unknown duration are dropped.
+fn cluster_by_duration(library: &[Track], mut indices: Vec<usize>) -> Vec<Vec<usize>> {
+    indices.retain(|&i| library[i].duration > 0.0);
+    indices.sort_by(|&a, &b| library[a].duration.total_cmp(&library[b].duration));
+
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut last = f64::NEG_INFINITY;
+    for i in indices {
+        let duration = library[i].duration;
+        match clusters.last_mut() {
+            Some(cluster) if duration - last <= DURATION_TOLERANCE => cluster.push(i),
+            _ => clusters.push(vec![i]),
+        }
+        last = duration;
+    }
+    clusters.retain(|c| c.len() > 1);
+    clusters
+}
+
+fn split_by_hash(library: &[Track], cluster: Vec<usize>) -> Vec<Vec<usize>> {
+    let mut by_hash: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+    for i in cluster {
+        if let Some(hash) = audio_hash(library, i) {
+            by_hash.entry(hash).or_default().push(i);
+        }
+    }
+    by_hash.into_values().collect()
+}
+
+/// Hash of the decoded samples plus the stream format, so tags and container
+/// details don't affect it
+fn audio_hash(library: &[Track], index: usize) -> Option<u64> {
+    let track = &library[index];
+    let decoder = audio::open_segment(&track.path, track.segment()).ok()?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u16(decoder.channels());
+    hasher.write_u32(decoder.sample_rate());
+    for sample in decoder {
+        hasher.write_i16(sample);
+    }
+    Some(hasher.finish())
+}
+
+/// Greedy grouping: each track joins the first group whose first member's
+/// envelope it matches
+fn split_by_fingerprint(library: &[Track], cluster: Vec<usize>) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(Vec<f64>, Vec<usize>)> = Vec::new();
+    for i in cluster {
+        let Some(print) = fingerprint(library, i) else {
+            continue;
+        };
+        match groups
+            .iter_mut()
+            .find(|(reference, _)| similarity(reference, &print) >= FINGERPRINT_THRESHOLD)
+        {
+            Some((_, members)) => members.push(i),
+            None => groups.push((print, vec![i])),
+        }
+    }
+    groups.into_iter().map(|(_, members)| members).collect()
+}
+
+/// RMS loudness per window over the opening seconds. Lossy encoding barely
+/// moves this, unlike the exact sample values.
+fn fingerprint(library: &[Track], index: usize) -> Option<Vec<f64>> {
+    let track = &library[index];
+    let decoder = audio::open_segment(&track.path, track.segment()).ok()?;
+    let channels = decoder.channels().max(1) as usize;
+    let window = (decoder.sample_rate() / FINGERPRINT_RATE).max(1) as usize * channels;
+    let limit = window * (FINGERPRINT_SECONDS * FINGERPRINT_RATE) as usize;
+
+    let mut envelope = Vec::new();
+    let mut sum = 0.0;
+    let mut count = 0;
+    for sample in decoder.take(limit) {
+        let s = sample as f64 / i16::MAX as f64;
+        sum += s * s;
+        count += 1;
+        if count == window {
+            envelope.push((sum / count as f64).sqrt());
+            sum = 0.0;
+            count = 0;
+        }
+    }
+    (envelope.len() > FINGERPRINT_MAX_LAG * 2).then_some(envelope)
+}
+
+/// Best Pearson correlation of the two envelopes over small shifts
+fn similarity(a: &[f64], b: &[f64]) -> f64 {
+    let mut best = f64::NEG_INFINITY;
+    for lag in 0..=FINGERPRINT_MAX_LAG {
+        best = best
+            .max(correlation(&a[lag.min(a.len())..], b))
+            .max(correlation(a, &b[lag.min(b.len())..]));
+    }
+    best
+}
+
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    if n < 2 {
+        return 0.0;
+    }
+    let (a, b) = (&a[..n], &b[..n]);
+    let mean_a = a.iter().sum::<f64>() / n as f64;
+    let mean_b = b.iter().sum::<f64>() / n as f64;
+    let mut cov = 0.0;
+    let mut var_a = 0.0;
+    let mut var_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a == 0.0 || var_b == 0.0 {
+        // Flat envelopes (silence) only match each other
+        return if var_a == var_b { 1.0 } else { 0.0 };
+    }
+    cov / (var_a * var_b).sqrt()
+}
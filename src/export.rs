@@ -0,0 +1,249 @@
+use std::cmp::Ordering;
+use std::fmt::Write as _;
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use crate::library::Track;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    M3u,
+}
+
+/// Columns written when no `--fields` are given for CSV
+const DEFAULT_CSV_FIELDS: &[&str] = &[
+    "path",
+    "title",
+    "artist",
+    "album",
+    "album_artist",
+    "disc_number",
+    "track_number",
+    "year",
+    "genre",
+    "duration",
+    "cue.start",
+    "cue.end",
+    "format",
+    "bitrate",
+    "sample_rate",
+];
+
+/// One `--sort` key: a field name, descending when written as `-field`
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn parse(spec: &str) -> Self {
+        match spec.strip_prefix('-') {
+            Some(field) => Self {
+                field: field.to_string(),
+                descending: true,
+            },
+            None => Self {
+                field: spec.to_string(),
+                descending: false,
+            },
+        }
+    }
+}
+
+/// Track fields in their serialized form, so field names match the JSON keys.
+/// Nested fields are addressed with dots, e.g. `musicbrainz.release_id`.
+fn track_values(tracks: &[&Track]) -> Result<Vec<Value>> {
+    tracks
+        .iter()
+        .map(|t| Ok(serde_json::to_value(t)?))
+        .collect()
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    name.split('.').try_fold(value, |v, key| v.get(key))
+}
+
+/// Whether `name` can be looked up in tracks like `sample`. A field below
+/// one that is null here (`cue.start` of a track without a CUE sheet) is
+/// taken as known.
+fn known_field(sample: &Value, name: &str) -> bool {
+    let mut value = sample;
+    for key in name.split('.') {
+        match value.get(key) {
+            Some(Value::Null) => return true,
+            Some(v) => value = v,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Fail early on typos rather than writing empty columns
+fn check_fields(sample: &Value, fields: &[String]) -> Result<()> {
+    for name in fields {
+        if !known_field(sample, name) {
+            let known: Vec<&str> = sample
+                .as_object()
+                .map(|o| o.keys().map(|k| k.as_str()).collect())
+                .unwrap_or_default();
+            bail!("Unknown field '{}'. Fields: {}", name, known.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Missing values sort last in both directions; text compares case-insensitively
+fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let a = a.filter(|v| !v.is_null());
+    let b = b.filter(|v| !v.is_null());
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(Value::Number(x)), Some(Value::Number(y))) => x
+            .as_f64()
+            .unwrap_or(0.0)
+            .total_cmp(&y.as_f64().unwrap_or(0.0)),
+        (Some(Value::Bool(x)), Some(Value::Bool(y))) => x.cmp(y),
+        (Some(x), Some(y)) => plain(x).to_lowercase().cmp(&plain(y).to_lowercase()),
+    }
+}
+
+/// Sort `tracks` by `keys` in priority order; the incoming (library) order
+/// breaks ties
+pub fn sort(tracks: &mut Vec<&Track>, keys: &[SortKey]) -> Result<()> {
+    if keys.is_empty() || tracks.is_empty() {
+        return Ok(());
+    }
+    let values = track_values(tracks)?;
+    let names: Vec<String> = keys.iter().map(|k| k.field.clone()).collect();
+    check_fields(&values[0], &names)?;
+
+    let mut order: Vec<usize> = (0..tracks.len()).collect();
+    order.sort_by(|&a, &b| {
+        keys.iter()
+            .map(|key| {
+                let (va, vb) = (field(&values[a], &key.field), field(&values[b], &key.field));
+                let missing = |v: Option<&Value>| v.is_none_or(|v| v.is_null());
+                let ord = compare_values(va, vb);
+                // Keep missing values last even when descending
+                if key.descending && !missing(va) && !missing(vb) {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    *tracks = order.into_iter().map(|i| tracks[i]).collect();
+    Ok(())
+}
+
+/// Render `tracks` in `format`. `fields` selects and orders the JSON keys or
+/// CSV columns; M3U always writes duration, artist, title and path, and
+/// leaves out tracks cut from a larger file by a CUE sheet, which other
+/// players would play as the whole file.
+pub fn render(tracks: &[&Track], format: ExportFormat, fields: &[String]) -> Result<String> {
+    match format {
+        ExportFormat::Json => to_json(tracks, fields),
+        ExportFormat::Csv => to_csv(tracks, fields),
+        ExportFormat::M3u => Ok(to_m3u(tracks)),
+    }
+}
+
+fn to_json(tracks: &[&Track], fields: &[String]) -> Result<String> {
+    let values = track_values(tracks)?;
+    if fields.is_empty() {
+        return Ok(serde_json::to_string_pretty(&values)? + "\n");
+    }
+    if let Some(first) = values.first() {
+        check_fields(first, fields)?;
+    }
+    let selected: Vec<Selected> = values
+        .iter()
+        .map(|v| Selected { value: v, fields })
+        .collect();
+    Ok(serde_json::to_string_pretty(&selected)? + "\n")
+}
+
+/// The chosen fields of one track, serialized in the order they were asked for
+struct Selected<'a> {
+    value: &'a Value,
+    fields: &'a [String],
+}
+
+impl Serialize for Selected<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for name in self.fields {
+            map.serialize_entry(name, field(self.value, name).unwrap_or(&Value::Null))?;
+        }
+        map.end()
+    }
+}
+
+fn to_csv(tracks: &[&Track], fields: &[String]) -> Result<String> {
+    let fields: Vec<String> = if fields.is_empty() {
+        DEFAULT_CSV_FIELDS.iter().map(|f| f.to_string()).collect()
+    } else {
+        fields.to_vec()
+    };
+    let values = track_values(tracks)?;
+    if let Some(first) = values.first() {
+        check_fields(first, &fields)?;
+    }
+
+    let mut out = String::new();
+    let header: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for v in &values {
+        let row: Vec<String> = fields
+            .iter()
+            .map(|name| csv_escape(&field(v, name).map(plain).unwrap_or_default()))
+            .collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn to_m3u(tracks: &[&Track]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for t in tracks.iter().filter(|t| t.cue.is_none()) {
+        let _ = writeln!(
+            out,
+            "#EXTINF:{},{} - {}\n{}",
+            t.duration.round() as i64,
+            t.artist,
+            t.title,
+            t.path.display()
+        );
+    }
+    out
+}
+
+/// A value as a bare cell: strings unquoted, null empty, objects as JSON
+fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Quote cells containing separators, quotes or line breaks (RFC 4180)
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::library::Track;
+
+/// One play, by library index
+#[derive(Debug, Clone, Copy)]
+pub struct Play {
+    pub track: usize,
+    /// Seconds since the epoch
+    pub played_at: u64,
+}
+
+/// Tracks in the order they actually played, oldest first. `prev_track`
+/// walks back through it and `next_track` forward again before picking
+/// anything new.
+#[derive(Debug, Clone)]
+pub struct History {
+    plays: Vec<Play>,
+    /// How many plays back from the newest the listener has stepped
+    back: usize,
+    /// Tracks stepped back onto in library order once `back` ran out, the
+    /// one playing last. They didn't play then, so they aren't logged.
+    earlier: Vec<usize>,
+    limit: usize,
+    /// Saved plays of tracks not in this library (another folder, or a
+    /// single file was opened), written back untouched
+    unresolved: Vec<SavedPlay>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedPlay {
+    path: PathBuf,
+    played_at: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedHistory {
+    plays: Vec<SavedPlay>,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            plays: Vec::new(),
+            back: 0,
+            earlier: Vec::new(),
+            limit,
+            unresolved: Vec::new(),
+        }
+    }
+
+    /// Log a newly started track. Replays of the track that just played
+    /// (repeat one, restarting) aren't logged twice.
+    pub fn record(&mut self, track: usize) {
+        self.back = 0;
+        self.earlier.clear();
+        if self.plays.last().is_some_and(|p| p.track == track) {
+            return;
+        }
+        self.plays.push(Play {
+            track,
+            played_at: now(),
+        });
+        if self.plays.len() > self.limit {
+            let excess = self.plays.len() - self.limit;
+            self.plays.drain(..excess);
+        }
+    }
+
+    /// Step back to the track played before the current one
+    pub fn back(&mut self) -> Option<usize> {
+        let index = self.plays.len().checked_sub(self.back + 2)?;
+        self.back += 1;
+        Some(self.plays[index].track)
+    }
+
+    /// Step back onto a track before the oldest play, for when `back` runs
+    /// out and the player falls back to library order
+    pub fn step_earlier(&mut self, track: usize) {
+        self.earlier.push(track);
+    }
+
+    /// Step forward again after `back`; None once at the newest play
+    pub fn forward(&mut self) -> Option<usize> {
+        if self.earlier.pop().is_some() {
+            let oldest = self.plays.len().checked_sub(1 + self.back);
+            return self
+                .earlier
+                .last()
+                .copied()
+                .or_else(|| oldest.map(|i| self.plays[i].track));
+        }
+        if self.back == 0 {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.plays[self.plays.len() - 1 - self.back].track)
+    }
+
+    /// Plays, newest first
+    pub fn recent(&self) -> impl Iterator<Item = &Play> {
+        self.plays.iter().rev()
+    }
+
+    /// Restore the history saved by the last session. Plays of tracks not
+    /// in this library are kept aside rather than dropped.
+    pub fn load(library: &[Track], limit: usize) -> Self {
+        let mut history = Self::new(limit);
+        let Some(saved) = read_saved() else {
+            return history;
+        };
+        let by_key: HashMap<PathBuf, usize> =
+            library.iter().enumerate().map(|(i, t)| (t.key(), i)).collect();
+        for play in saved.plays {
+            match by_key.get(&play.path) {
+                Some(&track) => history.plays.push(Play {
+                    track,
+                    played_at: play.played_at,
+                }),
+                None => history.unresolved.push(play),
+            }
+        }
+        let excess = history.plays.len().saturating_sub(limit);
+        history.plays.drain(..excess);
+        history
+    }
+
+    /// Write this library's plays and the ones kept aside, oldest first,
+    /// up to the limit
+    pub fn save(&self, library: &[Track]) {
+        let mut plays: Vec<SavedPlay> = self
+            .plays
+            .iter()
+            .map(|p| SavedPlay {
+                path: library[p.track].key(),
+                played_at: p.played_at,
+            })
+            .chain(self.unresolved.iter().cloned())
+            .collect();
+        plays.sort_by_key(|p| p.played_at);
+        let excess = plays.len().saturating_sub(self.limit);
+        plays.drain(..excess);
+        write_saved(&SavedHistory { plays });
+    }
+}
+
+/// Point saved plays at the new locations of moved files
+pub fn rename_paths(moves: &[(PathBuf, PathBuf)]) {
+    let Some(mut saved) = read_saved() else {
+        return;
+    };
+    let moves: HashMap<&PathBuf, &PathBuf> = moves.iter().map(|(from, to)| (from, to)).collect();
+    for play in &mut saved.plays {
+        if let Some(&to) = moves.get(&play.path) {
+            play.path = to.clone();
+        }
+    }
+    write_saved(&saved);
+}
+
+/// Seconds since the epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn history_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("history.json"))
+}
+
+fn read_saved() -> Option<SavedHistory> {
+    let data = std::fs::read_to_string(history_path()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_saved(saved: &SavedHistory) {
+    let Some(path) = history_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(saved) {
+        let _ = std::fs::write(path, json);
+    }
+}
@@ -1,43 +1,271 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use lofty::file::FileType;
+use lofty::probe::Probe;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
-use walkdir::WalkDir;
 
-use crate::metadata;
+use crate::audio::{self, Segment};
+use crate::config::{self, Config};
+use crate::cue::{self, CueRange, CueSheet};
+use crate::metadata::{self, MusicBrainzIds, TagEdit, TrackMetadata};
+use crate::pathtags::{self, PathPattern};
+use crate::playlist::{Playlist, PLAYLIST_EXTENSIONS};
 
-const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "ogg", "m4a", "aac"];
+/// Bump whenever `Track` or the cache gains fields so stale caches are rescanned
+const CACHE_VERSION: u32 = 9;
 
+/// Extensions worth opening during a scan. The extension only nominates a
+/// file; content sniffing decides what it actually is.
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "wav", "ogg", "oga", "opus", "m4a", "m4b", "aac", "aif", "aiff",
+    "aifc", "caf", "wv", "ape",
+];
+
+/// Per-directory ignore files, gitignore syntax
+const IGNORE_FILENAME: &str = ".tuneboxignore";
+
+/// Settings that change what a scan produces. Stored in the cache so
+/// changing them forces a rescan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Extensions to consider in addition to `AUDIO_EXTENSIONS`
+    pub extra_extensions: Vec<String>,
+    /// Gitignore-style globs, relative to the scan root, to leave out
+    pub exclude: Vec<String>,
+    /// Tracks shorter than this many seconds are left out (0 = keep all)
+    pub min_duration: f64,
+    /// Layouts like `%artist%/%album%/%track% - %title%` used to fill in
+    /// tags a file is missing
+    pub path_patterns: Vec<String>,
+}
+
+impl ScanOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            extra_extensions: config
+                .extra_extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            exclude: config.exclude.clone(),
+            min_duration: config.min_duration,
+            path_patterns: config.path_patterns.clone(),
+        }
+    }
+}
+
+/// A file that looked like audio but can't be played
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+pub struct ScanResult {
+    pub tracks: Vec<Track>,
+    pub skipped: Vec<SkippedFile>,
+    /// Playlist files found alongside the music
+    pub playlists: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Track {
     pub path: PathBuf,
     pub title: String,
     pub artist: String,
     pub album: String,
+    pub album_artist: Option<String>,
     pub duration: f64,
     pub track_number: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub year: Option<u32>,
+    pub date: Option<String>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub compilation: bool,
+    pub comment: Option<String>,
+    pub musicbrainz: MusicBrainzIds,
     pub bitrate: Option<u32>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u8>,
+    pub bit_depth: Option<u8>,
     pub format: String,
     pub file_size: u64,
+    /// Set for a track cut from a larger file by a CUE sheet; `path` is
+    /// that file
+    #[serde(default)]
+    pub cue: Option<CueRange>,
+}
+
+impl Track {
+    /// Identifies the track in saved state. Tracks from one CUE sheet share
+    /// a path, so theirs carries the track number after a `#`.
+    pub fn key(&self) -> PathBuf {
+        match &self.cue {
+            Some(cue) => {
+                let mut key = self.path.clone().into_os_string();
+                key.push(format!("#{}", cue.number));
+                key.into()
+            }
+            None => self.path.clone(),
+        }
+    }
+
+    /// The part of `path` this track plays
+    pub fn segment(&self) -> Segment {
+        match &self.cue {
+            Some(cue) => Segment {
+                start: cue.start,
+                end: cue.end,
+            },
+            None => Segment::default(),
+        }
+    }
+
+    /// The artist an album is filed under: the album artist tag, "Various Artists"
+    /// for untagged compilations, otherwise the track artist
+    pub fn album_artist_or_artist(&self) -> &str {
+        match &self.album_artist {
+            Some(a) => a,
+            None if self.compilation => "Various Artists",
+            None => &self.artist,
+        }
+    }
+
+    /// Identifies the album this track belongs to; album artist and year keep
+    /// same-named albums apart
+    pub fn album_key(&self) -> String {
+        format!(
+            "{}\u{1f}{}\u{1f}{}",
+            self.album_artist_or_artist().to_lowercase(),
+            self.album.to_lowercase(),
+            self.year.map(|y| y.to_string()).unwrap_or_default()
+        )
+    }
+
+    /// Mirror a tag edit that was written to this track's file, using the same
+    /// fallbacks as a scan for removed fields
+    pub fn apply_edit(&mut self, edit: &TagEdit) {
+        fn text(value: &Option<String>) -> Option<Option<String>> {
+            value.as_deref().map(|v| {
+                let v = v.trim();
+                (!v.is_empty()).then(|| v.to_string())
+            })
+        }
+        fn number(value: &Option<String>) -> Option<Option<u32>> {
+            value.as_deref().map(|v| v.trim().parse().ok())
+        }
+
+        if let Some(title) = text(&edit.title) {
+            self.title = title.unwrap_or_else(|| file_stem(&self.path));
+        }
+        if let Some(artist) = text(&edit.artist) {
+            self.artist = artist.unwrap_or_else(|| "Unknown Artist".to_string());
+        }
+        if let Some(album) = text(&edit.album) {
+            self.album = album.unwrap_or_else(|| "Unknown Album".to_string());
+        }
+        if let Some(album_artist) = text(&edit.album_artist) {
+            self.album_artist = album_artist;
+        }
+        if let Some(genre) = text(&edit.genre) {
+            self.genre = genre;
+        }
+        if let Some(track) = number(&edit.track_number) {
+            self.track_number = track;
+        }
+        if let Some(disc) = number(&edit.disc_number) {
+            self.disc_number = disc;
+        }
+        if let Some(year) = number(&edit.year) {
+            self.year = year;
+            // Writing the year replaces the date tag with it
+            self.date = year.map(|y| y.to_string());
+        }
+    }
+}
+
+/// Library ordering: album artist -> album -> year -> disc -> track -> title.
+/// Year and release ID keep same-named albums apart.
+pub fn sort_tracks(tracks: &mut [Track]) {
+    tracks.sort_by(|a, b| {
+        a.album_artist_or_artist()
+            .to_lowercase()
+            .cmp(&b.album_artist_or_artist().to_lowercase())
+            .then_with(|| a.album.to_lowercase().cmp(&b.album.to_lowercase()))
+            .then_with(|| a.year.cmp(&b.year))
+            .then_with(|| a.musicbrainz.release_id.cmp(&b.musicbrainz.release_id))
+            .then_with(|| a.disc_number.unwrap_or(1).cmp(&b.disc_number.unwrap_or(1)))
+            .then_with(|| a.track_number.cmp(&b.track_number))
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct LibraryCache {
+    #[serde(default)]
+    version: u32,
     directory: PathBuf,
+    /// Latest change in the tree at scan time, in milliseconds
     modified_time: u64,
+    options: ScanOptions,
     tracks: Vec<Track>,
+    skipped: Vec<SkippedFile>,
+    #[serde(default)]
+    playlists: Vec<PathBuf>,
 }
 
-fn is_audio_file(path: &Path) -> bool {
+fn is_audio_file(path: &Path, options: &ScanOptions) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
-        .map(|e| AUDIO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .map(|e| e.to_lowercase())
+        .map(|e| AUDIO_EXTENSIONS.contains(&e.as_str()) || options.extra_extensions.contains(&e))
         .unwrap_or(false)
 }
 
-fn format_from_extension(path: &Path) -> String {
+/// Identify a file from its leading bytes, ignoring the extension.
+/// Returns the format label, or why the file can't be played.
+pub fn sniff_format(path: &Path) -> std::result::Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("unreadable: {e}"))?;
+    let probe = Probe::new(BufReader::new(file))
+        .guess_file_type()
+        .map_err(|e| format!("unreadable: {e}"))?;
+
+    let label = match probe.file_type() {
+        Some(FileType::Mpeg) => "MP3",
+        Some(FileType::Flac) => "FLAC",
+        Some(FileType::Wav) => "WAV",
+        Some(FileType::Vorbis) => "OGG",
+        Some(FileType::Aiff) => "AIFF",
+        Some(FileType::Mp4) => "M4A",
+        Some(FileType::Aac) => "AAC",
+        // Decoded by codecs.rs
+        Some(FileType::Opus) => "OPUS",
+        Some(FileType::WavPack) => "WV",
+        Some(FileType::Ape) => "APE",
+        Some(FileType::Mpc) => return Err("Musepack: no decoder available".to_string()),
+        Some(FileType::Speex) => return Err("Speex: no decoder available".to_string()),
+        Some(other) => return Err(format!("{other:?}: no decoder available")),
+        // Formats lofty doesn't know (e.g. Matroska audio) may still decode
+        None => {
+            return match audio::probe_decoder(path) {
+                Ok(_) => Ok(extension_label(path)),
+                Err(_) => Err("not a recognized audio file".to_string()),
+            }
+        }
+    };
+    Ok(label.to_string())
+}
+
+fn extension_label(path: &Path) -> String {
     path.extension()
         .and_then(|e| e.to_str())
         .map(|e| e.to_uppercase())
@@ -45,50 +273,78 @@ fn format_from_extension(path: &Path) -> String {
 }
 
 fn cache_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|h| h.join(".tunebox").join("library.json"))
+    config::data_dir().map(|d| d.join("library.json"))
 }
 
-fn load_cache(dir: &Path) -> Option<Vec<Track>> {
+fn load_cache(dir: &Path, options: &ScanOptions) -> Option<ScanResult> {
     let cache_file = cache_path()?;
     let data = std::fs::read_to_string(&cache_file).ok()?;
     let cache: LibraryCache = serde_json::from_str(&data).ok()?;
 
-    if cache.directory != dir {
+    if cache.version != CACHE_VERSION || cache.directory != dir || cache.options != *options {
+        return None;
+    }
+
+    // Anything added, removed or re-ruled anywhere in the tree since the scan
+    let modified = tree_modified_time(dir, options)?;
+
+    if modified > cache.modified_time {
         return None;
     }
 
-    // Check directory modification time
-    let dir_modified = std::fs::metadata(dir)
+    Some(ScanResult {
+        tracks: cache.tracks,
+        skipped: cache.skipped,
+        playlists: cache.playlists,
+    })
+}
+
+/// Milliseconds since the epoch at which `path` last changed; whole
+/// seconds would miss a change made just after a scan
+fn modified_time(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
         .ok()?
         .modified()
         .ok()?
         .duration_since(std::time::UNIX_EPOCH)
-        .ok()?
-        .as_secs();
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
 
-    if dir_modified > cache.modified_time {
-        return None;
+/// Latest change to anything a scan of `dir` depends on besides the audio
+/// files' own tags: every folder it walks (files added or removed), the
+/// ignore files and the CUE sheets
+fn tree_modified_time(dir: &Path, options: &ScanOptions) -> Option<u64> {
+    let mut latest = modified_time(dir)?;
+    for entry in walk(dir, options).ok()?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        let is_rules = path.file_name().is_some_and(|n| n == IGNORE_FILENAME)
+            || path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("cue"));
+        if is_dir || is_rules {
+            latest = latest.max(modified_time(path).unwrap_or(0));
+        }
     }
-
-    Some(cache.tracks)
+    Some(latest)
 }
 
-fn save_cache(dir: &Path, tracks: &[Track]) {
+fn save_cache(dir: &Path, options: &ScanOptions, result: &ScanResult) {
     if let Some(cache_file) = cache_path() {
         if let Some(parent) = cache_file.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
-        let dir_modified = std::fs::metadata(dir)
-            .ok()
-            .and_then(|m| m.modified().ok())
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+        let modified = tree_modified_time(dir, options).unwrap_or(0);
 
         let cache = LibraryCache {
+            version: CACHE_VERSION,
             directory: dir.to_path_buf(),
-            modified_time: dir_modified,
-            tracks: tracks.to_vec(),
+            modified_time: modified,
+            options: options.clone(),
+            tracks: result.tracks.clone(),
+            skipped: result.skipped.clone(),
+            playlists: result.playlists.clone(),
         };
         if let Ok(json) = serde_json::to_string_pretty(&cache) {
             let _ = std::fs::write(&cache_file, json);
@@ -96,124 +352,347 @@ fn save_cache(dir: &Path, tracks: &[Track]) {
     }
 }
 
-pub fn scan_directory(dir: &Path) -> Result<Vec<Track>> {
-    // Try loading from cache first
-    if let Some(cached) = load_cache(dir) {
-        return Ok(cached);
+/// Replace cached entries for `tracks` (matched by path) after their tags were
+/// edited, so the next start picks up the changes without a rescan
+pub fn update_cache(tracks: &[&Track]) {
+    edit_cache(|cache| {
+        for track in tracks {
+            if let Some(cached) = cache.tracks.iter_mut().find(|t| t.path == track.path) {
+                *cached = (*track).clone();
+            }
+        }
+    });
+}
+
+/// Point cached entries at the new locations of moved files
+pub fn rename_in_cache(moves: &[(PathBuf, PathBuf)]) {
+    edit_cache(|cache| {
+        for (from, to) in moves {
+            if let Some(cached) = cache.tracks.iter_mut().find(|t| t.path == *from) {
+                cached.path = to.clone();
+            }
+        }
+    });
+}
+
+/// Apply `change` to the saved cache, if there is one
+fn edit_cache(change: impl FnOnce(&mut LibraryCache)) {
+    let Some(cache_file) = cache_path() else {
+        return;
+    };
+    let Some(mut cache) = std::fs::read_to_string(&cache_file)
+        .ok()
+        .and_then(|data| serde_json::from_str::<LibraryCache>(&data).ok())
+    else {
+        return;
+    };
+
+    // The folder's recorded time is left alone: files added since the
+    // last scan still need one
+    change(&mut cache);
+    if let Ok(json) = serde_json::to_string_pretty(&cache) {
+        let _ = std::fs::write(&cache_file, json);
     }
+}
 
-    let mut tracks = Vec::new();
+/// Walk `dir` honouring ignore files and excludes
+fn walk(dir: &Path, options: &ScanOptions) -> Result<ignore::Walk> {
+    let mut excludes = OverrideBuilder::new(dir);
+    for pattern in &options.exclude {
+        excludes
+            .add(&format!("!{}", pattern))
+            .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+    }
 
-    for entry in WalkDir::new(dir)
+    // Only .tuneboxignore files and explicit excludes apply; .gitignore and
+    // hidden-file rules are off so music in dot-folders is still found.
+    // Following links is loop-safe: the walker reports cycles as errors.
+    Ok(WalkBuilder::new(dir)
+        .standard_filters(false)
+        .add_custom_ignore_filename(IGNORE_FILENAME)
+        .overrides(excludes.build()?)
         .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
+        .build())
+}
+
+/// Every candidate audio file under `dir`, after ignore files, excludes and
+/// the extension filter. Files are not opened.
+pub fn audio_files(dir: &Path, options: &ScanOptions) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    // Canonical paths already listed, so a file reachable through several
+    // symlinks is only returned once
+    let mut seen = HashSet::new();
+
+    for entry in walk(dir, options)?.filter_map(|e| e.ok()) {
         let path = entry.path();
-        if !path.is_file() || !is_audio_file(path) {
+        if !path.is_file() || !is_audio_file(path, options) {
             continue;
         }
+        if let Ok(canonical) = path.canonicalize() {
+            if !seen.insert(canonical) {
+                continue;
+            }
+        }
+        files.push(path.to_path_buf());
+    }
+
+    Ok(files)
+}
 
-        let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-        let format = format_from_extension(path);
+/// Every CUE sheet and playlist file under `dir`, each in path order
+fn side_files(dir: &Path, options: &ScanOptions) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut cues = Vec::new();
+    let mut playlists = Vec::new();
+    for entry in walk(dir, options)?.filter_map(|e| e.ok()) {
+        let path = entry.into_path();
+        if !path.is_file() {
+            continue;
+        }
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        if ext == "cue" {
+            cues.push(path);
+        } else if PLAYLIST_EXTENSIONS.contains(&ext.as_str()) {
+            playlists.push(path);
+        }
+    }
+    cues.sort();
+    playlists.sort();
+    Ok((cues, playlists))
+}
 
-        match metadata::read_metadata(path) {
-            Ok(meta) => {
-                let filename = path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("Unknown")
-                    .to_string();
+pub fn scan_directory(dir: &Path, options: &ScanOptions) -> Result<ScanResult> {
+    // Try loading from cache first
+    if let Some(cached) = load_cache(dir, options) {
+        return Ok(cached);
+    }
 
-                tracks.push(Track {
-                    path: path.to_path_buf(),
-                    title: meta.title.unwrap_or_else(|| filename),
-                    artist: meta.artist.unwrap_or_else(|| "Unknown Artist".to_string()),
-                    album: meta.album.unwrap_or_else(|| "Unknown Album".to_string()),
-                    duration: meta
-                        .duration
-                        .unwrap_or(Duration::ZERO)
-                        .as_secs_f64(),
-                    track_number: meta.track_number,
-                    bitrate: meta.bitrate,
-                    sample_rate: meta.sample_rate,
-                    channels: meta.channels,
-                    format,
-                    file_size,
-                });
+    let patterns = pathtags::parse_patterns(&options.path_patterns)?;
+    let mut tracks = Vec::new();
+    let mut skipped = Vec::new();
+
+    let files = audio_files(dir, options)?;
+    let (cues, playlists) = side_files(dir, options)?;
+    let mut sheets = Vec::new();
+    for path in cues {
+        match cue::read_cue(&path, &files) {
+            Ok(sheet) => sheets.push(sheet),
+            Err(e) => skipped.push(SkippedFile {
+                path,
+                reason: format!("CUE sheet: {:#}", e),
+            }),
+        }
+    }
+    // A file named by several sheets is split by the first only, so its
+    // tracks aren't listed twice
+    let mut sheet_for: HashMap<&Path, &CueSheet> = HashMap::new();
+    for sheet in &sheets {
+        for track in &sheet.tracks {
+            sheet_for.entry(track.file.as_path()).or_insert(sheet);
+        }
+    }
+
+    for path in &files {
+        let path = path.as_path();
+        match sniff_format(path) {
+            Ok(format) => {
+                let rel = path.strip_prefix(dir).unwrap_or(path);
+                let track = build_track(path, format, rel, &patterns);
+                // The whole file is replaced by the tracks cut from it
+                let split = match sheet_for.get(path) {
+                    Some(sheet) => split_track(&track, sheet),
+                    None => vec![track],
+                };
+                // Unknown (zero) durations are kept; only known-short clips go
+                tracks.extend(split.into_iter().filter(|t| {
+                    t.duration <= 0.0 || t.duration >= options.min_duration
+                }));
             }
-            Err(_) => {
-                // Skip files we can't read metadata from but still add them
-                let filename = path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("Unknown")
-                    .to_string();
-
-                tracks.push(Track {
+            Err(reason) => {
+                // Say so when a CUE sheet's whole album goes with the file
+                let reason = match sheet_for.get(path) {
+                    Some(sheet) => format!(
+                        "{} (with the {} CUE sheet tracks cut from it)",
+                        reason,
+                        sheet.tracks_for(path).count()
+                    ),
+                    None => reason,
+                };
+                skipped.push(SkippedFile {
                     path: path.to_path_buf(),
-                    title: filename,
-                    artist: "Unknown Artist".to_string(),
-                    album: "Unknown Album".to_string(),
-                    duration: 0.0,
-                    track_number: None,
-                    bitrate: None,
-                    sample_rate: None,
-                    channels: None,
-                    format,
-                    file_size,
+                    reason,
                 });
             }
         }
     }
 
-    // Sort by artist -> album -> track number -> title
-    tracks.sort_by(|a, b| {
-        a.artist
-            .to_lowercase()
-            .cmp(&b.artist.to_lowercase())
-            .then_with(|| a.album.to_lowercase().cmp(&b.album.to_lowercase()))
-            .then_with(|| a.track_number.cmp(&b.track_number))
-            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
-    });
+    sort_tracks(&mut tracks);
+
+    let result = ScanResult {
+        tracks,
+        skipped,
+        playlists,
+    };
+    save_cache(dir, options, &result);
+    Ok(result)
+}
 
-    save_cache(dir, &tracks);
-    Ok(tracks)
+pub fn scan_single_file(path: &Path, options: &ScanOptions) -> Result<Vec<Track>> {
+    let patterns = pathtags::parse_patterns(&options.path_patterns)?;
+    match sniff_format(path) {
+        // Without a library root, patterns match the end of the full path
+        Ok(format) => Ok(vec![build_track(path, format, path, &patterns)]),
+        Err(reason) => bail!("Can't play {}: {}", path.display(), reason),
+    }
 }
 
-pub fn scan_single_file(path: &Path) -> Result<Vec<Track>> {
+/// The tracks of the playlist at `path`, in playlist order. Entries whose
+/// file is missing or can't be played come back as skipped instead of
+/// failing the load.
+pub fn scan_playlist(path: &Path, options: &ScanOptions) -> Result<(Vec<Track>, Vec<SkippedFile>)> {
+    let patterns = pathtags::parse_patterns(&options.path_patterns)?;
+    let playlist = Playlist::load(path)?;
+    let mut tracks = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in playlist.entries {
+        if !entry.path.is_file() {
+            let reason = if entry.path.to_string_lossy().contains("://") {
+                "streams aren't supported".to_string()
+            } else {
+                format!("missing (listed in {})", path.display())
+            };
+            skipped.push(SkippedFile {
+                path: entry.path,
+                reason,
+            });
+            continue;
+        }
+        match sniff_format(&entry.path) {
+            Ok(format) => tracks.push(build_track(&entry.path, format, &entry.path, &patterns)),
+            Err(reason) => skipped.push(SkippedFile {
+                path: entry.path,
+                reason,
+            }),
+        }
+    }
+    Ok((tracks, skipped))
+}
+
+/// Write every skipped file to `~/.tunebox/scan_report.txt`, returning the path
+pub fn write_scan_report(skipped: &[SkippedFile]) -> Option<PathBuf> {
+    let path = config::data_dir()?.join("scan_report.txt");
+    let report: String = skipped
+        .iter()
+        .map(|s| format!("{}\t{}\n", s.path.display(), s.reason))
+        .collect();
+    std::fs::write(&path, report).ok()?;
+    Some(path)
+}
+
+/// Build a `Track` from a file's tags. Missing tags are inferred from
+/// `rel_path` (the path below the library root) when a pattern fits, then
+/// fall back to the file stem and "Unknown" placeholders.
+fn build_track(path: &Path, format: String, rel_path: &Path, patterns: &[PathPattern]) -> Track {
     let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-    let format = format_from_extension(path);
-
-    let meta = metadata::read_metadata(path).unwrap_or(crate::metadata::TrackMetadata {
-        title: None,
-        artist: None,
-        album: None,
-        track_number: None,
-        duration: None,
-        bitrate: None,
-        sample_rate: None,
-        channels: None,
-        album_art: None,
-    });
+    let mut meta = metadata::read_metadata(path).unwrap_or_default();
+    if let Some(inferred) = pathtags::infer(patterns, rel_path) {
+        inferred.fill(&mut meta);
+    }
+    // MP4 audio is either lossy AAC or lossless ALAC; only ALAC reports a bit depth
+    let format = if format == "M4A" && meta.bit_depth.is_some() {
+        "ALAC".to_string()
+    } else {
+        format
+    };
+    track_from_metadata(path, meta, format, file_size)
+}
+
+/// The virtual tracks `sheet` cuts from `whole`. Sheet text wins over the
+/// file's own tags; audio properties come from the file.
+fn split_track(whole: &Track, sheet: &CueSheet) -> Vec<Track> {
+    let date = sheet.date.clone().or_else(|| whole.date.clone());
+    let year = date
+        .as_deref()
+        .and_then(|d| d.get(..4))
+        .and_then(|y| y.parse().ok())
+        .or(whole.year);
+    sheet
+        .tracks_for(&whole.path)
+        .map(|cue| {
+            let end = cue.range.end.unwrap_or(whole.duration);
+            Track {
+                title: cue
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("Track {}", cue.range.number)),
+                artist: cue
+                    .performer
+                    .clone()
+                    .or_else(|| sheet.performer.clone())
+                    .unwrap_or_else(|| whole.artist.clone()),
+                album: sheet.title.clone().unwrap_or_else(|| whole.album.clone()),
+                album_artist: sheet.performer.clone().or_else(|| whole.album_artist.clone()),
+                duration: (end - cue.range.start).max(0.0),
+                track_number: Some(cue.range.number),
+                track_total: Some(sheet.tracks.len() as u32),
+                disc_number: sheet.disc_number.or(whole.disc_number),
+                year,
+                date: date.clone(),
+                genre: sheet.genre.clone().or_else(|| whole.genre.clone()),
+                composer: cue
+                    .songwriter
+                    .clone()
+                    .or_else(|| sheet.songwriter.clone())
+                    .or_else(|| whole.composer.clone()),
+                musicbrainz: MusicBrainzIds {
+                    // The file's recording ID can't be right for every track
+                    recording_id: None,
+                    ..whole.musicbrainz.clone()
+                },
+                cue: Some(cue.range.clone()),
+                ..whole.clone()
+            }
+        })
+        .collect()
+}
 
-    let filename = path
-        .file_stem()
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("Unknown")
-        .to_string();
+        .to_string()
+}
 
-    Ok(vec![Track {
+fn track_from_metadata(path: &Path, meta: TrackMetadata, format: String, file_size: u64) -> Track {
+    let filename = file_stem(path);
+
+    Track {
         path: path.to_path_buf(),
         title: meta.title.unwrap_or(filename),
         artist: meta.artist.unwrap_or_else(|| "Unknown Artist".to_string()),
         album: meta.album.unwrap_or_else(|| "Unknown Album".to_string()),
+        album_artist: meta.album_artist,
         duration: meta.duration.unwrap_or(Duration::ZERO).as_secs_f64(),
         track_number: meta.track_number,
+        track_total: meta.track_total,
+        disc_number: meta.disc_number,
+        disc_total: meta.disc_total,
+        year: meta.year,
+        date: meta.date,
+        genre: meta.genre,
+        composer: meta.composer,
+        compilation: meta.compilation,
+        comment: meta.comment,
+        musicbrainz: meta.musicbrainz,
         bitrate: meta.bitrate,
         sample_rate: meta.sample_rate,
         channels: meta.channels,
+        bit_depth: meta.bit_depth,
         format,
         file_size,
-    }])
+        cue: None,
+    }
 }
@@ -1,62 +1,573 @@
 mod albumart;
 mod app;
+mod artloader;
 mod audio;
+mod browser;
+mod chapters;
+mod codecs;
+mod columns;
+mod config;
+mod cover;
+mod cue;
+mod desktop;
+mod doctor;
+mod duplicates;
+mod export;
+mod history;
 mod library;
 mod metadata;
+mod organize;
+mod positions;
+mod queue;
+mod pathtags;
+mod playlist;
+mod plays;
+mod query;
+mod ratings;
 mod remote;
+mod session;
+mod shuffle;
+mod smart;
+mod tageditor;
 mod ui;
 mod visualizer;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use crossbeam_channel::bounded;
 
 use app::PlaybackState;
 use remote::{RemoteCommand, RemoteServer};
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
+    MouseEvent, MouseEventKind,
+};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use crossterm::ExecutableCommand;
 use ratatui::backend::CrosstermBackend;
+use ratatui::layout::Rect;
 use ratatui::Terminal;
 
 use app::App;
+use config::Config;
+use duplicates::MatchMode;
+use export::{ExportFormat, SortKey};
+use library::{ScanOptions, SkippedFile};
+use history::History;
+use playlist::PlaylistFormat;
+use plays::PlayCounts;
+use query::{Query, Stats};
+use ratings::Ratings;
 use audio::{AudioCommand, AudioEngine};
 
 #[derive(Parser)]
 #[command(
     name = "tunebox",
     about = "A beautiful terminal music player",
-    version
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to a music directory or file
-    path: PathBuf,
+    #[arg(required = true)]
+    path: Option<PathBuf>,
 
     /// Start with shuffle enabled
     #[arg(long)]
     shuffle: bool,
 
+    /// Restore the last session: track and position, settings, view and search
+    #[arg(long)]
+    resume: bool,
+
     /// Port for remote control server (default: 8080)
     #[arg(long, default_value = "8080")]
     port: u16,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a music folder for broken files and tagging problems
+    Doctor {
+        /// Music directory to check
+        path: PathBuf,
+
+        /// Report format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// List copies of the same track, marking the best one to keep
+    Duplicates {
+        /// Music directory to check
+        path: PathBuf,
+
+        /// How copies are matched: tags and duration, identical decoded
+        /// audio, or an acoustic fingerprint
+        #[arg(long, value_enum, default_value_t = MatchMode::Tags)]
+        by: MatchMode,
+
+        /// Report format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// Write the library as JSON, CSV or extended M3U
+    Export(ExportArgs),
+    /// Preview the tags that path patterns would fill in for untagged files
+    Infer {
+        /// Music directory to check
+        path: PathBuf,
+
+        /// Try this pattern instead of the configured ones (repeatable),
+        /// e.g. '%artist%/%album%/%track% - %title%'
+        #[arg(long, value_name = "PATTERN")]
+        pattern: Vec<String>,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// Embed, extract or shrink album covers
+    Cover {
+        #[command(subcommand)]
+        action: CoverAction,
+    },
+    /// Move and rename files into a folder layout built from their tags
+    Organize {
+        /// Music directory to organize
+        path: PathBuf,
+
+        /// Layout to use instead of the configured one, e.g.
+        /// '{albumartist}/{year} - {album}/{disc}{track:02} {title}.{ext}'
+        #[arg(long, value_name = "TEMPLATE")]
+        template: Option<String>,
+
+        /// Show what would move without touching any file
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum CoverAction {
+    /// Embed an image as the front cover of every track in a folder
+    Embed {
+        /// JPEG or PNG to embed
+        image: PathBuf,
+
+        /// Folder (or single file) to embed it into
+        path: PathBuf,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// Save each folder's embedded cover as cover.jpg next to its tracks
+    Extract {
+        /// Music directory
+        path: PathBuf,
+
+        /// Replace existing cover.jpg files
+        #[arg(long)]
+        force: bool,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+    /// Remove embedded pictures above a size limit
+    Strip {
+        /// Music directory
+        path: PathBuf,
+
+        /// Size limit in kilobytes (default: max_cover_kb from the config)
+        #[arg(long, value_name = "KB")]
+        max_kb: Option<u64>,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+}
+
+/// Arguments for `tunebox export`
+#[derive(Args)]
+struct ExportArgs {
+    /// Music directory to export
+    path: PathBuf,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+    format: ExportFormat,
+
+    /// Fields to include, comma-separated (e.g. title,artist,year)
+    #[arg(long, value_delimiter = ',', value_name = "FIELDS")]
+    fields: Vec<String>,
+
+    /// Only export tracks matching this search query (same syntax as `/`)
+    #[arg(long, value_name = "QUERY", allow_hyphen_values = true)]
+    filter: Option<String>,
+
+    /// Sort by these fields, comma-separated; prefix with - for descending
+    #[arg(long, value_delimiter = ',', value_name = "FIELDS", allow_hyphen_values = true)]
+    sort: Vec<String>,
+
+    /// Write to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Scan filters shared by the player and the subcommands
+#[derive(Args)]
+struct ScanArgs {
+    /// Skip files matching this gitignore-style glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Hide tracks shorter than this many seconds
+    #[arg(long, value_name = "SECS")]
+    min_duration: Option<f64>,
+}
+
+impl ScanArgs {
+    /// Config settings with the command-line flags applied on top
+    fn options(&self, config: &Config) -> ScanOptions {
+        let mut options = ScanOptions::from_config(config);
+        options.exclude.extend(self.exclude.iter().cloned());
+        if let Some(secs) = self.min_duration {
+            options.min_duration = secs;
+        }
+        options
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    let path = cli.path.canonicalize().context("Invalid path")?;
+    let config = Config::load();
+
+    match cli.command {
+        Some(Command::Doctor { path, format, scan }) => {
+            run_doctor(&path, format, &scan.options(&config), &config)
+        }
+        Some(Command::Duplicates {
+            path,
+            by,
+            format,
+            scan,
+        }) => run_duplicates(&path, by, format, &scan.options(&config)),
+        Some(Command::Export(args)) => run_export(args, &config),
+        Some(Command::Infer {
+            path,
+            pattern,
+            scan,
+        }) => run_infer(&path, &pattern, &scan.options(&config)),
+        Some(Command::Cover { action }) => run_cover(action, &config),
+        Some(Command::Organize {
+            path,
+            template,
+            dry_run,
+            scan,
+        }) => run_organize(&path, template, dry_run, &scan.options(&config), &config),
+        None => run_player(cli, &config),
+    }
+}
+
+fn run_doctor(path: &Path, format: OutputFormat, options: &ScanOptions, config: &Config) -> Result<()> {
+    let path = path.canonicalize().context("Invalid path")?;
+    if !path.is_dir() {
+        bail!("Not a directory: {}", path.display());
+    }
+    let report = doctor::check(&path, options, &config.cover_files)?;
+    match format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+/// Scan a library directory for a non-interactive command
+fn scan_for_command(path: &Path, options: &ScanOptions) -> Result<Vec<library::Track>> {
+    let path = path.canonicalize().context("Invalid path")?;
+    if !path.is_dir() {
+        bail!("Not a directory: {}", path.display());
+    }
+    Ok(library::scan_directory(&path, options)?.tracks)
+}
+
+fn run_duplicates(path: &Path, by: MatchMode, format: OutputFormat, options: &ScanOptions) -> Result<()> {
+    let tracks = scan_for_command(path, options)?;
+    let groups = duplicates::find_duplicates(&tracks, by);
+    let report = duplicates::Report::new(&tracks, &groups);
+    match format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+fn run_export(args: ExportArgs, config: &Config) -> Result<()> {
+    let tracks = scan_for_command(&args.path, &args.scan.options(config))?;
+    let query = Query::parse(args.filter.as_deref().unwrap_or_default())?;
+    let history = History::load(&tracks, config.history_size);
+    let plays = PlayCounts::load(&tracks, &history);
+    let ratings = Ratings::load();
+    let all: Vec<usize> = (0..tracks.len()).collect();
+    let mut selected: Vec<&library::Track> = query
+        .run(&tracks, &all, &Stats::new(&plays, &ratings))
+        .into_iter()
+        .map(|i| &tracks[i])
+        .collect();
+    let keys: Vec<SortKey> = args.sort.iter().map(|s| SortKey::parse(s)).collect();
+    export::sort(&mut selected, &keys)?;
+
+    let data = export::render(&selected, args.format, &args.fields)?;
+    let cue_tracks = selected.iter().filter(|t| t.cue.is_some()).count();
+    if args.format == ExportFormat::M3u && cue_tracks > 0 {
+        eprintln!("Skipped {} CUE sheet track(s): M3U can't address part of a file", cue_tracks);
+    }
+    match args.output {
+        Some(file) => std::fs::write(&file, data)
+            .with_context(|| format!("Failed to write {}", file.display()))?,
+        None => print!("{}", data),
+    }
+    Ok(())
+}
+
+/// Preview path-pattern tags, using `patterns` instead of the configured
+/// ones when any are given
+fn run_infer(path: &Path, patterns: &[String], options: &ScanOptions) -> Result<()> {
+    let path = path.canonicalize().context("Invalid path")?;
+    let patterns = if patterns.is_empty() {
+        &options.path_patterns
+    } else {
+        patterns
+    };
+    let patterns = pathtags::parse_patterns(patterns)?;
+    print!("{}", pathtags::preview(&path, options, patterns)?);
+    Ok(())
+}
+
+/// Playable files under `path`, or `path` itself when it is a file. Cover
+/// actions don't need tags, so this skips the full scan and its cache.
+fn playable_files(path: &Path, options: &ScanOptions) -> Result<Vec<PathBuf>> {
+    let path = path.canonicalize().context("Invalid path")?;
+    let files = if path.is_file() {
+        vec![path]
+    } else {
+        library::audio_files(&path, options)?
+    };
+    Ok(files
+        .into_iter()
+        .filter(|f| library::sniff_format(f).is_ok())
+        .collect())
+}
+
+/// Delete the player's cached art for the albums of `files`, whose covers
+/// just changed
+fn forget_cached_art(files: &[PathBuf], options: &ScanOptions) {
+    let keys: BTreeSet<String> = files
+        .iter()
+        .filter_map(|f| library::scan_single_file(f, options).ok())
+        .flatten()
+        .map(|t| t.album_key())
+        .collect();
+    for key in keys {
+        artloader::invalidate_disk(&key);
+    }
+}
+
+fn run_cover(action: CoverAction, config: &Config) -> Result<()> {
+    let mut failed = 0;
+    match action {
+        CoverAction::Embed { image, path, scan } => {
+            let data = std::fs::read(&image)
+                .with_context(|| format!("Failed to read {}", image.display()))?;
+            let picture = metadata::cover_picture(&data)
+                .with_context(|| format!("Can't embed {}", image.display()))?;
+            let options = scan.options(config);
+            let files = playable_files(&path, &options)?;
+            let mut embedded = Vec::new();
+            for file in files {
+                match metadata::embed_cover(&file, &picture) {
+                    Ok(()) => embedded.push(file),
+                    Err(e) => {
+                        eprintln!("Failed: {}: {:#}", file.display(), e);
+                        failed += 1;
+                    }
+                }
+            }
+            forget_cached_art(&embedded, &options);
+            println!("Embedded {} into {} file(s)", image.display(), embedded.len());
+        }
+        CoverAction::Extract { path, force, scan } => {
+            let files = playable_files(&path, &scan.options(config))?;
+            let mut folders: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+            for file in &files {
+                if let Some(dir) = file.parent() {
+                    folders.entry(dir).or_default().push(file);
+                }
+            }
+            let mut written = 0;
+            let mut existing = 0;
+            for (dir, tracks) in &folders {
+                if !force && dir.join(cover::EXTRACTED_COVER).exists() {
+                    existing += 1;
+                    continue;
+                }
+                match cover::extract_cover(tracks, force) {
+                    Ok(Some(target)) => {
+                        println!("{}", target.display());
+                        written += 1;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!("Failed: {:#}", e);
+                        failed += 1;
+                    }
+                }
+            }
+            println!(
+                "Extracted {} cover(s); {} folder(s) already had {}",
+                written,
+                existing,
+                cover::EXTRACTED_COVER
+            );
+        }
+        CoverAction::Strip { path, max_kb, scan } => {
+            let max_bytes = max_kb.unwrap_or(config.max_cover_kb) as usize * 1024;
+            let options = scan.options(config);
+            let mut removed = 0;
+            let mut stripped = Vec::new();
+            for file in playable_files(&path, &options)? {
+                match metadata::strip_large_pictures(&file, max_bytes) {
+                    Ok(0) => {}
+                    Ok(n) => {
+                        println!("{}: removed {} picture(s)", file.display(), n);
+                        removed += n;
+                        stripped.push(file);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed: {}: {:#}", file.display(), e);
+                        failed += 1;
+                    }
+                }
+            }
+            forget_cached_art(&stripped, &options);
+            println!("Removed {} oversized picture(s)", removed);
+        }
+    }
+    if failed > 0 {
+        bail!("{} file(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn run_organize(
+    path: &Path,
+    template: Option<String>,
+    dry_run: bool,
+    options: &ScanOptions,
+    config: &Config,
+) -> Result<()> {
+    let template = organize::Template::parse(template.as_ref().unwrap_or(&config.organize_template))?;
+    let path = path.canonicalize().context("Invalid path")?;
+    if !path.is_dir() {
+        bail!("Not a directory: {}", path.display());
+    }
+    let scan = library::scan_directory(&path, options)?;
+    let tracks = scan.tracks;
+    let all: Vec<usize> = (0..tracks.len()).collect();
+    let plan = organize::plan(&tracks, &all, &path, &template, &config.cover_files);
+    print!("{}", plan);
+    if dry_run || plan.moves.is_empty() {
+        return Ok(());
+    }
+
+    let outcome = organize::execute(&plan);
+    let moved: Vec<(PathBuf, PathBuf)> = outcome
+        .moved
+        .iter()
+        .map(|(_, from, to)| (from.clone(), to.clone()))
+        .collect();
+    library::rename_in_cache(&moved);
+    history::rename_paths(&moved);
+    plays::rename_paths(&moved);
+    queue::rename_paths(&moved);
+    let mut ratings = Ratings::load();
+    ratings.rename(&moved);
+    ratings.save();
+    let playlists: Vec<PathBuf> = scan
+        .playlists
+        .into_iter()
+        .chain(playlist::saved_playlists())
+        .collect();
+    match playlist::rename_entries(&playlists, &moved) {
+        Ok(0) => {}
+        Ok(rewritten) => println!("Updated {} playlist(s)", rewritten),
+        Err(e) => eprintln!("Failed to update playlists: {:#}", e),
+    }
+    for (file, error) in &outcome.errors {
+        eprintln!("Failed: {}: {}", file.display(), error);
+    }
+    println!("Moved {} file(s)", outcome.moved.len());
+    if !outcome.errors.is_empty() {
+        bail!("{} file(s) could not be moved", outcome.errors.len());
+    }
+    Ok(())
+}
+
+fn run_player(cli: Cli, config: &Config) -> Result<()> {
+    let path = cli
+        .path
+        .as_deref()
+        .context("No path given")?
+        .canonicalize()
+        .context("Invalid path")?;
 
     // Scan library
-    let tracks = if path.is_file() {
-        library::scan_single_file(&path)?
+    let options = cli.scan.options(config);
+    let mut playlists = Vec::new();
+    let mut missing = 0;
+    let tracks = if PlaylistFormat::from_path(&path).is_some() && path.is_file() {
+        let (tracks, skipped) = library::scan_playlist(&path, &options)?;
+        report_skipped(&skipped);
+        missing = skipped.len();
+        tracks
+    } else if path.is_file() {
+        library::scan_single_file(&path, &options)?
     } else if path.is_dir() {
-        library::scan_directory(&path)?
+        let scan = library::scan_directory(&path, &options)?;
+        report_skipped(&scan.skipped);
+        playlists = scan.playlists;
+        scan.tracks
     } else {
         bail!("Path is neither a file nor directory: {}", path.display());
     };
@@ -101,13 +612,25 @@ fn main() -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     stdout.execute(EnterAlternateScreen)?;
+    stdout.execute(EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app
-    let mut app = App::new(tracks, cmd_tx, event_rx, sample_rx);
+    let root = path.is_dir().then(|| path.clone());
+    let mut app = App::new(tracks, root, config, cmd_tx, event_rx, sample_rx);
+    app.playlists = playlists;
+    if missing > 0 {
+        app.set_status(format!("⚠ {} playlist entries can't be played", missing));
+    }
+
+    if (cli.resume || config.resume) && path.is_dir() {
+        if let Some(session) = session::Session::load() {
+            app.restore_session(&session);
+        }
+    }
 
-    if cli.shuffle {
+    if cli.shuffle && !app.shuffle {
         app.toggle_shuffle();
     }
 
@@ -118,22 +641,63 @@ fn main() -> Result<()> {
 
     // Main event loop
     let result = run_app(&mut terminal, &mut app, playback_state, remote_cmd_rx);
+    save_state(&mut app);
 
     // Restore terminal
     disable_raw_mode()?;
+    terminal.backend_mut().execute(DisableMouseCapture)?;
     terminal.backend_mut().execute(LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
     result
 }
 
+/// Print files that were found but can't be played, with the reason
+fn report_skipped(skipped: &[SkippedFile]) {
+    if skipped.is_empty() {
+        return;
+    }
+    eprintln!("Skipped {} file(s) that can't be played:", skipped.len());
+    for file in skipped.iter().take(10) {
+        eprintln!("  {}: {}", file.path.display(), file.reason);
+    }
+    if skipped.len() > 10 {
+        eprintln!("  ... and {} more", skipped.len() - 10);
+    }
+    if let Some(report) = library::write_scan_report(skipped) {
+        eprintln!("Full report: {}", report.display());
+    }
+}
+
+/// How often the session is written while the player runs
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Write the session, queue and history so they survive a quit or crash.
+/// Playing a single file doesn't replace the library's session.
+fn save_state(app: &mut App) {
+    if app.library_root.is_some() {
+        app.session().save();
+    }
+    app.remember_position();
+    app.positions.save();
+    app.queue.save(&app.library);
+    app.history.save(&app.library);
+    app.play_counts.save();
+}
+
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
     playback_state: Arc<Mutex<PlaybackState>>,
     remote_cmd_rx: crossbeam_channel::Receiver<RemoteCommand>,
 ) -> Result<()> {
+    let mut last_save = Instant::now();
     loop {
+        if last_save.elapsed() >= SESSION_SAVE_INTERVAL {
+            save_state(app);
+            last_save = Instant::now();
+        }
+
         // Process audio events and samples
         app.process_audio_events();
 
@@ -155,6 +719,12 @@ fn run_app(
                     app.visualizer.mode = app.visualizer.mode.cycle();
                 }
                 RemoteCommand::ToggleShuffle => app.toggle_shuffle(),
+                RemoteCommand::QueuePlay(i) => app.queue_play(i),
+                RemoteCommand::QueueRemove(i) => app.queue_remove(i),
+                RemoteCommand::QueueMove { from, to } => {
+                    app.queue.move_to(from, to);
+                }
+                RemoteCommand::QueueClear => app.queue_clear(),
             }
         }
 
@@ -166,21 +736,48 @@ fn run_app(
         // Update sleep timer (fade volume, auto-pause)
         app.update_sleep_timer();
 
+        // Size the album grid to the terminal and load visible covers
+        let size = terminal.size()?;
+        let (cols, rows) = ui::grid_dimensions(Rect::new(0, 0, size.width, size.height), app);
+        app.grid_columns = cols;
+        app.grid_rows = rows;
+        app.update_art();
+
         // Draw
         terminal.draw(|frame| ui::draw(frame, app))?;
 
         // Handle input with timeout for ~30fps rendering
         if event::poll(Duration::from_millis(33))? {
-            if let Event::Key(key) = event::read()? {
-                // Only handle key press, not release (fixes double-trigger on some terminals)
-                if key.kind != crossterm::event::KeyEventKind::Press {
-                    continue;
+            match event::read()? {
+                Event::Key(key) => {
+                    // Only handle key press, not release (fixes double-trigger on some terminals)
+                    if key.kind != crossterm::event::KeyEventKind::Press {
+                        continue;
+                    }
+                    if app.tag_editor.is_some() {
+                        handle_tag_editor_input(app, key.code, key.modifiers);
+                    } else if app.playlist_prompt.is_some() {
+                        handle_playlist_prompt_input(app, key.code);
+                    } else if app.smart_prompt.is_some() {
+                        handle_smart_prompt_input(app, key.code);
+                    } else if app.column_editor.is_some() {
+                        handle_column_editor_input(app, key.code);
+                    } else if app.cover_prompt {
+                        handle_cover_input(app, key.code);
+                    } else if app.show_queue {
+                        handle_queue_input(app, key.code);
+                    } else if app.show_chapters {
+                        handle_chapters_input(app, key.code);
+                    } else if app.search_mode {
+                        handle_search_input(app, key.code);
+                    } else {
+                        handle_normal_input(app, key.code, key.modifiers);
+                    }
                 }
-                if app.search_mode {
-                    handle_search_input(app, key.code);
-                } else {
-                    handle_normal_input(app, key.code, key.modifiers);
+                Event::Mouse(mouse) => {
+                    handle_mouse(app, mouse, Rect::new(0, 0, size.width, size.height));
                 }
+                _ => {}
             }
         }
 
@@ -194,6 +791,10 @@ fn run_app(
 }
 
 fn handle_normal_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
+    // Any other key cancels a pending organize
+    if key != KeyCode::Char('O') && app.pending_organize.take().is_some() {
+        app.status_message = None;
+    }
     match key {
         KeyCode::Char('q') => app.should_quit = true,
         KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
@@ -204,8 +805,38 @@ fn handle_normal_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
         KeyCode::Char('p') => app.prev_track(),
         KeyCode::Char('j') | KeyCode::Down => app.move_selection_down(),
         KeyCode::Char('k') | KeyCode::Up => app.move_selection_up(),
+        KeyCode::Char('h') => app.move_selection_left(),
+        KeyCode::Char('l') => app.move_selection_right(),
         KeyCode::Enter => app.play_selected(),
-        KeyCode::Char('s') => app.toggle_shuffle(),
+        KeyCode::Tab => app.next_view(),
+        KeyCode::BackTab => app.prev_view(),
+        KeyCode::Backspace | KeyCode::Esc => app.browse_back(),
+        KeyCode::Char('P') => app.play_all(),
+        KeyCode::Char('S') => app.shuffle_selection(),
+        KeyCode::Char('a') => app.queue_selection(),
+        KeyCode::Char('A') => app.play_selection_next(),
+        KeyCode::Char('Q') => app.toggle_queue(),
+        KeyCode::Char('D') => app.toggle_hide_duplicates(),
+        KeyCode::Char('e') => app.open_tag_editor(),
+        KeyCode::Char('u') => app.undo_tag_edit(),
+        KeyCode::Char('O') => app.organize_selection(),
+        KeyCode::Char('C') => app.open_cover_prompt(),
+        KeyCode::Char('c') => app.toggle_chapters(),
+        KeyCode::Char('w') => app.open_playlist_prompt(false),
+        KeyCode::Char('W') => app.open_smart_prompt(),
+        // Editing the open playlist
+        KeyCode::Char('d') => app.playlist_remove(),
+        KeyCode::Char('J') => app.playlist_move(true),
+        KeyCode::Char('K') => app.playlist_move(false),
+        KeyCode::Char(c @ '0'..='5') => app.rate_selection(c as u8 - b'0'),
+        // Marking tracks for the bulk actions above
+        KeyCode::Char('x') => app.toggle_mark(),
+        KeyCode::Char('V') => app.toggle_visual(),
+        KeyCode::Char('*') => app.mark_matching(),
+        KeyCode::Char('y') => app.copy_paths(),
+        KeyCode::Char('o') => app.reveal_selection(),
+        KeyCode::Char('L') => app.open_column_editor(),
+        KeyCode::Char('s') => app.cycle_shuffle(),
         KeyCode::Char('r') => app.cycle_repeat(),
         KeyCode::Char('+') | KeyCode::Char(']') => app.volume_up(),
         KeyCode::Char('-') | KeyCode::Char('[') => app.volume_down(),
@@ -228,9 +859,138 @@ fn handle_normal_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
 
 fn handle_search_input(app: &mut App, key: KeyCode) {
     match key {
-        KeyCode::Esc | KeyCode::Enter => app.toggle_search(),
+        KeyCode::Esc => app.toggle_search(),
+        KeyCode::Enter => app.finish_search(),
         KeyCode::Backspace => app.search_backspace(),
         KeyCode::Char(c) => app.search_input(c),
         _ => {}
     }
 }
+
+fn handle_queue_input(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Esc | KeyCode::Char('Q') => app.toggle_queue(),
+        KeyCode::Char('j') | KeyCode::Down => app.queue_cursor_down(),
+        KeyCode::Char('k') | KeyCode::Up => app.queue_cursor_up(),
+        KeyCode::Char('J') => app.queue_move_down(),
+        KeyCode::Char('K') => app.queue_move_up(),
+        KeyCode::Char('d') | KeyCode::Delete => app.queue_remove(app.queue_cursor),
+        KeyCode::Char('c') => app.queue_clear(),
+        KeyCode::Char('w') => app.open_playlist_prompt(true),
+        KeyCode::Enter => app.queue_play(app.queue_cursor),
+        // Playback keys keep working with the pane open
+        KeyCode::Char(' ') => app.toggle_pause(),
+        KeyCode::Char('n') => app.next_track(),
+        KeyCode::Char('p') => app.prev_track(),
+        KeyCode::Char('q') => app.should_quit = true,
+        _ => {}
+    }
+}
+
+fn handle_chapters_input(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Esc | KeyCode::Char('c') => app.toggle_chapters(),
+        KeyCode::Char('j') | KeyCode::Down => app.chapter_cursor_down(),
+        KeyCode::Char('k') | KeyCode::Up => app.chapter_cursor_up(),
+        KeyCode::Enter => app.play_chapter(app.chapter_cursor),
+        KeyCode::Char(' ') => app.toggle_pause(),
+        KeyCode::Char('n') => app.next_track(),
+        KeyCode::Char('p') => app.prev_track(),
+        KeyCode::Right => app.seek_forward(),
+        KeyCode::Left => app.seek_backward(),
+        KeyCode::Char('q') => app.should_quit = true,
+        _ => {}
+    }
+}
+
+fn handle_playlist_prompt_input(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Esc => app.playlist_prompt = None,
+        KeyCode::Enter => app.save_playlist(),
+        KeyCode::Backspace => app.playlist_prompt_backspace(),
+        KeyCode::Char(c) => app.playlist_prompt_input(c),
+        _ => {}
+    }
+}
+
+fn handle_smart_prompt_input(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Esc => {
+            app.smart_prompt = None;
+            app.error_message = None;
+        }
+        KeyCode::Enter => app.save_smart_playlist(),
+        KeyCode::Backspace => app.smart_prompt_backspace(),
+        KeyCode::Char(c) => app.smart_prompt_input(c),
+        _ => {}
+    }
+}
+
+fn handle_column_editor_input(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('L') | KeyCode::Char('q') => {
+            app.close_column_editor();
+        }
+        KeyCode::Char('j') | KeyCode::Down => app.column_editor_move(true),
+        KeyCode::Char('k') | KeyCode::Up => app.column_editor_move(false),
+        KeyCode::Char(' ') | KeyCode::Char('x') => app.column_editor_toggle(),
+        KeyCode::Char('J') => app.column_editor_reorder(true),
+        KeyCode::Char('K') => app.column_editor_reorder(false),
+        KeyCode::Char('s') => app.column_editor_sort(),
+        _ => {}
+    }
+}
+
+/// Header clicks sort by that column; the wheel moves through the list
+fn handle_mouse(app: &mut App, mouse: MouseEvent, size: Rect) {
+    if app.tag_editor.is_some()
+        || app.playlist_prompt.is_some()
+        || app.smart_prompt.is_some()
+        || app.column_editor.is_some()
+        || app.cover_prompt
+    {
+        return;
+    }
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(column) = ui::column_at(size, app, mouse.column, mouse.row) {
+                app.sort_by_column(column);
+            }
+        }
+        // The queue and chapter panes take j/k for their own cursor
+        _ if app.show_queue || app.show_chapters => {}
+        MouseEventKind::ScrollDown => app.move_selection_down(),
+        MouseEventKind::ScrollUp => app.move_selection_up(),
+        _ => {}
+    }
+}
+
+fn handle_cover_input(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Char(c) => app.cover_action(c),
+        _ => {
+            app.cover_prompt = false;
+            app.status_message = None;
+        }
+    }
+}
+
+fn handle_tag_editor_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
+    if key == KeyCode::Enter {
+        app.save_tag_editor();
+        return;
+    }
+    let Some(editor) = app.tag_editor.as_mut() else {
+        return;
+    };
+    match key {
+        KeyCode::Esc => app.tag_editor = None,
+        KeyCode::Tab | KeyCode::Down => editor.next_field(),
+        KeyCode::BackTab | KeyCode::Up => editor.prev_field(),
+        KeyCode::Backspace => editor.backspace(),
+        KeyCode::Char('u') if modifiers.contains(KeyModifiers::CONTROL) => editor.clear(),
+        KeyCode::Char('r') if modifiers.contains(KeyModifiers::CONTROL) => editor.keep(),
+        KeyCode::Char(c) => editor.input(c),
+        _ => {}
+    }
+}
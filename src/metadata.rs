@@ -1,23 +1,49 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use lofty::config::WriteOptions;
+use lofty::file::TaggedFile;
 use lofty::prelude::*;
 use lofty::probe::Probe;
-use lofty::picture::PictureType;
+use lofty::picture::{Picture, PictureType};
+use lofty::tag::{ItemKey, Tag};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct TrackMetadata {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
+    pub album_artist: Option<String>,
     pub track_number: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub year: Option<u32>,
+    pub date: Option<String>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub compilation: bool,
+    pub comment: Option<String>,
+    pub musicbrainz: MusicBrainzIds,
     pub duration: Option<Duration>,
     pub bitrate: Option<u32>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u8>,
+    pub bit_depth: Option<u8>,
     pub album_art: Option<Vec<u8>>,
 }
 
+/// MusicBrainz identifiers as written by Picard and similar taggers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MusicBrainzIds {
+    pub recording_id: Option<String>,
+    pub release_id: Option<String>,
+    pub release_group_id: Option<String>,
+    pub artist_id: Option<String>,
+    pub album_artist_id: Option<String>,
+}
+
 pub fn read_metadata(path: &Path) -> Result<TrackMetadata> {
     let tagged_file = Probe::open(path)?.read()?;
 
@@ -27,21 +53,44 @@ pub fn read_metadata(path: &Path) -> Result<TrackMetadata> {
     } else {
         None
     };
-    let bitrate = properties.overall_bitrate();
-    let sample_rate = properties.sample_rate();
-    let channels = properties.channels();
 
-    let mut title = None;
-    let mut artist = None;
-    let mut album = None;
-    let mut track_number = None;
-    let mut album_art = None;
+    let mut meta = TrackMetadata {
+        duration,
+        bitrate: properties.overall_bitrate(),
+        sample_rate: properties.sample_rate(),
+        channels: properties.channels(),
+        bit_depth: properties.bit_depth(),
+        ..Default::default()
+    };
 
     if let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
-        title = tag.title().map(|s| s.to_string());
-        artist = tag.artist().map(|s| s.to_string());
-        album = tag.album().map(|s| s.to_string());
-        track_number = tag.track();
+        meta.title = tag.title().map(|s| s.to_string());
+        meta.artist = tag.artist().map(|s| s.to_string());
+        meta.album = tag.album().map(|s| s.to_string());
+        meta.album_artist = get_string(tag, &ItemKey::AlbumArtist);
+        meta.track_number = tag.track();
+        meta.track_total = tag.track_total();
+        meta.disc_number = tag.disk();
+        meta.disc_total = tag.disk_total();
+        meta.date = get_string(tag, &ItemKey::RecordingDate)
+            .or_else(|| get_string(tag, &ItemKey::ReleaseDate))
+            .or_else(|| get_string(tag, &ItemKey::Year));
+        meta.year = tag
+            .year()
+            .or_else(|| meta.date.as_deref().and_then(parse_year));
+        meta.genre = tag.genre().map(|s| s.to_string());
+        meta.composer = get_string(tag, &ItemKey::Composer);
+        meta.compilation = get_string(tag, &ItemKey::FlagCompilation)
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        meta.comment = tag.comment().map(|s| s.to_string());
+        meta.musicbrainz = MusicBrainzIds {
+            recording_id: get_string(tag, &ItemKey::MusicBrainzRecordingId),
+            release_id: get_string(tag, &ItemKey::MusicBrainzReleaseId),
+            release_group_id: get_string(tag, &ItemKey::MusicBrainzReleaseGroupId),
+            artist_id: get_string(tag, &ItemKey::MusicBrainzArtistId),
+            album_artist_id: get_string(tag, &ItemKey::MusicBrainzReleaseArtistId),
+        };
 
         // Extract album art
         if let Some(pic) = tag
@@ -50,19 +99,212 @@ pub fn read_metadata(path: &Path) -> Result<TrackMetadata> {
             .find(|p| p.pic_type() == PictureType::CoverFront)
             .or_else(|| tag.pictures().first())
         {
-            album_art = Some(pic.data().to_vec());
+            meta.album_art = Some(pic.data().to_vec());
         }
     }
 
-    Ok(TrackMetadata {
-        title,
-        artist,
-        album,
-        track_number,
-        duration,
-        bitrate,
-        sample_rate,
-        channels,
-        album_art,
-    })
+    Ok(meta)
+}
+
+/// New values for the editable tag fields. `None` leaves a field alone and
+/// an empty string removes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagEdit {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<String>,
+    pub disc_number: Option<String>,
+    pub year: Option<String>,
+    pub genre: Option<String>,
+}
+
+impl TagEdit {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Reject numbers that can't be written, before any file is touched
+    pub fn validate(&self) -> Result<()> {
+        parse_number("Track", &self.track_number)?;
+        parse_number("Disc", &self.disc_number)?;
+        parse_number("Year", &self.year)?;
+        Ok(())
+    }
+
+    /// The edit that puts back `meta`'s values for every field this edit changes
+    pub fn undo_for(&self, meta: &TrackMetadata) -> TagEdit {
+        fn old(changed: &Option<String>, value: Option<String>) -> Option<String> {
+            changed.as_ref().map(|_| value.unwrap_or_default())
+        }
+        TagEdit {
+            title: old(&self.title, meta.title.clone()),
+            artist: old(&self.artist, meta.artist.clone()),
+            album: old(&self.album, meta.album.clone()),
+            album_artist: old(&self.album_artist, meta.album_artist.clone()),
+            track_number: old(&self.track_number, meta.track_number.map(|n| n.to_string())),
+            disc_number: old(&self.disc_number, meta.disc_number.map(|n| n.to_string())),
+            year: old(&self.year, meta.year.map(|n| n.to_string())),
+            genre: old(&self.genre, meta.genre.clone()),
+        }
+    }
+}
+
+/// `Ok(None)` for unchanged or cleared fields
+fn parse_number(label: &str, value: &Option<String>) -> Result<Option<u32>> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(v) => match v.parse() {
+            Ok(n) => Ok(Some(n)),
+            Err(_) => bail!("{} must be a number, not '{}'", label, v),
+        },
+    }
+}
+
+/// Write `edit` into the tag `read_metadata` reads from, creating one when the
+/// file has none.
+pub fn write_tags(path: &Path, edit: &TagEdit) -> Result<()> {
+    edit.validate()?;
+    let mut tagged_file = Probe::open(path)?.read()?;
+    let tag = writable_tag(&mut tagged_file)?;
+
+    if let Some(v) = &edit.title {
+        if v.trim().is_empty() {
+            tag.remove_title();
+        } else {
+            tag.set_title(v.trim().to_string());
+        }
+    }
+    if let Some(v) = &edit.artist {
+        if v.trim().is_empty() {
+            tag.remove_artist();
+        } else {
+            tag.set_artist(v.trim().to_string());
+        }
+    }
+    if let Some(v) = &edit.album {
+        if v.trim().is_empty() {
+            tag.remove_album();
+        } else {
+            tag.set_album(v.trim().to_string());
+        }
+    }
+    if let Some(v) = &edit.album_artist {
+        if v.trim().is_empty() {
+            tag.remove_key(&ItemKey::AlbumArtist);
+        } else {
+            tag.insert_text(ItemKey::AlbumArtist, v.trim().to_string());
+        }
+    }
+    if let Some(v) = &edit.genre {
+        if v.trim().is_empty() {
+            tag.remove_genre();
+        } else {
+            tag.set_genre(v.trim().to_string());
+        }
+    }
+    if edit.track_number.is_some() {
+        match parse_number("Track", &edit.track_number)? {
+            Some(n) => tag.set_track(n),
+            None => tag.remove_track(),
+        }
+    }
+    if edit.disc_number.is_some() {
+        match parse_number("Disc", &edit.disc_number)? {
+            Some(n) => tag.set_disk(n),
+            None => tag.remove_disk(),
+        }
+    }
+    if edit.year.is_some() {
+        match parse_number("Year", &edit.year)? {
+            Some(n) => tag.set_year(n),
+            None => tag.remove_year(),
+        }
+    }
+
+    save(&tagged_file, path)
+}
+
+/// A front cover picture from image file contents (JPEG, PNG, GIF, BMP or TIFF)
+pub fn cover_picture(image: &[u8]) -> Result<Picture> {
+    let mut picture =
+        Picture::from_reader(&mut &image[..]).context("Not a supported image format")?;
+    picture.set_pic_type(PictureType::CoverFront);
+    Ok(picture)
+}
+
+/// Make `picture` the front cover of `path`, replacing any front cover
+/// already embedded
+pub fn embed_cover(path: &Path, picture: &Picture) -> Result<()> {
+    let mut tagged_file = Probe::open(path)?.read()?;
+    let tag = writable_tag(&mut tagged_file)?;
+    tag.remove_picture_type(PictureType::CoverFront);
+    tag.push_picture(picture.clone());
+    save(&tagged_file, path)
+}
+
+/// Remove embedded pictures larger than `max_bytes` from every tag in
+/// `path`, returning how many went. The file is only rewritten when
+/// something was removed.
+pub fn strip_large_pictures(path: &Path, max_bytes: usize) -> Result<usize> {
+    let mut tagged_file = Probe::open(path)?.read()?;
+    let tag_types: Vec<_> = tagged_file.tags().iter().map(|t| t.tag_type()).collect();
+
+    let mut removed = 0;
+    for tag_type in tag_types {
+        let Some(tag) = tagged_file.tag_mut(tag_type) else {
+            continue;
+        };
+        for i in (0..tag.pictures().len()).rev() {
+            if tag.pictures()[i].data().len() > max_bytes {
+                tag.remove_picture(i);
+                removed += 1;
+            }
+        }
+    }
+    if removed > 0 {
+        save(&tagged_file, path)?;
+    }
+    Ok(removed)
+}
+
+/// The tag `read_metadata` reads from (the primary tag, or the first one
+/// present), created with the format's usual type when the file has none
+fn writable_tag(tagged_file: &mut TaggedFile) -> Result<&mut Tag> {
+    let tag_type = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+        .map(|t| t.tag_type())
+        .unwrap_or_else(|| tagged_file.primary_tag_type());
+    if tagged_file.tag(tag_type).is_none() {
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    tagged_file
+        .tag_mut(tag_type)
+        .context("File format doesn't support tags")
+}
+
+fn save(tagged_file: &TaggedFile, path: &Path) -> Result<()> {
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .with_context(|| format!("Failed to write tags to {}", path.display()))?;
+    Ok(())
+}
+
+fn get_string(tag: &Tag, key: &ItemKey) -> Option<String> {
+    tag.get_string(key)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Pull a four-digit year out of dates like "1997", "1997-05-12" or "1997/05"
+fn parse_year(date: &str) -> Option<u32> {
+    let digits: String = date.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() == 4 {
+        digits.parse().ok()
+    } else {
+        None
+    }
 }
@@ -0,0 +1,520 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::cover;
+use crate::library::Track;
+
+/// Lyrics files that share a track's name and move with it
+const LYRICS_EXTENSIONS: &[&str] = &["lrc", "txt"];
+
+/// Longest file or folder name written, in bytes; most filesystems stop at 255
+const MAX_NAME_BYTES: usize = 240;
+
+/// Tag fields a template can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    AlbumArtist,
+    Artist,
+    Album,
+    Title,
+    Track,
+    Disc,
+    Year,
+    Genre,
+    Composer,
+    Ext,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "albumartist" => Self::AlbumArtist,
+            "artist" => Self::Artist,
+            "album" => Self::Album,
+            "title" => Self::Title,
+            "track" => Self::Track,
+            "disc" => Self::Disc,
+            "year" => Self::Year,
+            "genre" => Self::Genre,
+            "composer" => Self::Composer,
+            "ext" => Self::Ext,
+            _ => return None,
+        })
+    }
+
+    fn value(self, track: &Track, width: usize) -> String {
+        let number = |n: Option<u32>| {
+            n.map(|n| format!("{:0width$}", n, width = width))
+                .unwrap_or_default()
+        };
+        match self {
+            Self::AlbumArtist => track.album_artist_or_artist().to_string(),
+            Self::Artist => track.artist.clone(),
+            Self::Album => track.album.clone(),
+            Self::Title => track.title.clone(),
+            Self::Track => number(track.track_number),
+            // Only multi-disc albums get a disc number, so `{disc}{track:02}`
+            // gives "01" on single discs and "101" on sets
+            Self::Disc => {
+                let multi_disc = track.disc_total.is_some_and(|t| t > 1)
+                    || track.disc_number.is_some_and(|n| n > 1);
+                if multi_disc {
+                    number(track.disc_number)
+                } else {
+                    String::new()
+                }
+            }
+            Self::Year => number(track.year),
+            Self::Genre => track.genre.clone().unwrap_or_default(),
+            Self::Composer => track.composer.clone().unwrap_or_default(),
+            Self::Ext => track
+                .path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(String),
+    /// A field with its zero-padding width (`{track:02}` → 2)
+    Field(Field, usize),
+}
+
+/// A layout such as `{albumartist}/{year} - {album}/{disc}{track:02} {title}.{ext}`,
+/// relative to the library root
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    components: Vec<Vec<Token>>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let components = template
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .map(|c| parse_component(template, c))
+            .collect::<Result<Vec<_>>>()?;
+        if components.is_empty() {
+            bail!("Empty organize template");
+        }
+        Ok(Self {
+            source: template.to_string(),
+            components,
+        })
+    }
+
+    /// Where `track` belongs, relative to the library root
+    pub fn render(&self, track: &Track) -> PathBuf {
+        let last = self.components.len() - 1;
+        self.components
+            .iter()
+            .enumerate()
+            .map(|(i, tokens)| {
+                let name: String = tokens
+                    .iter()
+                    .map(|token| match token {
+                        Token::Literal(text) => text.clone(),
+                        Token::Field(field, width) => sanitize(&field.value(track, *width)),
+                    })
+                    .collect();
+                clean_name(&name, i == last)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_component(template: &str, component: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = component;
+    while !rest.is_empty() {
+        match rest.find('{') {
+            Some(0) => {
+                let Some(end) = rest.find('}') else {
+                    bail!("Unclosed '{{' in organize template '{}'", template);
+                };
+                let spec = &rest[1..end];
+                let (name, width) = match spec.split_once(':') {
+                    Some((name, width)) => {
+                        let Ok(width) = width.parse() else {
+                            bail!("Bad width in '{{{}}}' in organize template '{}'", spec, template);
+                        };
+                        (name, width)
+                    }
+                    None => (spec, 0),
+                };
+                let Some(field) = Field::parse(&name.to_lowercase()) else {
+                    bail!("Unknown field '{{{}}}' in organize template '{}'", name, template);
+                };
+                tokens.push(Token::Field(field, width));
+                rest = &rest[end + 1..];
+            }
+            Some(start) => {
+                tokens.push(Token::Literal(rest[..start].to_string()));
+                rest = &rest[start..];
+            }
+            None => {
+                tokens.push(Token::Literal(rest.to_string()));
+                rest = "";
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Replace characters that are invalid in file names on common filesystems
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Tidy a rendered name: drop separators left dangling by empty fields
+/// ("{year} - {album}" without a year), trailing dots, and excess length
+fn clean_name(name: &str, is_file: bool) -> String {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if is_file && !ext.is_empty() => (stem, Some(ext)),
+        _ => (name, None),
+    };
+    let trim = |c: char| c.is_whitespace() || matches!(c, '-' | '_' | '.');
+    let mut stem = stem.trim_matches(trim).to_string();
+    if stem.is_empty() {
+        stem = "Unknown".to_string();
+    }
+    let ext_len = ext.map_or(0, |e| e.len() + 1);
+    while stem.len() + ext_len > MAX_NAME_BYTES {
+        stem.pop();
+    }
+    let stem = stem.trim_end_matches(trim);
+    match ext {
+        Some(ext) => format!("{}.{}", stem, ext),
+        None => stem.to_string(),
+    }
+}
+
+/// One track's move, with the lyrics files that go along with it
+#[derive(Debug, Clone)]
+pub struct FileMove {
+    /// Library index of the track
+    pub track: usize,
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub sidecars: Vec<(PathBuf, PathBuf)>,
+    /// The template's path was taken, so a " (2)"-style suffix was added
+    pub renamed: bool,
+}
+
+/// A cover image following its tracks. It is moved to the first folder and
+/// copied to any others; when some tracks stay behind the original is kept.
+#[derive(Debug, Clone)]
+pub struct CoverMove {
+    pub from: PathBuf,
+    pub to: Vec<PathBuf>,
+    pub keep_original: bool,
+}
+
+/// Everything `execute` would do
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub root: PathBuf,
+    pub template: String,
+    pub moves: Vec<FileMove>,
+    pub covers: Vec<CoverMove>,
+    /// Tracks already where the template puts them
+    pub unchanged: usize,
+    /// Files split by a CUE sheet, left next to their sheet
+    pub cue_files: Vec<PathBuf>,
+}
+
+/// Work out where each of `tracks` (library indices) goes under `root`
+pub fn plan(
+    library: &[Track],
+    tracks: &[usize],
+    root: &Path,
+    template: &Template,
+    cover_files: &[String],
+) -> Plan {
+    let mut moves = Vec::new();
+    let mut unchanged = 0;
+    // Lowercased, so two names differing only in case don't collide on
+    // case-insensitive filesystems
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut cue_files: Vec<PathBuf> = Vec::new();
+
+    for &i in tracks {
+        let track = &library[i];
+        // Several CUE tracks share one file, which the sheet refers to by name
+        if track.cue.is_some() {
+            if !cue_files.contains(&track.path) {
+                cue_files.push(track.path.clone());
+            }
+            continue;
+        }
+        let target = root.join(template.render(track));
+        if target == track.path {
+            unchanged += 1;
+            claimed.insert(key(&target));
+            continue;
+        }
+        let (to, renamed) = free_path(&target, &track.path, &mut claimed);
+        if to == track.path {
+            unchanged += 1;
+            continue;
+        }
+        let sidecars = lyrics_for(&track.path)
+            .into_iter()
+            .map(|from| {
+                let ext = from.extension().unwrap_or_default().to_owned();
+                let dest = to.with_extension(ext);
+                (from, dest)
+            })
+            .collect();
+        moves.push(FileMove {
+            track: i,
+            from: track.path.clone(),
+            to,
+            sidecars,
+            renamed,
+        });
+    }
+
+    let covers = plan_covers(library, &moves, cover_files);
+    Plan {
+        root: root.to_path_buf(),
+        template: template.to_string(),
+        moves,
+        covers,
+        unchanged,
+        cue_files,
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// `target`, or `target (2)`, `target (3)`… when it is already used by
+/// another file or claimed by an earlier move in the plan
+fn free_path(target: &Path, source: &Path, claimed: &mut HashSet<String>) -> (PathBuf, bool) {
+    let taken = |path: &Path, claimed: &HashSet<String>| {
+        claimed.contains(&key(path)) || (path.exists() && !same_file(path, source))
+    };
+    let mut candidate = target.to_path_buf();
+    let mut n = 2;
+    while taken(&candidate, claimed) {
+        let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match target.extension() {
+            Some(ext) => format!("{} ({}).{}", stem, n, ext.to_string_lossy()),
+            None => format!("{} ({})", stem, n),
+        };
+        candidate = target.with_file_name(name);
+        n += 1;
+    }
+    claimed.insert(key(&candidate));
+    let renamed = candidate != target;
+    (candidate, renamed)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Lyrics next to `path` with the same file stem
+fn lyrics_for(path: &Path) -> Vec<PathBuf> {
+    LYRICS_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+fn plan_covers(library: &[Track], moves: &[FileMove], cover_files: &[String]) -> Vec<CoverMove> {
+    // Destination folders per source folder, in first-seen order
+    let mut targets: Vec<(PathBuf, Vec<PathBuf>)> = Vec::new();
+    let mut moving: HashMap<PathBuf, usize> = HashMap::new();
+    for m in moves {
+        let (Some(from_dir), Some(to_dir)) = (m.from.parent(), m.to.parent()) else {
+            continue;
+        };
+        *moving.entry(from_dir.to_path_buf()).or_default() += 1;
+        match targets.iter_mut().find(|(dir, _)| dir == from_dir) {
+            Some((_, dirs)) if dirs.iter().any(|d| d == to_dir) => {}
+            Some((_, dirs)) => dirs.push(to_dir.to_path_buf()),
+            None => targets.push((from_dir.to_path_buf(), vec![to_dir.to_path_buf()])),
+        }
+    }
+
+    let mut covers = Vec::new();
+    for (from_dir, dirs) in targets {
+        let tracks_in_dir = library
+            .iter()
+            .filter(|t| t.path.parent() == Some(from_dir.as_path()))
+            .count();
+        let keep_original = moving.get(&from_dir).copied().unwrap_or(0) < tracks_in_dir;
+        for image in cover::find_sidecars(&from_dir, cover_files) {
+            let Some(name) = image.file_name() else {
+                continue;
+            };
+            let to: Vec<PathBuf> = dirs
+                .iter()
+                .filter(|d| **d != from_dir)
+                .map(|d| d.join(name))
+                .filter(|p| !p.exists())
+                .collect();
+            if !to.is_empty() {
+                covers.push(CoverMove {
+                    from: image,
+                    to,
+                    keep_original,
+                });
+            }
+        }
+    }
+    covers
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rel = |p: &Path| p.strip_prefix(&self.root).unwrap_or(p).display().to_string();
+        for m in &self.moves {
+            writeln!(f, "{}", rel(&m.from))?;
+            writeln!(
+                f,
+                "  → {}{}",
+                rel(&m.to),
+                if m.renamed { "  (name taken)" } else { "" }
+            )?;
+            for (_, to) in &m.sidecars {
+                writeln!(f, "  + {}", rel(to))?;
+            }
+        }
+        for c in &self.covers {
+            for (i, to) in c.to.iter().enumerate() {
+                let verb = if c.keep_original || i > 0 { "copy" } else { "move" };
+                writeln!(f, "{} {} → {}", verb, rel(&c.from), rel(to))?;
+            }
+        }
+        for file in &self.cue_files {
+            writeln!(f, "skip {}  (split by a CUE sheet)", rel(file))?;
+        }
+        writeln!(
+            f,
+            "\n{} file(s) to move, {} already in place (template: {})",
+            self.moves.len(),
+            self.unchanged,
+            self.template
+        )
+    }
+}
+
+/// What `execute` did
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// Library index, old path and new path of every track moved
+    pub moved: Vec<(usize, PathBuf, PathBuf)>,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Carry out `plan`. A failed file is reported and skipped; the rest still
+/// move. Folders left empty are removed, up to the library root.
+pub fn execute(plan: &Plan) -> Outcome {
+    let mut outcome = Outcome::default();
+    let mut emptied: HashSet<PathBuf> = HashSet::new();
+
+    for m in &plan.moves {
+        if let Err(e) = move_file(&m.from, &m.to) {
+            outcome.errors.push((m.from.clone(), format!("{:#}", e)));
+            continue;
+        }
+        for (from, to) in &m.sidecars {
+            if let Err(e) = move_file(from, to) {
+                outcome.errors.push((from.clone(), format!("{:#}", e)));
+            }
+        }
+        if let Some(dir) = m.from.parent() {
+            emptied.insert(dir.to_path_buf());
+        }
+        outcome.moved.push((m.track, m.from.clone(), m.to.clone()));
+    }
+
+    for c in &plan.covers {
+        let Some((first, rest)) = c.to.split_first() else {
+            continue;
+        };
+        let result = if c.keep_original {
+            c.to.iter().try_for_each(|to| copy_file(&c.from, to))
+        } else {
+            rest.iter()
+                .try_for_each(|to| copy_file(&c.from, to))
+                .and_then(|()| move_file(&c.from, first))
+        };
+        if let Err(e) = result {
+            outcome.errors.push((c.from.clone(), format!("{:#}", e)));
+        }
+    }
+
+    for dir in emptied {
+        remove_empty_dirs(&dir, &plan.root);
+    }
+    outcome
+}
+
+/// Rename, falling back to copy and delete across filesystems. Never
+/// overwrites.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        bail!("{} already exists", to.display());
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+        std::fs::remove_file(from)
+            .with_context(|| format!("Failed to remove {}", from.display()))?;
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+/// Remove `dir` and its parents while they are empty, stopping at `root`
+fn remove_empty_dirs(dir: &Path, root: &Path) {
+    let mut current = Some(dir);
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) || std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
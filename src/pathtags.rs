@@ -0,0 +1,457 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::library::{self, ScanOptions};
+use crate::metadata::{self, TrackMetadata};
+
+/// Tag fields a path pattern can fill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Track,
+    Disc,
+    Year,
+    Genre,
+    /// `%ignore%`: matches text that isn't a tag, e.g. a "CD1" folder
+    Ignore,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "title" => Self::Title,
+            "artist" => Self::Artist,
+            "album" => Self::Album,
+            "albumartist" => Self::AlbumArtist,
+            "track" => Self::Track,
+            "disc" => Self::Disc,
+            "year" => Self::Year,
+            "genre" => Self::Genre,
+            "ignore" => Self::Ignore,
+            _ => return None,
+        })
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Self::Track | Self::Disc | Self::Year)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Field(Field),
+}
+
+/// A layout such as `%artist%/%album%/%track% - %title%`. It is matched
+/// against the last path components, with the extension removed from the
+/// file name, so it works at any depth below the library root.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    source: String,
+    components: Vec<Vec<Token>>,
+}
+
+impl PathPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let components = pattern
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .map(|c| parse_component(pattern, c))
+            .collect::<Result<Vec<_>>>()?;
+        if components.is_empty() {
+            bail!("Empty path pattern");
+        }
+        Ok(Self {
+            source: pattern.to_string(),
+            components,
+        })
+    }
+
+    /// Tags read from `rel_path`, or None when the pattern doesn't fit
+    pub fn infer(&self, rel_path: &Path) -> Option<InferredTags> {
+        let mut parts: Vec<String> = rel_path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        if parts.len() < self.components.len() {
+            return None;
+        }
+        let file = parts.pop()?;
+        let stem = Path::new(&file)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or(file);
+        parts.push(stem);
+
+        let mut tags = InferredTags::default();
+        let tail = &parts[parts.len() - self.components.len()..];
+        for (tokens, text) in self.components.iter().zip(tail) {
+            for (field, value) in match_tokens(tokens, text)? {
+                tags.set(field, value.trim());
+            }
+        }
+        Some(tags)
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_component(pattern: &str, component: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = component;
+    while !rest.is_empty() {
+        match rest.find('%') {
+            Some(0) => {
+                let Some(end) = rest[1..].find('%') else {
+                    bail!("Unclosed '%' in path pattern '{}'", pattern);
+                };
+                let name = &rest[1..end + 1];
+                let Some(field) = Field::parse(&name.to_lowercase()) else {
+                    bail!("Unknown field '%{}%' in path pattern '{}'", name, pattern);
+                };
+                if matches!(tokens.last(), Some(Token::Field(_))) {
+                    bail!(
+                        "Fields need text between them in path pattern '{}'",
+                        pattern
+                    );
+                }
+                tokens.push(Token::Field(field));
+                rest = &rest[end + 2..];
+            }
+            Some(start) => {
+                tokens.push(Token::Literal(rest[..start].to_string()));
+                rest = &rest[start..];
+            }
+            None => {
+                tokens.push(Token::Literal(rest.to_string()));
+                rest = "";
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Match one path component, backtracking over where each field ends.
+/// Literals compare case-insensitively; fields must be non-empty and numeric
+/// fields all digits.
+fn match_tokens<'a>(tokens: &[Token], text: &'a str) -> Option<Vec<(Field, &'a str)>> {
+    let Some((first, rest)) = tokens.split_first() else {
+        return text.is_empty().then(Vec::new);
+    };
+    match first {
+        Token::Literal(lit) => {
+            let head = text.get(..lit.len())?;
+            if !head.eq_ignore_ascii_case(lit) {
+                return None;
+            }
+            match_tokens(rest, &text[lit.len()..])
+        }
+        Token::Field(field) => {
+            let ends = text
+                .char_indices()
+                .map(|(i, c)| i + c.len_utf8())
+                .collect::<Vec<_>>();
+            for end in ends {
+                let value = &text[..end];
+                if field.is_numeric() && !value.trim().chars().all(|c| c.is_ascii_digit()) {
+                    break;
+                }
+                if let Some(mut found) = match_tokens(rest, &text[end..]) {
+                    if !value.trim().is_empty() {
+                        found.insert(0, (*field, value));
+                        return Some(found);
+                    }
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Tags recovered from a file's location
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InferredTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+}
+
+impl InferredTags {
+    fn set(&mut self, field: Field, value: &str) {
+        let text = Some(value.to_string());
+        let number = value.parse().ok();
+        match field {
+            Field::Title => self.title = text,
+            Field::Artist => self.artist = text,
+            Field::Album => self.album = text,
+            Field::AlbumArtist => self.album_artist = text,
+            Field::Track => self.track_number = number,
+            Field::Disc => self.disc_number = number,
+            Field::Year => self.year = number,
+            Field::Genre => self.genre = text,
+            Field::Ignore => {}
+        }
+    }
+
+    /// Copy inferred values into fields the tags left empty. Returns the
+    /// fields that were filled, with their new values.
+    pub fn fill(&self, meta: &mut TrackMetadata) -> Vec<(&'static str, String)> {
+        let mut filled = Vec::new();
+        fill_field(&mut filled, "title", &mut meta.title, &self.title);
+        fill_field(&mut filled, "artist", &mut meta.artist, &self.artist);
+        fill_field(&mut filled, "album", &mut meta.album, &self.album);
+        fill_field(&mut filled, "album artist", &mut meta.album_artist, &self.album_artist);
+        fill_field(&mut filled, "track", &mut meta.track_number, &self.track_number);
+        fill_field(&mut filled, "disc", &mut meta.disc_number, &self.disc_number);
+        fill_field(&mut filled, "year", &mut meta.year, &self.year);
+        fill_field(&mut filled, "genre", &mut meta.genre, &self.genre);
+        filled
+    }
+}
+
+fn fill_field<T: Clone + ToString>(
+    filled: &mut Vec<(&'static str, String)>,
+    name: &'static str,
+    target: &mut Option<T>,
+    value: &Option<T>,
+) {
+    if target.is_none() {
+        if let Some(v) = value {
+            filled.push((name, v.to_string()));
+            *target = Some(v.clone());
+        }
+    }
+}
+
+/// Parse every pattern, failing on the first invalid one
+pub fn parse_patterns(patterns: &[String]) -> Result<Vec<PathPattern>> {
+    patterns.iter().map(|p| PathPattern::parse(p)).collect()
+}
+
+/// Tags from the first pattern that fits `rel_path`
+pub fn infer(patterns: &[PathPattern], rel_path: &Path) -> Option<InferredTags> {
+    patterns.iter().find_map(|p| p.infer(rel_path))
+}
+
+/// A file a pattern fills tags in for
+pub struct InferredFile {
+    /// Relative to the library root
+    pub path: PathBuf,
+    pub pattern: PathPattern,
+    /// Tag names and the values the pattern supplies
+    pub tags: Vec<(&'static str, String)>,
+}
+
+/// What `tunebox infer` would change, without touching any file
+pub struct Preview {
+    pub patterns: Vec<PathPattern>,
+    pub files_checked: usize,
+    /// Files with missing tags a pattern fills, and the values it supplies
+    pub inferred: Vec<InferredFile>,
+    /// Files missing a title, artist or album that no pattern fits
+    pub unmatched: Vec<PathBuf>,
+}
+
+/// Check every file a scan of `dir` would pick up against `patterns`
+pub fn preview(dir: &Path, options: &ScanOptions, patterns: Vec<PathPattern>) -> Result<Preview> {
+    let files = library::audio_files(dir, options)?;
+    let mut inferred = Vec::new();
+    let mut unmatched = Vec::new();
+
+    for path in &files {
+        // Unplayable files never reach the library
+        if library::sniff_format(path).is_err() {
+            continue;
+        }
+        let mut meta = metadata::read_metadata(path).unwrap_or_default();
+        let rel = path.strip_prefix(dir).unwrap_or(path);
+        let found = patterns
+            .iter()
+            .find_map(|p| p.infer(rel).map(|tags| (p, tags)));
+        let missing_core = meta.title.is_none() || meta.artist.is_none() || meta.album.is_none();
+        match found {
+            Some((pattern, tags)) => {
+                let filled = tags.fill(&mut meta);
+                if !filled.is_empty() {
+                    inferred.push(InferredFile {
+                        path: rel.to_path_buf(),
+                        pattern: pattern.clone(),
+                        tags: filled,
+                    });
+                }
+            }
+            None if missing_core => unmatched.push(rel.to_path_buf()),
+            None => {}
+        }
+    }
+
+    Ok(Preview {
+        patterns,
+        files_checked: files.len(),
+        inferred,
+        unmatched,
+    })
+}
+
+impl fmt::Display for Preview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.inferred {
+            writeln!(f, "{}  [{}]", file.path.display(), file.pattern)?;
+            for (name, value) in &file.tags {
+                writeln!(f, "    {}: {}", name, value)?;
+            }
+        }
+        if !self.unmatched.is_empty() {
+            writeln!(f, "\nMissing tags, no pattern fits ({}):", self.unmatched.len())?;
+            for path in &self.unmatched {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        let patterns: Vec<String> = self.patterns.iter().map(|p| p.to_string()).collect();
+        writeln!(
+            f,
+            "\n{} of {} files would get tags from their path (patterns: {})",
+            self.inferred.len(),
+            self.files_checked,
+            if patterns.is_empty() { "none".to_string() } else { patterns.join(", ") }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer_with(pattern: &str, path: &str) -> Option<InferredTags> {
+        PathPattern::parse(pattern).unwrap().infer(Path::new(path))
+    }
+
+    #[test]
+    fn matches_the_last_components() {
+        let tags = infer_with(
+            "%artist%/%album%/%track% - %title%",
+            "Jazz/Miles Davis/Kind of Blue/01 - So What.flac",
+        )
+        .unwrap();
+        assert_eq!(tags.artist.as_deref(), Some("Miles Davis"));
+        assert_eq!(tags.album.as_deref(), Some("Kind of Blue"));
+        assert_eq!(tags.track_number, Some(1));
+        assert_eq!(tags.title.as_deref(), Some("So What"));
+        assert!(infer_with("%artist%/%album%/%title%", "Album/song.mp3").is_none());
+    }
+
+    #[test]
+    fn backtracks_over_repeated_separators() {
+        // The artist ends at the first separator that lets the rest match
+        let tags = infer_with(
+            "%track% - %artist% - %title%",
+            "03 - Band - Song - Live Version.mp3",
+        )
+        .unwrap();
+        assert_eq!(tags.track_number, Some(3));
+        assert_eq!(tags.artist.as_deref(), Some("Band"));
+        assert_eq!(tags.title.as_deref(), Some("Song - Live Version"));
+
+        let tags = infer_with("%artist% - %title% (%year%)", "A - B (Remix) (1999).flac").unwrap();
+        assert_eq!(tags.title.as_deref(), Some("B (Remix)"));
+        assert_eq!(tags.year, Some(1999));
+    }
+
+    #[test]
+    fn numeric_fields_take_digits_only() {
+        assert!(infer_with("%track% %title%", "Intro 01.mp3").is_none());
+        let tags = infer_with("%disc%-%track% %title%", "2-05 Song.mp3").unwrap();
+        assert_eq!((tags.disc_number, tags.track_number), (Some(2), Some(5)));
+    }
+
+    #[test]
+    fn literals_ignore_case_and_fields_are_not_empty() {
+        let tags = infer_with("%album%/cd%disc%/%title%", "Box Set/CD2/Song.ogg").unwrap();
+        assert_eq!(tags.disc_number, Some(2));
+        assert!(infer_with("%artist% - %title%", " - Song.mp3").is_none());
+        assert!(infer_with("%artist% - %title%", "Artist - .mp3").is_none());
+    }
+
+    #[test]
+    fn ignore_and_unicode() {
+        let tags = infer_with(
+            "%albumartist%/%album%/%ignore%/%title%",
+            "Björk/Homogenic/Disc 1/Jóga.flac",
+        )
+        .unwrap();
+        assert_eq!(tags.album_artist.as_deref(), Some("Björk"));
+        assert_eq!(tags.title.as_deref(), Some("Jóga"));
+        let tags = infer_with("%artist% – %title%", "Sigur Rós – Hoppípolla.flac").unwrap();
+        assert_eq!(tags.artist.as_deref(), Some("Sigur Rós"));
+    }
+
+    #[test]
+    fn bad_patterns() {
+        assert!(PathPattern::parse("").is_err());
+        assert!(PathPattern::parse("///").is_err());
+        assert!(PathPattern::parse("%artist/%title%").is_err());
+        assert!(PathPattern::parse("%band%/%title%").is_err());
+        assert!(PathPattern::parse("%artist%%title%").is_err());
+        assert!(PathPattern::parse("%ARTIST%/%Title%").is_ok());
+    }
+
+    #[test]
+    fn fill_keeps_existing_tags() {
+        let tags = infer_with(
+            "%artist%/%year% %album%/%track% %title%",
+            "Band/1999 Record/02 Song.mp3",
+        )
+        .unwrap();
+        let mut meta = TrackMetadata {
+            title: Some("Real Title".to_string()),
+            ..Default::default()
+        };
+        let filled = tags.fill(&mut meta);
+        assert_eq!(meta.title.as_deref(), Some("Real Title"));
+        assert_eq!(meta.artist.as_deref(), Some("Band"));
+        assert_eq!(meta.year, Some(1999));
+        let names: Vec<&str> = filled.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["artist", "album", "track", "year"]);
+    }
+
+    #[test]
+    fn first_fitting_pattern_wins() {
+        let patterns = parse_patterns(&[
+            "%artist%/%album%/%track% %title%".to_string(),
+            "%title%".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            infer(&patterns, Path::new("loose file.mp3"))
+                .unwrap()
+                .title
+                .as_deref(),
+            Some("loose file")
+        );
+        assert_eq!(
+            infer(&patterns, Path::new("A/B/1 C.mp3"))
+                .unwrap()
+                .artist
+                .as_deref(),
+            Some("A")
+        );
+    }
+}
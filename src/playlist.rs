@@ -0,0 +1,719 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config;
+use crate::cue;
+use crate::library::Track;
+
+/// Extensions of the playlist files tunebox reads and writes
+pub const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls", "xspf"];
+
+/// Playlists saved from the player go here unless one of the same name
+/// already exists under the library
+pub const DEFAULT_EXTENSION: &str = "m3u8";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    /// Plain or extended M3U; `.m3u8` is the same, always UTF-8
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    /// The format a file's extension names, if it's a playlist at all
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u),
+            "pls" => Some(Self::Pls),
+            "xspf" => Some(Self::Xspf),
+            _ => None,
+        }
+    }
+}
+
+/// One line of a playlist. `path` is absolute; whether the file is there is
+/// only checked when the playlist is matched against the library.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Seconds
+    pub duration: Option<f64>,
+}
+
+impl PlaylistEntry {
+    /// A track cut from a larger file by a CUE sheet is stored as the
+    /// sheet, which other players can open, rather than the whole file
+    pub fn from_track(track: &Track) -> Self {
+        Self {
+            path: track.cue.as_ref().map_or(&track.path, |cue| &cue.sheet).clone(),
+            title: Some(track.title.clone()),
+            artist: Some(track.artist.clone()),
+            duration: (track.duration > 0.0).then_some(track.duration),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    pub path: PathBuf,
+    /// The title stored in the file, else the file name
+    pub name: String,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+impl Playlist {
+    /// An empty playlist to be written to `path`
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            name: file_stem(&path),
+            path,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Read `path` in the format its extension names. Entries for missing
+    /// files are kept; see `resolve`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = PlaylistFormat::from_path(path)
+            .with_context(|| format!("Not a playlist: {}", path.display()))?;
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let text = cue::decode_text(&bytes);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let (name, entries) = match format {
+            PlaylistFormat::M3u => parse_m3u(&text, dir),
+            PlaylistFormat::Pls => parse_pls(&text, dir),
+            PlaylistFormat::Xspf => parse_xspf(&text, dir)?,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            name: name.unwrap_or_else(|| file_stem(path)),
+            entries,
+        })
+    }
+
+    /// Write the playlist back to `path`. Files below the playlist's folder
+    /// are stored relative to it, so the folder can be moved as a whole.
+    pub fn save(&self) -> Result<()> {
+        let format = PlaylistFormat::from_path(&self.path)
+            .with_context(|| format!("Not a playlist: {}", self.path.display()))?;
+        let dir = self.path.parent().unwrap_or(Path::new(""));
+        let text = match format {
+            PlaylistFormat::M3u => write_m3u(self, dir),
+            PlaylistFormat::Pls => write_pls(self, dir),
+            PlaylistFormat::Xspf => write_xspf(self, dir),
+        };
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// The library track for each entry, or `None` where the file is missing
+    /// or isn't in the library. Tracks cut from one file by a CUE sheet are
+    /// found by the sheet's path or the file's, and told apart by title, or
+    /// "artist - title" as older PLS files have it.
+    pub fn resolve(&self, library: &[Track]) -> Vec<Option<usize>> {
+        let mut by_path: HashMap<&Path, Vec<usize>> = HashMap::new();
+        for (i, track) in library.iter().enumerate() {
+            by_path.entry(track.path.as_path()).or_default().push(i);
+            if let Some(cue) = &track.cue {
+                by_path.entry(cue.sheet.as_path()).or_default().push(i);
+            }
+        }
+        self.entries
+            .iter()
+            .map(|entry| {
+                let candidates = by_path.get(entry.path.as_path())?;
+                let by_title = entry.title.as_ref().and_then(|title| {
+                    candidates
+                        .iter()
+                        .find(|&&i| {
+                            let track = &library[i];
+                            track.title.eq_ignore_ascii_case(title)
+                                || format!("{} - {}", track.artist, track.title)
+                                    .eq_ignore_ascii_case(title)
+                        })
+                });
+                by_title.or(candidates.first()).copied()
+            })
+            .collect()
+    }
+}
+
+/// Point entries of the playlists at `paths` to the new locations of moved
+/// files. Only playlists with a moved entry are rewritten; returns how many.
+pub fn rename_entries(paths: &[PathBuf], moves: &[(PathBuf, PathBuf)]) -> Result<usize> {
+    let moves: HashMap<&Path, &PathBuf> = moves.iter().map(|(from, to)| (from.as_path(), to)).collect();
+    let mut rewritten = 0;
+    for path in paths {
+        // One that can't be read can't be played from either
+        let Ok(mut playlist) = Playlist::load(path) else {
+            continue;
+        };
+        let mut changed = false;
+        for entry in &mut playlist.entries {
+            if let Some(&to) = moves.get(entry.path.as_path()) {
+                entry.path = to.clone();
+                changed = true;
+            }
+        }
+        if changed {
+            playlist.save()?;
+            rewritten += 1;
+        }
+    }
+    Ok(rewritten)
+}
+
+/// Where playlists saved from the player go: `~/.tunebox/playlists`
+pub fn playlists_dir() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("playlists"))
+}
+
+/// Playlist files in `~/.tunebox/playlists`, by name
+pub fn saved_playlists() -> Vec<PathBuf> {
+    let Some(entries) = playlists_dir().and_then(|d| std::fs::read_dir(d).ok()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && PlaylistFormat::from_path(p).is_some())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Playlist".to_string())
+}
+
+// === Paths ===
+
+/// Turn a path as written in a playlist into an absolute one. Relative
+/// paths are relative to the playlist's folder; backslashes from playlists
+/// made on Windows are read as separators.
+fn entry_path(raw: &str, dir: &Path) -> PathBuf {
+    let raw = raw.trim();
+    let path = if Path::new(raw).is_absolute() || dir.join(raw).exists() {
+        PathBuf::from(raw)
+    } else {
+        PathBuf::from(raw.replace('\\', "/"))
+    };
+    normalize(&dir.join(path))
+}
+
+/// Drop `.` and fold `..` without touching the filesystem, so paths match
+/// the library's even through symlinks
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// How `path` is written into a playlist in `dir`
+fn stored_path(path: &Path, dir: &Path) -> PathBuf {
+    path.strip_prefix(dir).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Decode a `file://` URL or a relative URI reference
+fn uri_to_path(uri: &str, dir: &Path) -> Option<PathBuf> {
+    let uri = uri.trim();
+    if let Some(rest) = uri.strip_prefix("file://") {
+        // The host part is empty or "localhost"
+        let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+        return Some(normalize(Path::new(&percent_decode(rest))));
+    }
+    if uri.contains("://") {
+        return None;
+    }
+    Some(normalize(&dir.join(percent_decode(uri))))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn percent_encode(path: &Path) -> String {
+    let mut out = String::new();
+    for &b in path.to_string_lossy().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "%{:02X}", b);
+        }
+    }
+    out
+}
+
+// === M3U ===
+
+fn parse_m3u(text: &str, dir: &Path) -> (Option<String>, Vec<PlaylistEntry>) {
+    let mut name = None;
+    let mut entries = Vec::new();
+    // `#EXTINF` describes the path on the next line
+    let mut info: Option<(Option<f64>, Option<String>, Option<String>)> = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("#EXTINF:") {
+            // `#EXTINF:<seconds> [attributes],<artist> - <title>`
+            let (head, display) = rest.split_once(',').unwrap_or((rest, ""));
+            let duration = head
+                .split_whitespace()
+                .next()
+                .and_then(|d| d.parse::<f64>().ok())
+                .filter(|&d| d > 0.0);
+            let display = display.trim();
+            let (artist, title) = match display.split_once(" - ") {
+                Some((artist, title)) => (Some(artist.trim().to_string()), title.trim()),
+                None => (None, display),
+            };
+            let title = (!title.is_empty()).then(|| title.to_string());
+            info = Some((duration, artist, title));
+        } else if let Some(rest) = line.strip_prefix("#PLAYLIST:") {
+            name = Some(rest.trim().to_string()).filter(|n| !n.is_empty());
+        } else if line.starts_with('#') {
+            continue;
+        } else {
+            let (duration, artist, title) = info.take().unwrap_or_default();
+            let path = if line.contains("://") {
+                // Streams and other URLs never match a library file
+                uri_to_path(line, dir).unwrap_or_else(|| PathBuf::from(line))
+            } else {
+                entry_path(line, dir)
+            };
+            entries.push(PlaylistEntry {
+                path,
+                title,
+                artist,
+                duration,
+            });
+        }
+    }
+    (name, entries)
+}
+
+fn write_m3u(playlist: &Playlist, dir: &Path) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    let _ = writeln!(out, "#PLAYLIST:{}", playlist.name);
+    for entry in &playlist.entries {
+        let display = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+            (None, Some(title)) => Some(title.clone()),
+            _ => None,
+        };
+        if let Some(display) = display {
+            let secs = entry.duration.map_or(-1, |d| d.round() as i64);
+            let _ = writeln!(out, "#EXTINF:{},{}", secs, display);
+        }
+        let _ = writeln!(out, "{}", stored_path(&entry.path, dir).display());
+    }
+    out
+}
+
+// === PLS ===
+
+fn parse_pls(text: &str, dir: &Path) -> (Option<String>, Vec<PlaylistEntry>) {
+    let mut name = None;
+    let mut numbered: BTreeMap<u32, PlaylistEntry> = BTreeMap::new();
+    for line in text.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        if key == "x-playlist-name" {
+            name = Some(value.to_string()).filter(|n| !n.is_empty());
+            continue;
+        }
+        // FileN, TitleN and LengthN describe entry N
+        let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+        let Ok(number) = key[split..].parse::<u32>() else {
+            continue;
+        };
+        let entry = numbered.entry(number).or_insert_with(|| PlaylistEntry {
+            path: PathBuf::new(),
+            title: None,
+            artist: None,
+            duration: None,
+        });
+        match &key[..split] {
+            "file" if value.contains("://") => {
+                entry.path = uri_to_path(value, dir).unwrap_or_else(|| PathBuf::from(value));
+            }
+            "file" => entry.path = entry_path(value, dir),
+            "title" => entry.title = Some(value.to_string()).filter(|t| !t.is_empty()),
+            "length" => entry.duration = value.parse::<f64>().ok().filter(|&d| d > 0.0),
+            _ => {}
+        }
+    }
+    let entries = numbered
+        .into_values()
+        .filter(|e| !e.path.as_os_str().is_empty())
+        .collect();
+    (name, entries)
+}
+
+fn write_pls(playlist: &Playlist, dir: &Path) -> String {
+    let mut out = String::from("[playlist]\n");
+    let _ = writeln!(out, "X-Playlist-Name={}", playlist.name);
+    for (i, entry) in playlist.entries.iter().enumerate() {
+        let n = i + 1;
+        let _ = writeln!(out, "File{}={}", n, stored_path(&entry.path, dir).display());
+        // PLS has no artist field; the bare title is what `resolve` matches
+        // CUE tracks by
+        if let Some(title) = &entry.title {
+            let _ = writeln!(out, "Title{}={}", n, title);
+        }
+        let _ = writeln!(out, "Length{}={}", n, entry.duration.map_or(-1, |d| d.round() as i64));
+    }
+    let _ = writeln!(out, "NumberOfEntries={}", playlist.entries.len());
+    out.push_str("Version=2\n");
+    out
+}
+
+// === XSPF ===
+
+fn parse_xspf(text: &str, dir: &Path) -> Result<(Option<String>, Vec<PlaylistEntry>)> {
+    let Some(list_start) = text.find("<trackList") else {
+        bail!("no <trackList> element");
+    };
+    let name = element(&text[..list_start], "title").map(|t| unescape(&t));
+    let entries = elements(&text[list_start..], "track")
+        .into_iter()
+        .filter_map(|track| {
+            let location = element(track, "location")?;
+            let location = unescape(&location);
+            let path = uri_to_path(&location, dir).unwrap_or_else(|| PathBuf::from(&location));
+            Some(PlaylistEntry {
+                path,
+                title: element(track, "title").map(|t| unescape(&t)),
+                artist: element(track, "creator").map(|t| unescape(&t)),
+                duration: element(track, "duration")
+                    .and_then(|d| d.trim().parse::<f64>().ok())
+                    .map(|ms| ms / 1000.0),
+            })
+        })
+        .collect();
+    Ok((name, entries))
+}
+
+/// Inner text of every `<tag>` element in `xml`; they must not nest
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // `<track>` but not `<trackList>`
+        if !after.starts_with(['>', ' ', '\t', '\r', '\n']) {
+            rest = after;
+            continue;
+        }
+        let Some(body_start) = after.find('>') else {
+            break;
+        };
+        let body = &after[body_start + 1..];
+        let Some(end) = body.find(&close) else {
+            break;
+        };
+        found.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    found
+}
+
+fn element(xml: &str, tag: &str) -> Option<String> {
+    elements(xml, tag)
+        .first()
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn unescape(text: &str) -> String {
+    let text = text.trim();
+    if let Some(raw) = text.strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")) {
+        return raw.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                code => {
+                    let code = code.strip_prefix('#')?;
+                    let value = match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => code.parse().ok()?,
+                    };
+                    char::from_u32(value)?
+                }
+            };
+            Some((c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_xspf(playlist: &Playlist, dir: &Path) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    let _ = writeln!(out, "  <title>{}</title>", escape(&playlist.name));
+    out.push_str("  <trackList>\n");
+    for entry in &playlist.entries {
+        let stored = stored_path(&entry.path, dir);
+        let location = if stored.to_string_lossy().contains("://") {
+            // Stream URLs are already URIs; only file paths need encoding
+            stored.to_string_lossy().into_owned()
+        } else if stored.is_absolute() {
+            format!("file://{}", percent_encode(&stored))
+        } else {
+            percent_encode(&stored)
+        };
+        out.push_str("    <track>\n");
+        let _ = writeln!(out, "      <location>{}</location>", escape(&location));
+        if let Some(title) = &entry.title {
+            let _ = writeln!(out, "      <title>{}</title>", escape(title));
+        }
+        if let Some(artist) = &entry.artist {
+            let _ = writeln!(out, "      <creator>{}</creator>", escape(artist));
+        }
+        if let Some(duration) = entry.duration {
+            let _ = writeln!(out, "      <duration>{}</duration>", (duration * 1000.0).round() as u64);
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cue::CueRange;
+
+    fn entry(
+        path: &str,
+        title: Option<&str>,
+        artist: Option<&str>,
+        duration: Option<f64>,
+    ) -> PlaylistEntry {
+        PlaylistEntry {
+            path: PathBuf::from(path),
+            title: title.map(str::to_string),
+            artist: artist.map(str::to_string),
+            duration,
+        }
+    }
+
+    fn sample(format: &str) -> Playlist {
+        Playlist {
+            path: PathBuf::from(format!("/music/lists/mix.{}", format)),
+            name: "Mix & Match".to_string(),
+            entries: vec![
+                entry(
+                    "/music/lists/local/01 One.flac",
+                    Some("One"),
+                    Some("Band"),
+                    Some(181.0),
+                ),
+                entry(
+                    "/music/Café Song.mp3",
+                    Some("Café Song"),
+                    Some("Guest"),
+                    None,
+                ),
+                entry("http://radio.example/stream?x=1", Some("Radio"), None, None),
+            ],
+        }
+    }
+
+    #[test]
+    fn m3u_round_trip() {
+        let list = sample("m3u8");
+        let dir = Path::new("/music/lists");
+        let text = write_m3u(&list, dir);
+        // Files below the playlist's folder are stored relative to it
+        assert!(text.contains("\nlocal/01 One.flac\n"));
+        assert!(text.contains("\n/music/Café Song.mp3\n"));
+        let (name, entries) = parse_m3u(&text, dir);
+        assert_eq!(name.as_deref(), Some("Mix & Match"));
+        assert_eq!(entries, list.entries);
+    }
+
+    #[test]
+    fn pls_round_trip_keeps_bare_titles() {
+        let list = sample("pls");
+        let dir = Path::new("/music/lists");
+        let (name, entries) = parse_pls(&write_pls(&list, dir), dir);
+        assert_eq!(name.as_deref(), Some("Mix & Match"));
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+        let expected: Vec<&Path> = list.entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, expected);
+        assert_eq!(entries[0].title.as_deref(), Some("One"));
+        assert_eq!(entries[0].artist, None);
+        assert_eq!(entries[0].duration, Some(181.0));
+        assert_eq!(entries[1].duration, None);
+    }
+
+    #[test]
+    fn xspf_round_trip() {
+        let list = sample("xspf");
+        let dir = Path::new("/music/lists");
+        let text = write_xspf(&list, dir);
+        assert!(text.contains("<location>local/01%20One.flac</location>"));
+        assert!(text.contains("<location>file:///music/Caf%C3%A9%20Song.mp3</location>"));
+        assert!(text.contains("<location>http://radio.example/stream?x=1</location>"));
+        assert!(text.contains("<title>Mix &amp; Match</title>"));
+        let (name, entries) = parse_xspf(&text, dir).unwrap();
+        assert_eq!(name.as_deref(), Some("Mix & Match"));
+        assert_eq!(entries, list.entries);
+    }
+
+    #[test]
+    fn xspf_file_urls_are_decoded() {
+        let text = "<playlist><trackList>\
+                    <track><location>file:///music/Caf%C3%A9%20%2350.flac</location>\
+                    <duration>1500</duration></track>\
+                    <track><location>file://localhost/music/a%2Fb.flac</location></track>\
+                    <track><location>../up/c.flac</location><title>A &amp; B &#233;</title></track>\
+                    <track><title>no location</title></track>\
+                    </trackList></playlist>";
+        let (_, entries) = parse_xspf(text, Path::new("/music/lists")).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, Path::new("/music/Café #50.flac"));
+        assert_eq!(entries[0].duration, Some(1.5));
+        assert_eq!(entries[1].path, Path::new("/music/a/b.flac"));
+        assert_eq!(entries[2].path, Path::new("/music/up/c.flac"));
+        assert_eq!(entries[2].title.as_deref(), Some("A & B é"));
+        assert!(parse_xspf("<playlist/>", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_the_playlist() {
+        let dir = Path::new("/music/lists");
+        let text = "#EXTM3U\n#EXTINF:-1,Only Title\n./a.mp3\n../b/c.mp3\nsub\\d.mp3\n# comment\n";
+        let (name, entries) = parse_m3u(text, dir);
+        assert_eq!(name, None);
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("/music/lists/a.mp3"),
+                Path::new("/music/b/c.mp3"),
+                Path::new("/music/lists/sub/d.mp3")
+            ]
+        );
+        assert_eq!(entries[0].title.as_deref(), Some("Only Title"));
+        assert_eq!(entries[0].artist, None);
+        assert_eq!(entries[0].duration, None);
+        // The description belongs to the next path only
+        assert_eq!(entries[1].title, None);
+    }
+
+    #[test]
+    fn pls_entries_are_numbered() {
+        let text =
+            "[playlist]\nFile2=/b.mp3\nTitle1=First\nFile1=/a.mp3\nLength1=10\nTitle3=No file\n";
+        let (_, entries) = parse_pls(text, Path::new("/"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, Path::new("/a.mp3"));
+        assert_eq!(entries[0].title.as_deref(), Some("First"));
+        assert_eq!(entries[1].path, Path::new("/b.mp3"));
+    }
+
+    #[test]
+    fn cue_tracks_resolve_by_sheet_and_title() {
+        let cue_track = |number: u32, title: &str| Track {
+            path: PathBuf::from("/music/image.flac"),
+            title: title.to_string(),
+            artist: "Band".to_string(),
+            cue: Some(CueRange {
+                sheet: PathBuf::from("/music/image.cue"),
+                number,
+                start: 0.0,
+                end: None,
+            }),
+            ..Default::default()
+        };
+        let library = vec![cue_track(1, "Intro"), cue_track(2, "Outro")];
+        let saved = PlaylistEntry::from_track(&library[1]);
+        assert_eq!(saved.path, Path::new("/music/image.cue"));
+        let list = Playlist {
+            path: PathBuf::from("/music/x.m3u"),
+            name: "x".to_string(),
+            entries: vec![
+                saved,
+                entry("/music/image.flac", Some("Band - Intro"), None, None),
+                entry("/music/image.cue", Some("Missing"), None, None),
+                entry("/music/gone.flac", None, None, None),
+            ],
+        };
+        assert_eq!(list.resolve(&library), [Some(1), Some(0), Some(0), None]);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::history::{self, History};
+use crate::library::Track;
+
+/// How often and when a track has played
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlayCount {
+    pub count: u32,
+    /// Seconds since the epoch
+    pub last_played: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlayCount {
+    path: PathBuf,
+    count: u32,
+    last_played: u64,
+}
+
+/// Play counts by `Track::key`, in `~/.tunebox/plays.json`. Unlike the
+/// history, which keeps the last `history_size` plays for stepping back,
+/// this never forgets a track.
+#[derive(Debug, Default)]
+pub struct PlayCounts {
+    by_key: HashMap<PathBuf, PlayCount>,
+}
+
+impl PlayCounts {
+    /// Restore the saved counts. The first time, before there are any,
+    /// start from the plays in the history.
+    pub fn load(library: &[Track], history: &History) -> Self {
+        let Some(entries) = read_saved() else {
+            let mut counts = Self::default();
+            for play in history.recent().collect::<Vec<_>>().into_iter().rev() {
+                let entry = counts.by_key.entry(library[play.track].key()).or_default();
+                entry.count += 1;
+                entry.last_played = play.played_at;
+            }
+            return counts;
+        };
+        Self {
+            by_key: entries
+                .into_iter()
+                .map(|e| {
+                    let count = PlayCount {
+                        count: e.count,
+                        last_played: e.last_played,
+                    };
+                    (e.path, count)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn save(&self) {
+        let mut entries: Vec<SavedPlayCount> = self
+            .by_key
+            .iter()
+            .map(|(path, c)| SavedPlayCount {
+                path: path.clone(),
+                count: c.count,
+                last_played: c.last_played,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        write_saved(&entries);
+    }
+
+    pub fn get(&self, track: &Track) -> Option<PlayCount> {
+        self.by_key.get(&track.key()).copied()
+    }
+
+    /// Count a play of `track` starting now
+    pub fn record(&mut self, track: &Track) {
+        let entry = self.by_key.entry(track.key()).or_default();
+        entry.count += 1;
+        entry.last_played = history::now();
+    }
+
+    /// Follow files moved by organize
+    pub fn rename(&mut self, moves: &[(PathBuf, PathBuf)]) {
+        for (from, to) in moves {
+            if let Some(count) = self.by_key.remove(from) {
+                self.by_key.insert(to.clone(), count);
+            }
+        }
+    }
+}
+
+/// Point saved counts at the new locations of moved files
+pub fn rename_paths(moves: &[(PathBuf, PathBuf)]) {
+    let Some(mut saved) = read_saved() else {
+        return;
+    };
+    let moves: HashMap<&PathBuf, &PathBuf> = moves.iter().map(|(from, to)| (from, to)).collect();
+    for entry in &mut saved {
+        if let Some(&to) = moves.get(&entry.path) {
+            entry.path = to.clone();
+        }
+    }
+    write_saved(&saved);
+}
+
+fn plays_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("plays.json"))
+}
+
+fn read_saved() -> Option<Vec<SavedPlayCount>> {
+    let data = std::fs::read_to_string(plays_path()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_saved(saved: &[SavedPlayCount]) {
+    let Some(path) = plays_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(saved) {
+        let _ = std::fs::write(path, json);
+    }
+}
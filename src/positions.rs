@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// Bytes read from each end of a file for its content hash
+const HASH_SPAN: u64 = 64 * 1024;
+
+/// Stopping this close to the end counts as finishing the file
+pub const FINISH_MARGIN_SECS: f64 = 30.0;
+
+/// Where a long file was left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub path: PathBuf,
+    /// Size plus head and tail bytes, so the entry can be found again after
+    /// the file is renamed or moved
+    pub hash: u64,
+    /// Seconds in; 0 once finished
+    pub position: f64,
+    /// Played through to the end at least once
+    pub finished: bool,
+}
+
+/// Saved positions for audiobooks, podcasts and other long files, in
+/// `~/.tunebox/positions.json`
+#[derive(Debug, Default)]
+pub struct Positions {
+    by_path: HashMap<PathBuf, Position>,
+}
+
+impl Positions {
+    pub fn load() -> Self {
+        let entries: Vec<Position> = positions_path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            by_path: entries.into_iter().map(|e| (e.path.clone(), e)).collect(),
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = positions_path() else {
+            return;
+        };
+        let mut entries: Vec<&Position> = self.by_path.values().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&entries) {
+            let _ = std::fs::write(path, json);
+        }
+    }
+
+    /// The saved position for `path`. A file not known by its path is matched
+    /// by content against entries whose file has gone, and takes them over.
+    pub fn get(&mut self, path: &Path) -> Option<&Position> {
+        if !self.by_path.contains_key(path) {
+            let hash = content_hash(path)?;
+            let old = self
+                .by_path
+                .iter()
+                .find(|(p, e)| e.hash == hash && !p.exists())
+                .map(|(p, _)| p.clone())?;
+            let mut entry = self.by_path.remove(&old)?;
+            entry.path = path.to_path_buf();
+            self.by_path.insert(path.to_path_buf(), entry);
+        }
+        self.by_path.get(path)
+    }
+
+    /// Remember stopping at `position` of a file lasting `duration` seconds
+    pub fn record(&mut self, path: &Path, position: f64, duration: f64) {
+        let finished = duration > 0.0 && position >= duration - FINISH_MARGIN_SECS;
+        if let Some(entry) = self.by_path.get_mut(path) {
+            // Tag edits change the bytes; keep the hash current for renames
+            if let Some(hash) = content_hash(path) {
+                entry.hash = hash;
+            }
+            if finished {
+                entry.position = 0.0;
+                entry.finished = true;
+            } else {
+                entry.position = position;
+            }
+            return;
+        }
+        let Some(hash) = content_hash(path) else {
+            return;
+        };
+        self.by_path.insert(
+            path.to_path_buf(),
+            Position {
+                path: path.to_path_buf(),
+                hash,
+                position: if finished { 0.0 } else { position },
+                finished,
+            },
+        );
+    }
+
+    /// Whether `path` has been played to the end
+    pub fn is_finished(&self, path: &Path) -> bool {
+        self.by_path.get(path).is_some_and(|e| e.finished)
+    }
+}
+
+/// FNV-1a over the file size and its first and last 64 KiB. Stable across
+/// builds, unlike `DefaultHasher`, since it is saved to disk.
+fn content_hash(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for &b in bytes {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(&size.to_le_bytes());
+
+    let mut buf = Vec::with_capacity(HASH_SPAN as usize);
+    file.by_ref().take(HASH_SPAN).read_to_end(&mut buf).ok()?;
+    feed(&buf);
+    if size > HASH_SPAN * 2 {
+        buf.clear();
+        file.seek(SeekFrom::End(-(HASH_SPAN as i64))).ok()?;
+        file.read_to_end(&mut buf).ok()?;
+        feed(&buf);
+    }
+    Some(hash)
+}
+
+fn positions_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("positions.json"))
+}
@@ -0,0 +1,904 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use anyhow::{bail, Context, Result};
+use rand::seq::SliceRandom;
+
+use crate::history;
+use crate::library::Track;
+use crate::plays::PlayCounts;
+use crate::ratings::Ratings;
+
+const DAY_SECS: f64 = 86400.0;
+
+/// Field names accepted before a `:`, for error messages
+const FIELD_NAMES: &str = "title, artist, album, albumartist, genre, composer, comment, format, \
+                           path, year, duration, bitrate, samplerate, track, disc, rating, plays, \
+                           lastplayed";
+
+/// Play counts and ratings that rules can test, as of one evaluation
+pub struct Stats<'a> {
+    plays: &'a PlayCounts,
+    ratings: &'a Ratings,
+    now: u64,
+}
+
+impl<'a> Stats<'a> {
+    pub fn new(plays: &'a PlayCounts, ratings: &'a Ratings) -> Self {
+        Self {
+            plays,
+            ratings,
+            now: history::now(),
+        }
+    }
+
+    pub fn plays(&self, track: &Track) -> u32 {
+        self.plays.get(track).map_or(0, |p| p.count)
+    }
+
+    pub fn rating(&self, track: &Track) -> Option<u8> {
+        self.ratings.get(track)
+    }
+
+    /// Seconds since `track` was last played
+    pub fn since_played(&self, track: &Track) -> Option<u64> {
+        self.plays
+            .get(track)
+            .map(|p| self.now.saturating_sub(p.last_played))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    Composer,
+    Comment,
+    Format,
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberField {
+    Year,
+    /// Seconds
+    Duration,
+    /// kbps
+    Bitrate,
+    SampleRate,
+    TrackNumber,
+    Disc,
+    /// Stars, 0 when unrated
+    Rating,
+    /// Times played, from the play counts
+    Plays,
+    /// Seconds since the last play; never played is infinitely long ago
+    LastPlayed,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Text(TextField),
+    Number(NumberField),
+}
+
+fn field(name: &str) -> Option<Field> {
+    use NumberField as N;
+    use TextField as T;
+    Some(match name {
+        "title" => Field::Text(T::Title),
+        "artist" => Field::Text(T::Artist),
+        "album" => Field::Text(T::Album),
+        "albumartist" | "album_artist" => Field::Text(T::AlbumArtist),
+        "genre" => Field::Text(T::Genre),
+        "composer" => Field::Text(T::Composer),
+        "comment" => Field::Text(T::Comment),
+        "format" => Field::Text(T::Format),
+        "path" => Field::Text(T::Path),
+        "year" => Field::Number(N::Year),
+        "duration" => Field::Number(N::Duration),
+        "bitrate" => Field::Number(N::Bitrate),
+        "samplerate" | "sample_rate" => Field::Number(N::SampleRate),
+        "track" => Field::Number(N::TrackNumber),
+        "disc" => Field::Number(N::Disc),
+        "rating" => Field::Number(N::Rating),
+        "plays" => Field::Number(N::Plays),
+        "lastplayed" | "last_played" => Field::Number(N::LastPlayed),
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Compare {
+    Eq(f64),
+    Lt(f64),
+    Le(f64),
+    Gt(f64),
+    Ge(f64),
+    /// Inclusive at both ends
+    Between(f64, f64),
+}
+
+impl Compare {
+    fn test(self, value: f64) -> bool {
+        match self {
+            Self::Eq(x) => value == x,
+            Self::Lt(x) => value < x,
+            Self::Le(x) => value <= x,
+            Self::Gt(x) => value > x,
+            Self::Ge(x) => value >= x,
+            Self::Between(lo, hi) => (lo..=hi).contains(&value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    /// A bare word or "quoted phrase", looked for in the `ANY_FIELDS`
+    Any(Pattern),
+    Text(TextField, Pattern),
+    Number(NumberField, Compare),
+}
+
+/// Text to look for, lowercased. Words match with a typo or two; quoted
+/// phrases only exactly.
+#[derive(Debug, Clone)]
+struct Pattern {
+    text: Vec<char>,
+    exact: bool,
+}
+
+impl Pattern {
+    fn new(text: &str, exact: bool) -> Self {
+        Self {
+            text: lower_chars(text),
+            exact,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    negated: bool,
+    test: Test,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SortKey {
+    Random,
+    Text(TextField, bool),
+    Number(NumberField, bool),
+}
+
+/// Fields a bare word is looked for in, with what a match in each is worth
+const ANY_FIELDS: [(TextField, u32); 6] = [
+    (TextField::Title, 10),
+    (TextField::Artist, 8),
+    (TextField::Album, 6),
+    (TextField::AlbumArtist, 4),
+    (TextField::Genre, 2),
+    (TextField::Composer, 2),
+];
+
+/// A search or smart playlist query, e.g. `artist:"miles davis" year:<1970`
+/// or `genre:jazz rating:>=4 not played in 90 days sort:random limit:50`.
+/// Rules separated by spaces must all match, `OR` (or `|`) separates
+/// alternatives, and `not` or a leading `-` negates a rule.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Alternatives, each a list of rules that must all match
+    groups: Vec<Vec<Rule>>,
+    sort: Vec<SortKey>,
+    limit: Option<usize>,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self> {
+        let words = split_words(text);
+        let mut query = Self::default();
+        let mut group = Vec::new();
+        let mut negated = false;
+        let mut i = 0;
+        while i < words.len() {
+            let word = &words[i];
+            i += 1;
+            if word.is("not") {
+                negated = !negated;
+                continue;
+            }
+            if !word.quoted && (word.text == "OR" || word.text == "|") {
+                if !group.is_empty() {
+                    query.groups.push(std::mem::take(&mut group));
+                }
+                negated = false;
+                continue;
+            }
+            negated ^= word.negated;
+            let test = if word.is("played") && words.get(i).is_some_and(|w| w.is("in")) {
+                // "played in 90 days"
+                let amount: f64 = words
+                    .get(i + 1)
+                    .and_then(|n| n.text.parse().ok())
+                    .context("Expected a number after 'played in'")?;
+                let unit = words.get(i + 2).map(|u| u.text.to_lowercase()).unwrap_or_default();
+                let secs = unit_seconds(&unit)
+                    .with_context(|| format!("Unknown time unit '{}' after 'played in'", unit))?;
+                i += 3;
+                Test::Number(NumberField::LastPlayed, Compare::Le(amount * secs))
+            } else if let (Ok(count), false, Some(true)) = (
+                word.text.parse::<usize>(),
+                word.quoted,
+                words.get(i).map(|w| w.is("random")),
+            ) {
+                // "50 random tracks"
+                query.limit = Some(count);
+                query.sort.push(SortKey::Random);
+                i += 1;
+                if words.get(i).is_some_and(|w| w.is("tracks") || w.is("songs")) {
+                    i += 1;
+                }
+                negated = false;
+                continue;
+            } else if let Some((name, value)) = word.text.split_once(':').filter(|_| !word.phrase) {
+                let name = name.to_lowercase();
+                match name.as_str() {
+                    "limit" => {
+                        query.limit = Some(
+                            value
+                                .parse()
+                                .with_context(|| format!("Bad limit '{}'", value))?,
+                        );
+                        negated = false;
+                        continue;
+                    }
+                    "sort" => {
+                        for key in value.split(',').filter(|k| !k.is_empty()) {
+                            query.sort.push(parse_sort(key)?);
+                        }
+                        negated = false;
+                        continue;
+                    }
+                    _ => {}
+                }
+                match field(&name) {
+                    // Formats are codes, where one letter off is another format
+                    Some(Field::Text(f)) => {
+                        Test::Text(f, Pattern::new(value, word.quoted || f == TextField::Format))
+                    }
+                    Some(Field::Number(f)) => Test::Number(
+                        f,
+                        parse_compare(f, value)
+                            .with_context(|| format!("Can't compare {} with '{}'", name, value))?,
+                    ),
+                    None => bail!("Unknown field '{}'. Fields: {}", name, FIELD_NAMES),
+                }
+            } else {
+                Test::Any(Pattern::new(&word.text, word.quoted))
+            };
+            group.push(Rule { negated, test });
+            negated = false;
+        }
+        if !group.is_empty() {
+            query.groups.push(group);
+        }
+        Ok(query)
+    }
+
+    /// Whether the query has words to rank matches by
+    fn has_patterns(&self) -> bool {
+        self.groups
+            .iter()
+            .flatten()
+            .any(|r| !r.negated && matches!(r.test, Test::Any(_) | Test::Text(..)))
+    }
+
+    /// How well library track `index` matches, or None if it doesn't
+    pub fn score(&self, library: &[Track], index: usize, stats: &Stats) -> Option<u32> {
+        if self.groups.is_empty() {
+            return Some(0);
+        }
+        self.groups
+            .iter()
+            .filter_map(|group| {
+                group
+                    .iter()
+                    .map(|rule| rule_score(rule, &library[index], stats))
+                    .sum::<Option<u32>>()
+            })
+            .max()
+    }
+
+    /// Whether a browser entry matches: words are looked for in its `text`
+    /// (name and detail), other rules in its tracks
+    pub fn matches_entry(&self, text: &str, tracks: &[usize], library: &[Track], stats: &Stats) -> bool {
+        self.groups.is_empty()
+            || self.groups.iter().any(|group| {
+                group.iter().all(|rule| match &rule.test {
+                    Test::Any(pattern) => fuzzy_find(text, pattern).is_some() != rule.negated,
+                    _ => tracks
+                        .iter()
+                        .any(|&i| rule_score(rule, &library[i], stats).is_some()),
+                })
+            })
+    }
+
+    /// The tracks of `indices` that match, sorted and cut to the limit.
+    /// Without sort keys the best matches for the query's words come first;
+    /// `random` among the sort keys shuffles tracks the other keys leave tied.
+    pub fn run(&self, library: &[Track], indices: &[usize], stats: &Stats) -> Vec<usize> {
+        let mut scored: Vec<(usize, u32)> = indices
+            .iter()
+            .filter_map(|&i| Some((i, self.score(library, i, stats)?)))
+            .collect();
+        if self.sort.iter().any(|k| matches!(k, SortKey::Random)) {
+            scored.shuffle(&mut rand::thread_rng());
+        }
+        if !self.sort.is_empty() {
+            scored.sort_by(|&(a, _), &(b, _)| {
+                self.sort.iter().fold(Ordering::Equal, |order, key| {
+                    order.then_with(|| compare(*key, library, a, b, stats))
+                })
+            });
+        } else if self.has_patterns() {
+            scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        }
+        if let Some(limit) = self.limit {
+            scored.truncate(limit);
+        }
+        scored.into_iter().map(|(i, _)| i).collect()
+    }
+
+    /// Whether the query orders tracks itself with `sort:`
+    pub fn has_sort(&self) -> bool {
+        !self.sort.is_empty()
+    }
+
+    /// Character ranges of `text`, the value of `field` (e.g. "title"),
+    /// that the query's words match, for highlighting
+    pub fn highlights(&self, field_name: &str, text: &str) -> Vec<Range<usize>> {
+        let Some(Field::Text(target)) = field(field_name) else {
+            return Vec::new();
+        };
+        self.groups
+            .iter()
+            .flatten()
+            .filter(|rule| !rule.negated)
+            .filter_map(|rule| match &rule.test {
+                Test::Any(pattern) if ANY_FIELDS.iter().any(|&(f, _)| f == target) => {
+                    fuzzy_find(text, pattern)
+                }
+                Test::Text(f, pattern) if *f == target => fuzzy_find(text, pattern),
+                _ => None,
+            })
+            .map(|(_, range)| range)
+            .collect()
+    }
+}
+
+/// What a rule adds to a track's score, or None when it rules the track out
+fn rule_score(rule: &Rule, track: &Track, stats: &Stats) -> Option<u32> {
+    let score = match &rule.test {
+        Test::Any(pattern) => {
+            let in_fields = ANY_FIELDS
+                .iter()
+                .filter_map(|&(f, weight)| Some(fuzzy_find(&text(f, track), pattern)?.0 + weight));
+            // A bare year matches the year tag
+            let year = track
+                .year
+                .filter(|y| lower_chars(&y.to_string()) == pattern.text)
+                .map(|_| 50);
+            in_fields.chain(year).max()
+        }
+        Test::Text(f, pattern) => fuzzy_find(&text(*f, track), pattern).map(|(score, _)| score),
+        Test::Number(f, compare) => number(*f, track, stats)
+            .filter(|&v| compare.test(v))
+            .map(|_| 0),
+    };
+    match (score, rule.negated) {
+        (Some(score), false) => Some(score),
+        (None, true) => Some(0),
+        _ => None,
+    }
+}
+
+fn text(field: TextField, track: &Track) -> Cow<'_, str> {
+    let value = match field {
+        TextField::Title => &track.title,
+        TextField::Artist => &track.artist,
+        TextField::Album => &track.album,
+        TextField::AlbumArtist => track.album_artist_or_artist(),
+        TextField::Genre => track.genre.as_deref().unwrap_or(""),
+        TextField::Composer => track.composer.as_deref().unwrap_or(""),
+        TextField::Comment => track.comment.as_deref().unwrap_or(""),
+        TextField::Format => &track.format,
+        TextField::Path => return track.path.to_string_lossy(),
+    };
+    Cow::Borrowed(value)
+}
+
+fn number(field: NumberField, track: &Track, stats: &Stats) -> Option<f64> {
+    match field {
+        NumberField::Year => track.year.map(f64::from),
+        NumberField::Duration => Some(track.duration),
+        NumberField::Bitrate => track.bitrate.map(f64::from),
+        NumberField::SampleRate => track.sample_rate.map(f64::from),
+        NumberField::TrackNumber => track.track_number.map(f64::from),
+        NumberField::Disc => track.disc_number.map(f64::from),
+        NumberField::Rating => Some(stats.ratings.get(track).unwrap_or(0) as f64),
+        NumberField::Plays => Some(stats.plays(track) as f64),
+        NumberField::LastPlayed => Some(
+            stats
+                .since_played(track)
+                .map_or(f64::INFINITY, |secs| secs as f64),
+        ),
+    }
+}
+
+/// Stable-sort library `indices` by the field called `name`, e.g. for a
+/// clicked column. Tracks missing a number sort last either way.
+pub fn sort_by_field(
+    name: &str,
+    descending: bool,
+    library: &[Track],
+    indices: &mut [usize],
+    stats: &Stats,
+) {
+    let key = match field(name) {
+        Some(Field::Text(f)) => SortKey::Text(f, descending),
+        Some(Field::Number(f)) => SortKey::Number(f, descending),
+        None => return,
+    };
+    indices.sort_by(|&a, &b| compare(key, library, a, b, stats));
+}
+
+/// Order two tracks by one key. Tracks missing a number sort last either way.
+fn compare(key: SortKey, library: &[Track], a: usize, b: usize, stats: &Stats) -> Ordering {
+    let (order, descending) = match key {
+        SortKey::Random => return Ordering::Equal,
+        SortKey::Text(f, descending) => (
+            text(f, &library[a])
+                .to_lowercase()
+                .cmp(&text(f, &library[b]).to_lowercase()),
+            descending,
+        ),
+        SortKey::Number(f, descending) => {
+            match (number(f, &library[a], stats), number(f, &library[b], stats)) {
+                (Some(x), Some(y)) => (x.total_cmp(&y), descending),
+                (Some(_), None) => return Ordering::Less,
+                (None, Some(_)) => return Ordering::Greater,
+                (None, None) => return Ordering::Equal,
+            }
+        }
+    };
+    if descending {
+        order.reverse()
+    } else {
+        order
+    }
+}
+
+/// `year`, `-rating` (descending) or `random`
+fn parse_sort(key: &str) -> Result<SortKey> {
+    let (name, descending) = match key.strip_prefix('-') {
+        Some(name) => (name, true),
+        None => (key, false),
+    };
+    let name = name.to_lowercase();
+    if name == "random" {
+        return Ok(SortKey::Random);
+    }
+    match field(&name) {
+        Some(Field::Text(f)) => Ok(SortKey::Text(f, descending)),
+        Some(Field::Number(f)) => Ok(SortKey::Number(f, descending)),
+        None => bail!("Can't sort by '{}'. Fields: random, {}", name, FIELD_NAMES),
+    }
+}
+
+/// `4`, `>=4`, `<1970`, `1990..1999`
+fn parse_compare(field: NumberField, value: &str) -> Option<Compare> {
+    if let Some((lo, hi)) = value.split_once("..") {
+        return Some(Compare::Between(amount(field, lo)?, amount(field, hi)?));
+    }
+    let (make, rest): (fn(f64) -> Compare, &str) = if let Some(rest) = value.strip_prefix(">=") {
+        (Compare::Ge, rest)
+    } else if let Some(rest) = value.strip_prefix("<=") {
+        (Compare::Le, rest)
+    } else if let Some(rest) = value.strip_prefix('>') {
+        (Compare::Gt, rest)
+    } else if let Some(rest) = value.strip_prefix('<') {
+        (Compare::Lt, rest)
+    } else {
+        (Compare::Eq, value.strip_prefix('=').unwrap_or(value))
+    };
+    Some(make(amount(field, rest)?))
+}
+
+/// A value in the field's unit. Durations take `90s`, `10m`, `1h` or
+/// `3:30`; ages take `12h`, `90d`, `2w` or `1y` and default to days;
+/// sample rates take `44.1k`.
+fn amount(field: NumberField, text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let unit = unit.to_lowercase();
+    match field {
+        NumberField::Duration if number.contains(':') => number
+            .split(':')
+            .try_fold(0.0, |total, part| Some(total * 60.0 + part.parse::<f64>().ok()?)),
+        NumberField::Duration => {
+            let scale = match unit.as_str() {
+                "" | "s" => 1.0,
+                "m" | "min" => 60.0,
+                "h" => 3600.0,
+                _ => return None,
+            };
+            Some(number.parse::<f64>().ok()? * scale)
+        }
+        NumberField::LastPlayed => {
+            let scale = if unit.is_empty() { DAY_SECS } else { unit_seconds(&unit)? };
+            Some(number.parse::<f64>().ok()? * scale)
+        }
+        NumberField::SampleRate => {
+            let scale = match unit.as_str() {
+                "" => 1.0,
+                "k" | "khz" => 1000.0,
+                _ => return None,
+            };
+            Some(number.parse::<f64>().ok()? * scale)
+        }
+        _ if unit.is_empty() => number.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Seconds in a unit of time since a play
+fn unit_seconds(unit: &str) -> Option<f64> {
+    Some(match unit {
+        "h" | "hour" | "hours" => 3600.0,
+        "d" | "day" | "days" => DAY_SECS,
+        "w" | "week" | "weeks" => 7.0 * DAY_SECS,
+        "month" | "months" => 30.0 * DAY_SECS,
+        "y" | "year" | "years" => 365.0 * DAY_SECS,
+        _ => return None,
+    })
+}
+
+/// One word of a query
+#[derive(Debug, Default)]
+struct Word {
+    text: String,
+    /// Some or all of it was in quotes
+    quoted: bool,
+    /// It began with a quote, so it's a phrase even if it has a `:`
+    phrase: bool,
+    /// Written with a leading `-`
+    negated: bool,
+}
+
+impl Word {
+    /// Whether this is the unquoted keyword `keyword`
+    fn is(&self, keyword: &str) -> bool {
+        !self.quoted && !self.negated && self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+/// Split on whitespace, keeping "quoted strings" together. Quotes may start
+/// inside a word, as in `artist:"miles davis"`.
+fn split_words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut word: Option<Word> = None;
+    let mut in_quotes = false;
+    for c in text.chars() {
+        if c.is_whitespace() && !in_quotes {
+            words.extend(word.take());
+            continue;
+        }
+        let w = word.get_or_insert_with(Word::default);
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                w.phrase |= w.text.is_empty();
+                w.quoted = true;
+            }
+            '-' if w.text.is_empty() && !w.quoted && !w.negated => w.negated = true,
+            c => w.text.push(c),
+        }
+    }
+    words.extend(word);
+    words.retain(|w| !w.text.is_empty());
+    words
+}
+
+fn lower_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Typos forgiven in a word this many characters long
+fn typo_allowance(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// How well `pattern` matches `text`, and the characters of `text` it
+/// covers. A substring scores best, more so at the start of a word or as
+/// the whole text. Failing that, an unquoted word matches the start of a
+/// word in `text` that is a typo or two away, so partly typed words count.
+fn fuzzy_find(text: &str, pattern: &Pattern) -> Option<(u32, Range<usize>)> {
+    let p = &pattern.text;
+    let t = lower_chars(text);
+    if p.is_empty() {
+        return Some((0, 0..0));
+    }
+    let word_start = |i: usize| i == 0 || !t[i - 1].is_alphanumeric();
+    if p.len() <= t.len() {
+        let found: Vec<usize> = (0..=t.len() - p.len())
+            .filter(|&i| t[i..i + p.len()] == p[..])
+            .collect();
+        let best = found.iter().copied().find(|&i| word_start(i)).or(found.first().copied());
+        if let Some(start) = best {
+            let mut score = 100;
+            if word_start(start) {
+                score += 20;
+            }
+            if p.len() == t.len() {
+                score += 30;
+            }
+            return Some((score, start..start + p.len()));
+        }
+    }
+    if pattern.exact {
+        return None;
+    }
+    let allowed = typo_allowance(p.len());
+    if allowed == 0 {
+        return None;
+    }
+    let mut best: Option<(u32, Range<usize>)> = None;
+    let mut start = 0;
+    while start < t.len() {
+        if !t[start].is_alphanumeric() {
+            start += 1;
+            continue;
+        }
+        let end = (start..t.len())
+            .find(|&i| !t[i].is_alphanumeric())
+            .unwrap_or(t.len());
+        let word = &t[start..end];
+        for len in p.len().saturating_sub(allowed).max(1)..=(p.len() + allowed).min(word.len()) {
+            let distance = edit_distance(p, &word[..len]);
+            if distance <= allowed {
+                let mut score = 60 - 15 * distance as u32;
+                if len == word.len() {
+                    score += 5;
+                }
+                if best.as_ref().is_none_or(|(s, _)| score > *s) {
+                    best = Some((score, start..start + len));
+                }
+            }
+        }
+        start = end;
+    }
+    best
+}
+
+/// Edits (insert, delete, substitute, swap neighbours) turning `a` into `b`
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, artist: &str, year: Option<u32>) -> Track {
+        Track {
+            path: format!("/music/{} - {}.flac", artist, title).into(),
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: "Album".to_string(),
+            year,
+            duration: 200.0,
+            format: "FLAC".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn library() -> Vec<Track> {
+        vec![
+            track("So What", "Miles Davis", Some(1959)),
+            track("Teen Spirit", "Nirvana", Some(1991)),
+            track("Loser", "Beck", Some(1993)),
+            track("Windowlicker", "Aphex Twin", Some(1999)),
+            track("Untitled", "Davis Miles", None),
+        ]
+    }
+
+    /// Titles of the tracks `query` finds, in the order it returns them
+    fn titles(query: &str, library: &[Track], plays: &PlayCounts) -> Vec<String> {
+        let ratings = Ratings::default();
+        let all: Vec<usize> = (0..library.len()).collect();
+        Query::parse(query)
+            .unwrap()
+            .run(library, &all, &Stats::new(plays, &ratings))
+            .into_iter()
+            .map(|i| library[i].title.clone())
+            .collect()
+    }
+
+    fn find(query: &str) -> Vec<String> {
+        titles(query, &library(), &PlayCounts::default())
+    }
+
+    #[test]
+    fn year_range_is_inclusive() {
+        assert_eq!(
+            find("year:1991..1999"),
+            ["Teen Spirit", "Loser", "Windowlicker"]
+        );
+        assert_eq!(find("year:<1991"), ["So What"]);
+        assert_eq!(find("year:>=1993"), ["Loser", "Windowlicker"]);
+        assert_eq!(find("year:1993"), ["Loser"]);
+    }
+
+    #[test]
+    fn missing_numbers_never_match() {
+        assert!(!find("year:<3000").contains(&"Untitled".to_string()));
+    }
+
+    #[test]
+    fn quoted_value_is_one_exact_phrase() {
+        assert_eq!(find("artist:\"miles davis\""), ["So What"]);
+        // Unquoted, "davis" is a word of its own and matches both
+        assert_eq!(find("artist:miles davis").len(), 2);
+        // Quoted phrases don't forgive typos
+        assert!(find("artist:\"mils davis\"").is_empty());
+    }
+
+    #[test]
+    fn words_forgive_typos_by_length() {
+        assert_eq!(find("nirvanna"), ["Teen Spirit"]);
+        assert_eq!(find("windowlickr"), ["Windowlicker"]);
+        // Three letters or fewer have to be exact
+        assert!(find("bek").is_empty());
+    }
+
+    #[test]
+    fn bare_year_matches_year_tag() {
+        assert_eq!(find("1959"), ["So What"]);
+    }
+
+    #[test]
+    fn or_and_negation() {
+        assert_eq!(find("nirvana OR beck"), ["Teen Spirit", "Loser"]);
+        assert_eq!(find("nirvana | beck"), ["Teen Spirit", "Loser"]);
+        assert_eq!(
+            find("year:1990..1999 -artist:beck"),
+            ["Teen Spirit", "Windowlicker"]
+        );
+        assert_eq!(
+            find("year:1990..1999 not artist:beck"),
+            ["Teen Spirit", "Windowlicker"]
+        );
+        // "not" applies to one rule only
+        assert_eq!(find("not artist:beck year:1993"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn not_played_in_days() {
+        let library = library();
+        let mut plays = PlayCounts::default();
+        plays.record(&library[1]);
+        assert_eq!(
+            titles("played in 90 days", &library, &plays),
+            ["Teen Spirit"]
+        );
+        let never = titles("not played in 90 days", &library, &plays);
+        assert_eq!(never.len(), 4);
+        assert!(!never.contains(&"Teen Spirit".to_string()));
+        assert_eq!(titles("plays:>0", &library, &plays), ["Teen Spirit"]);
+        // Never played counts as longer ago than any age
+        assert_eq!(titles("lastplayed:>10y", &library, &plays).len(), 4);
+    }
+
+    #[test]
+    fn sort_and_limit() {
+        assert_eq!(
+            find("sort:-year"),
+            [
+                "Windowlicker",
+                "Loser",
+                "Teen Spirit",
+                "So What",
+                "Untitled"
+            ]
+        );
+        // Tracks without a year stay last when ascending too
+        assert_eq!(find("sort:year limit:2"), ["So What", "Teen Spirit"]);
+        assert_eq!(find("3 random tracks").len(), 3);
+        assert!(Query::parse("sort:random").unwrap().has_sort());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Query::parse("colour:red").is_err());
+        assert!(Query::parse("limit:lots").is_err());
+        assert!(Query::parse("year:soon").is_err());
+        assert!(Query::parse("played in 3 fortnights").is_err());
+        assert!(Query::parse("played in many days").is_err());
+        assert!(Query::parse("sort:colour").is_err());
+    }
+
+    #[test]
+    fn amounts_take_units() {
+        let secs = |text| amount(NumberField::Duration, text);
+        assert_eq!(secs("3:30"), Some(210.0));
+        assert_eq!(secs("1:00:00"), Some(3600.0));
+        assert_eq!(secs("90s"), Some(90.0));
+        assert_eq!(secs("10m"), Some(600.0));
+        assert_eq!(secs("2x"), None);
+        assert_eq!(amount(NumberField::LastPlayed, "2"), Some(2.0 * DAY_SECS));
+        assert_eq!(amount(NumberField::LastPlayed, "2w"), Some(14.0 * DAY_SECS));
+        assert_eq!(amount(NumberField::SampleRate, "44.1k"), Some(44100.0));
+        assert_eq!(amount(NumberField::Year, "1990s"), None);
+    }
+
+    #[test]
+    fn split_words_keeps_quotes_together() {
+        let words = split_words("artist:\"a b\" -\"c d\"  e");
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, ["artist:a b", "c d", "e"]);
+        assert!(words[0].quoted && !words[0].phrase);
+        assert!(words[1].phrase && words[1].negated);
+        assert!(!words[2].quoted);
+    }
+
+    #[test]
+    fn phrase_with_colon_is_not_a_field() {
+        let library = vec![track("Re: Stacks", "Bon Iver", None)];
+        assert_eq!(
+            titles("\"re: stacks\"", &library, &PlayCounts::default()),
+            ["Re: Stacks"]
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_once() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("beatles"), &chars("beatels")), 1);
+        assert_eq!(edit_distance(&chars("abc"), &chars("")), 3);
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+    }
+
+    #[test]
+    fn highlights_cover_the_match() {
+        let query = Query::parse("spirit artist:nirvana").unwrap();
+        assert_eq!(query.highlights("title", "Teen Spirit"), vec![5..11]);
+        assert_eq!(query.highlights("artist", "Nirvana"), vec![0..7]);
+        assert!(query.highlights("year", "1991").is_empty());
+    }
+}
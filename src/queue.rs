@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::library::Track;
+
+/// Tracks to play before continuing in library (or shuffle) order, as
+/// library indices
+#[derive(Debug, Clone, Default)]
+pub struct Queue {
+    tracks: Vec<usize>,
+    /// Saved entries not in this library (another folder, or a single file
+    /// was opened), written back untouched so they're there next time
+    unresolved: Vec<PathBuf>,
+}
+
+/// On-disk form. Paths rather than indices, since indices change with every
+/// rescan.
+#[derive(Serialize, Deserialize)]
+struct SavedQueue {
+    tracks: Vec<PathBuf>,
+}
+
+impl Queue {
+    pub fn tracks(&self) -> &[usize] {
+        &self.tracks
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Replace the whole queue
+    pub fn set(&mut self, tracks: Vec<usize>) {
+        self.tracks = tracks;
+    }
+
+    /// Add to the end
+    pub fn add(&mut self, tracks: &[usize]) {
+        self.tracks.extend_from_slice(tracks);
+    }
+
+    /// Insert at the front, keeping `tracks` in order
+    pub fn play_next(&mut self, tracks: &[usize]) {
+        self.tracks.splice(0..0, tracks.iter().copied());
+    }
+
+    /// Take the next track to play
+    pub fn pop(&mut self) -> Option<usize> {
+        (!self.tracks.is_empty()).then(|| self.tracks.remove(0))
+    }
+
+    pub fn remove(&mut self, position: usize) -> Option<usize> {
+        (position < self.tracks.len()).then(|| self.tracks.remove(position))
+    }
+
+    /// Move the entry at `from` to `to`, returning whether anything moved
+    pub fn move_to(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.tracks.len() || to >= self.tracks.len() || from == to {
+            return false;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Restore the queue saved by the last session. Tracks not in this
+    /// library are kept aside rather than dropped.
+    pub fn load(library: &[Track]) -> Self {
+        let Some(saved) = read_saved() else {
+            return Self::default();
+        };
+        let by_key: HashMap<PathBuf, usize> =
+            library.iter().enumerate().map(|(i, t)| (t.key(), i)).collect();
+        let mut queue = Self::default();
+        for path in saved.tracks {
+            match by_key.get(&path) {
+                Some(&i) => queue.tracks.push(i),
+                None => queue.unresolved.push(path),
+            }
+        }
+        queue
+    }
+
+    pub fn save(&self, library: &[Track]) {
+        let tracks = self
+            .tracks
+            .iter()
+            .map(|&i| library[i].key())
+            .chain(self.unresolved.iter().cloned())
+            .collect();
+        write_saved(&SavedQueue { tracks });
+    }
+}
+
+/// Point saved entries at the new locations of moved files
+pub fn rename_paths(moves: &[(PathBuf, PathBuf)]) {
+    let Some(mut saved) = read_saved() else {
+        return;
+    };
+    let moves: HashMap<&PathBuf, &PathBuf> = moves.iter().map(|(from, to)| (from, to)).collect();
+    for path in &mut saved.tracks {
+        if let Some(&to) = moves.get(path) {
+            *path = to.clone();
+        }
+    }
+    write_saved(&saved);
+}
+
+fn queue_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("queue.json"))
+}
+
+fn read_saved() -> Option<SavedQueue> {
+    let data = std::fs::read_to_string(queue_path()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_saved(saved: &SavedQueue) {
+    let Some(path) = queue_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(saved) {
+        let _ = std::fs::write(path, json);
+    }
+}
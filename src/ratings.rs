@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::library::Track;
+
+/// Highest rating; ratings run from 1 to this many stars
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Serialize, Deserialize)]
+struct SavedRating {
+    path: PathBuf,
+    rating: u8,
+}
+
+/// Star ratings by `Track::key`, in `~/.tunebox/ratings.json`
+#[derive(Debug, Default)]
+pub struct Ratings {
+    by_key: HashMap<PathBuf, u8>,
+}
+
+impl Ratings {
+    pub fn load() -> Self {
+        let entries = read_saved().unwrap_or_default();
+        Self {
+            by_key: entries.into_iter().map(|e| (e.path, e.rating)).collect(),
+        }
+    }
+
+    pub fn save(&self) {
+        let mut entries: Vec<SavedRating> = self
+            .by_key
+            .iter()
+            .map(|(path, &rating)| SavedRating {
+                path: path.clone(),
+                rating,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        write_saved(&entries);
+    }
+
+    pub fn get(&self, track: &Track) -> Option<u8> {
+        self.by_key.get(&track.key()).copied()
+    }
+
+    /// Follow files moved by organize
+    pub fn rename(&mut self, moves: &[(PathBuf, PathBuf)]) {
+        for (from, to) in moves {
+            if let Some(rating) = self.by_key.remove(from) {
+                self.by_key.insert(to.clone(), rating);
+            }
+        }
+    }
+
+    /// Rate `track`; 0 clears its rating
+    pub fn set(&mut self, track: &Track, rating: u8) {
+        if rating == 0 {
+            self.by_key.remove(&track.key());
+        } else {
+            self.by_key.insert(track.key(), rating.min(MAX_RATING));
+        }
+    }
+}
+
+/// "★★★☆☆"
+pub fn stars(rating: u8) -> String {
+    let rating = rating.min(MAX_RATING) as usize;
+    format!(
+        "{}{}",
+        "★".repeat(rating),
+        "☆".repeat(MAX_RATING as usize - rating)
+    )
+}
+
+fn ratings_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("ratings.json"))
+}
+
+fn read_saved() -> Option<Vec<SavedRating>> {
+    let data = std::fs::read_to_string(ratings_path()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_saved(saved: &[SavedRating]) {
+    let Some(path) = ratings_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(saved) {
+        let _ = std::fs::write(path, json);
+    }
+}
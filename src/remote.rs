@@ -15,6 +15,11 @@ pub enum RemoteCommand {
     CycleTheme,
     CycleVisualizer,
     ToggleShuffle,
+    /// Play the queue entry at this position now
+    QueuePlay(usize),
+    QueueRemove(usize),
+    QueueMove { from: usize, to: usize },
+    QueueClear,
 }
 
 pub struct RemoteServer {
@@ -56,6 +61,9 @@ impl RemoteServer {
                 (Method::Post, path) if path.starts_with("/api/seek") => {
                     self.handle_seek(&url)
                 }
+                (Method::Post, path) if path.starts_with("/api/queue/") => {
+                    self.handle_queue(&url)
+                }
                 _ => Response::from_string("Not Found").with_status_code(404).boxed(),
             };
 
@@ -114,6 +122,28 @@ impl RemoteServer {
         Response::from_string("Bad Request").with_status_code(400).boxed()
     }
 
+    /// `/api/queue/play?i=N`, `/remove?i=N`, `/move?from=N&to=M` and `/clear`
+    fn handle_queue(&self, url: &str) -> tiny_http::ResponseBox {
+        let action = url.trim_start_matches("/api/queue/").split('?').next();
+        let index = |key| parse_query_param(url, key).and_then(|v| v.parse::<usize>().ok());
+        let cmd = match action {
+            Some("play") => index("i").map(RemoteCommand::QueuePlay),
+            Some("remove") => index("i").map(RemoteCommand::QueueRemove),
+            Some("move") => index("from")
+                .zip(index("to"))
+                .map(|(from, to)| RemoteCommand::QueueMove { from, to }),
+            Some("clear") => Some(RemoteCommand::QueueClear),
+            _ => return Response::from_string("Not Found").with_status_code(404).boxed(),
+        };
+        match cmd {
+            Some(cmd) => {
+                let _ = self.cmd_tx.send(cmd);
+                Response::from_string("OK").boxed()
+            }
+            None => Response::from_string("Bad Request").with_status_code(400).boxed(),
+        }
+    }
+
     fn handle_theme(&self) -> tiny_http::ResponseBox {
         let _ = self.cmd_tx.send(RemoteCommand::CycleTheme);
         Response::from_string("OK").boxed()
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::{PlaybackSpeed, RepeatMode, Theme};
+use crate::browser::BrowseView;
+use crate::config;
+use crate::shuffle::ShuffleMode;
+use crate::visualizer::VisualizerMode;
+
+/// Player state saved on quit (and periodically) so `--resume` can pick up
+/// where the last session left off. The queue and history have their own
+/// files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    /// Track that was loaded, by path since library indices change on rescan
+    pub track: Option<PathBuf>,
+    /// Seconds into `track`
+    pub position: f64,
+    pub volume: f32,
+    pub speed: PlaybackSpeed,
+    pub repeat: RepeatMode,
+    pub shuffle: bool,
+    pub shuffle_mode: ShuffleMode,
+    pub theme: Theme,
+    pub visualizer: VisualizerMode,
+    pub hide_duplicates: bool,
+    pub show_info: bool,
+    pub show_queue: bool,
+    pub mini_mode: bool,
+    pub view: BrowseView,
+    pub search_query: String,
+    /// Cursor row in `view`
+    pub selected: usize,
+}
+
+impl Session {
+    /// The last saved session, if there is one and it still parses
+    pub fn load() -> Option<Self> {
+        let data = std::fs::read_to_string(session_path()?).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = session_path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(self) {
+            let _ = std::fs::write(path, json);
+        }
+    }
+}
+
+fn session_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("session.json"))
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::plays::PlayCounts;
+use crate::library::Track;
+use crate::ratings::Ratings;
+
+/// How long since a track last played before weighted shuffle stops
+/// favouring anything over it
+const WEIGHT_HORIZON_SECS: f64 = 30.0 * 24.0 * 3600.0;
+
+/// How a shuffled play order is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShuffleMode {
+    /// Every track in uniformly random order
+    #[default]
+    Tracks,
+    /// Albums in random order, each played through in track order
+    Albums,
+    /// Random, but never the same artist twice in a row when avoidable
+    ArtistSpread,
+    /// Random, favouring higher-rated tracks and those that haven't
+    /// played recently
+    Weighted,
+}
+
+impl ShuffleMode {
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Tracks => Self::Albums,
+            Self::Albums => Self::ArtistSpread,
+            Self::ArtistSpread => Self::Weighted,
+            Self::Weighted => Self::Tracks,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tracks => "Tracks",
+            Self::Albums => "Albums",
+            Self::ArtistSpread => "Artist spread",
+            Self::Weighted => "Weighted",
+        }
+    }
+
+    /// Short form for the status bar badge
+    pub fn badge(self) -> &'static str {
+        match self {
+            Self::Tracks => "SHUF",
+            Self::Albums => "SHUF ALB",
+            Self::ArtistSpread => "SHUF ART",
+            Self::Weighted => "SHUF WT",
+        }
+    }
+}
+
+/// Play order for `tracks` (library indices) under `mode`
+pub fn order(
+    mode: ShuffleMode,
+    tracks: &[usize],
+    library: &[Track],
+    plays: &PlayCounts,
+    ratings: &Ratings,
+) -> Vec<usize> {
+    let mut rng = rand::thread_rng();
+    match mode {
+        ShuffleMode::Tracks => {
+            let mut order = tracks.to_vec();
+            order.shuffle(&mut rng);
+            order
+        }
+        ShuffleMode::Albums => {
+            // `tracks` come in library order, so each album is already in
+            // disc/track order
+            let mut albums = group_by(tracks, |i| library[i].album_key());
+            albums.shuffle(&mut rng);
+            albums.concat()
+        }
+        ShuffleMode::ArtistSpread => {
+            let mut artists = group_by(tracks, |i| library[i].artist.to_lowercase());
+            for songs in &mut artists {
+                songs.shuffle(&mut rng);
+            }
+            spread(artists, &mut rng)
+        }
+        ShuffleMode::Weighted => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            // Efraimidis-Spirakis: sorting by u^(1/w) draws a weighted
+            // random order in one pass
+            let mut keyed: Vec<(f64, usize)> = tracks
+                .iter()
+                .map(|&i| {
+                    let age = plays.get(&library[i]).map_or(WEIGHT_HORIZON_SECS, |p| {
+                        now.saturating_sub(p.last_played) as f64
+                    });
+                    let recency = 1.0 + 3.0 * (age / WEIGHT_HORIZON_SECS).min(1.0);
+                    // Three stars or unrated is neutral; five weighs ~2.8x,
+                    // one ~1/9
+                    let stars = ratings.get(&library[i]).map_or(1.0, |s| (s as f64 / 3.0).powi(2));
+                    let weight = recency * stars;
+                    let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                    (u.powf(1.0 / weight), i)
+                })
+                .collect();
+            keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+            keyed.into_iter().map(|(_, i)| i).collect()
+        }
+    }
+}
+
+/// Split `tracks` into groups sharing a key, keeping first-seen order
+fn group_by(tracks: &[usize], key: impl Fn(usize) -> String) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for &i in tracks {
+        let slot = *by_key.entry(key(i)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[slot].push(i);
+    }
+    groups
+}
+
+/// Interleave artist groups so neighbours differ. Picks are random, weighted
+/// by how many tracks each artist has left, except that an artist holding
+/// more than half of what remains must go next or it can't be spread out later.
+fn spread(mut groups: Vec<Vec<usize>>, rng: &mut impl Rng) -> Vec<usize> {
+    let total: usize = groups.iter().map(Vec::len).sum();
+    let mut order = Vec::with_capacity(total);
+    let mut last: Option<usize> = None;
+    while order.len() < total {
+        let remaining = total - order.len();
+        let candidates: Vec<usize> = (0..groups.len())
+            .filter(|&g| !groups[g].is_empty() && Some(g) != last)
+            .collect();
+        let pick = if candidates.is_empty() {
+            // Only the previous artist is left
+            match last {
+                Some(g) => g,
+                None => break,
+            }
+        } else if let Some(&g) = candidates.iter().find(|&&g| groups[g].len() * 2 > remaining) {
+            g
+        } else {
+            let mut n = rng.gen_range(0..candidates.iter().map(|&g| groups[g].len()).sum::<usize>());
+            *candidates
+                .iter()
+                .find(|&&g| {
+                    if n < groups[g].len() {
+                        return true;
+                    }
+                    n -= groups[g].len();
+                    false
+                })
+                .unwrap_or(&candidates[0])
+        };
+        order.extend(groups[pick].pop());
+        last = Some(pick);
+    }
+    order
+}
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// A playlist of whatever currently matches a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartPlaylist {
+    pub name: String,
+    /// Rules in the `query` language, e.g. `genre:jazz year:<1970 50 random tracks`
+    pub query: String,
+    /// Defined in `config.json` rather than from the player, which leaves
+    /// it alone
+    #[serde(skip)]
+    pub from_config: bool,
+}
+
+/// Smart playlists created from the player, in `~/.tunebox/smart_playlists.json`
+pub fn saved() -> Vec<SmartPlaylist> {
+    smart_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Write the player's smart playlists, skipping those from the config
+pub fn save(playlists: &[SmartPlaylist]) -> Result<()> {
+    let path = smart_path().context("No home directory")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let own: Vec<&SmartPlaylist> = playlists.iter().filter(|p| !p.from_config).collect();
+    std::fs::write(&path, serde_json::to_string_pretty(&own)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn smart_path() -> Option<PathBuf> {
+    config::data_dir().map(|d| d.join("smart_playlists.json"))
+}
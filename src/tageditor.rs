@@ -0,0 +1,165 @@
+use crate::library::Track;
+use crate::metadata::TagEdit;
+
+/// Fields shown in the tag editor, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Track,
+    Disc,
+    Year,
+    Genre,
+}
+
+impl TagField {
+    pub const ALL: [TagField; 8] = [
+        Self::Title,
+        Self::Artist,
+        Self::Album,
+        Self::AlbumArtist,
+        Self::Track,
+        Self::Disc,
+        Self::Year,
+        Self::Genre,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Title => "Title",
+            Self::Artist => "Artist",
+            Self::Album => "Album",
+            Self::AlbumArtist => "Album Artist",
+            Self::Track => "Track",
+            Self::Disc => "Disc",
+            Self::Year => "Year",
+            Self::Genre => "Genre",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Self::Track | Self::Disc | Self::Year)
+    }
+
+    fn value(self, track: &Track) -> String {
+        match self {
+            Self::Title => track.title.clone(),
+            Self::Artist => track.artist.clone(),
+            Self::Album => track.album.clone(),
+            Self::AlbumArtist => track.album_artist.clone().unwrap_or_default(),
+            Self::Track => track.track_number.map(|n| n.to_string()).unwrap_or_default(),
+            Self::Disc => track.disc_number.map(|n| n.to_string()).unwrap_or_default(),
+            Self::Year => track.year.map(|n| n.to_string()).unwrap_or_default(),
+            Self::Genre => track.genre.clone().unwrap_or_default(),
+        }
+    }
+
+    fn slot(self, edit: &mut TagEdit) -> &mut Option<String> {
+        match self {
+            Self::Title => &mut edit.title,
+            Self::Artist => &mut edit.artist,
+            Self::Album => &mut edit.album,
+            Self::AlbumArtist => &mut edit.album_artist,
+            Self::Track => &mut edit.track_number,
+            Self::Disc => &mut edit.disc_number,
+            Self::Year => &mut edit.year,
+            Self::Genre => &mut edit.genre,
+        }
+    }
+}
+
+/// One row of the form. A field whose tracks disagree starts out `mixed`
+/// and is only written if the user types into it.
+#[derive(Debug, Clone)]
+pub struct FieldState {
+    pub field: TagField,
+    pub value: String,
+    pub original: String,
+    pub mixed: bool,
+    pub changed: bool,
+}
+
+/// The tag edit form for one track or a batch of them
+#[derive(Debug, Clone)]
+pub struct TagEditor {
+    /// Library indices being edited
+    pub tracks: Vec<usize>,
+    pub fields: Vec<FieldState>,
+    pub selected: usize,
+}
+
+impl TagEditor {
+    pub fn new(library: &[Track], tracks: Vec<usize>) -> Self {
+        let fields = TagField::ALL
+            .iter()
+            .map(|&field| {
+                let mut values = tracks.iter().map(|&i| field.value(&library[i]));
+                let first = values.next().unwrap_or_default();
+                let mixed = values.any(|v| v != first);
+                let original = if mixed { String::new() } else { first };
+                FieldState {
+                    field,
+                    value: original.clone(),
+                    original,
+                    mixed,
+                    changed: false,
+                }
+            })
+            .collect();
+        Self {
+            tracks,
+            fields,
+            selected: 0,
+        }
+    }
+
+    pub fn next_field(&mut self) {
+        self.selected = (self.selected + 1) % self.fields.len();
+    }
+
+    pub fn prev_field(&mut self) {
+        self.selected = (self.selected + self.fields.len() - 1) % self.fields.len();
+    }
+
+    pub fn input(&mut self, c: char) {
+        let state = &mut self.fields[self.selected];
+        if state.field.is_numeric() && !c.is_ascii_digit() {
+            return;
+        }
+        state.value.push(c);
+        state.changed = true;
+    }
+
+    pub fn backspace(&mut self) {
+        let state = &mut self.fields[self.selected];
+        state.value.pop();
+        state.changed = true;
+    }
+
+    /// Empty the field; saving then removes the tag
+    pub fn clear(&mut self) {
+        let state = &mut self.fields[self.selected];
+        state.value.clear();
+        state.changed = true;
+    }
+
+    /// Back to the value the form opened with ("keep existing" for mixed fields)
+    pub fn keep(&mut self) {
+        let state = &mut self.fields[self.selected];
+        state.value = state.original.clone();
+        state.changed = false;
+    }
+
+    /// The fields the user actually changed
+    pub fn edit(&self) -> TagEdit {
+        let mut edit = TagEdit::default();
+        for state in &self.fields {
+            if state.changed && (state.mixed || state.value != state.original) {
+                *state.field.slot(&mut edit) = Some(state.value.clone());
+            }
+        }
+        edit
+    }
+}
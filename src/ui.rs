@@ -5,6 +5,12 @@ use ratatui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph};
 use ratatui::Frame;
 
 use crate::app::{App, Theme};
+use crate::artloader::{ArtSize, THUMB_HEIGHT, THUMB_WIDTH};
+use crate::browser::BrowseView;
+use crate::columns::{self, Column};
+use crate::library::Track;
+use crate::query::Stats;
+use crate::ratings;
 use crate::visualizer::VisualizerMode;
 
 // Theme color struct
@@ -90,36 +96,92 @@ pub fn draw(frame: &mut Frame, app: &App) {
     let bg_block = Block::default().style(Style::default().bg(colors.bg_dark));
     frame.render_widget(bg_block, size);
 
-    // Smart layout: give more space to visualizer when library is small
-    let lib_size = app.filtered_indices.len();
-    let vis_height = if lib_size <= 3 { 10 } else if lib_size <= 10 { 8 } else { 6 };
-    let lib_min = if lib_size <= 3 { 4 } else { 6 };
-
-    // Main layout: header area, visualizer, library, footer
-    let main_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(12),           // Now Playing (art + info)
-            Constraint::Length(vis_height),   // Visualizer (dynamic)
-            Constraint::Min(lib_min),         // Library
-            Constraint::Length(2),            // Footer (2 lines for help bar)
-        ])
-        .split(size);
+    let main_chunks = main_layout(size, app);
 
     draw_now_playing(frame, app, main_chunks[0], &colors);
     draw_visualizer(frame, app, main_chunks[1], size.width, &colors);
-    draw_library(frame, app, main_chunks[2], &colors);
+    let library_chunks = library_layout(main_chunks[2], app);
+    draw_library(frame, app, library_chunks[0], &colors);
+    if app.show_queue {
+        draw_queue(frame, app, library_chunks[1], &colors);
+    } else if app.show_chapters {
+        draw_chapters(frame, app, library_chunks[1], &colors);
+    }
     draw_footer(frame, app, main_chunks[3], &colors);
 
     // Search overlay
     if app.search_mode {
-        draw_search(frame, app, size, &colors);
+        let title = match &app.search_error {
+            Some(e) => format!(" Search · ⚠ {} ", e),
+            None => " Search ".to_string(),
+        };
+        draw_input(frame, &title, format!("/{}", app.search_query), size, &colors);
+    }
+
+    if let Some(prompt) = &app.playlist_prompt {
+        draw_input(frame, " Save to playlist ", prompt.name.clone(), size, &colors);
+    }
+    if let Some(text) = &app.smart_prompt {
+        draw_input(frame, " Smart playlist: name = rules ", text.clone(), size, &colors);
     }
 
     // Info panel overlay
     if app.show_info {
         draw_info_panel(frame, app, size, &colors);
     }
+
+    // Tag editor overlay
+    if app.tag_editor.is_some() {
+        draw_tag_editor(frame, app, size, &colors);
+    }
+
+    if app.column_editor.is_some() {
+        draw_column_editor(frame, app, size, &colors);
+    }
+}
+
+/// Library pane, plus the queue pane beside it when that is open
+fn library_layout(area: Rect, app: &App) -> std::rc::Rc<[Rect]> {
+    let queue_width = if app.show_queue || app.show_chapters { area.width * 2 / 5 } else { 0 };
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(queue_width)])
+        .split(area)
+}
+
+fn main_layout(size: Rect, app: &App) -> std::rc::Rc<[Rect]> {
+    // Smart layout: give more space to visualizer when library is small
+    let lib_size = app.visible_len();
+    let vis_height = if lib_size <= 3 { 10 } else if lib_size <= 10 { 8 } else { 6 };
+    let lib_min = if lib_size <= 3 { 4 } else { 6 };
+
+    // Main layout: header area, visualizer, library, footer
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(12),           // Now Playing (art + info)
+            Constraint::Length(vis_height),   // Visualizer (dynamic)
+            Constraint::Min(lib_min),         // Library
+            Constraint::Length(2),            // Footer (2 lines for help bar)
+        ])
+        .split(size)
+}
+
+// Album grid tile: cover plus one line for the name, with a gap around it
+const TILE_WIDTH: u16 = THUMB_WIDTH as u16 + 2;
+const TILE_HEIGHT: u16 = THUMB_HEIGHT as u16 + 2;
+/// How long a status message stays in the footer
+const STATUS_DURATION: std::time::Duration = std::time::Duration::from_secs(4);
+
+/// Columns and rows of album tiles that fit in the library pane
+pub fn grid_dimensions(size: Rect, app: &App) -> (usize, usize) {
+    let library = library_layout(main_layout(size, app)[2], app)[0];
+    let inner_width = library.width.saturating_sub(2);
+    let inner_height = library.height.saturating_sub(2);
+    (
+        (inner_width / TILE_WIDTH).max(1) as usize,
+        (inner_height / TILE_HEIGHT).max(1) as usize,
+    )
 }
 
 fn draw_mini_mode(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
@@ -165,7 +227,7 @@ fn draw_mini_mode(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors
 
     if app.shuffle {
         spans.push(Span::styled(" │ ", Style::default().fg(colors.text_muted)));
-        spans.push(Span::styled("SHUF", Style::default().fg(colors.accent)));
+        spans.push(Span::styled(app.shuffle_mode.badge(), Style::default().fg(colors.accent)));
     }
 
     if let Some(remaining) = app.sleep_timer_remaining() {
@@ -237,7 +299,7 @@ fn draw_now_playing(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColo
         width: chunks[0].width.saturating_sub(2).min(20),
         height: chunks[0].height.min(10),
     };
-    if let Some(ref art) = app.album_art {
+    if let Some(art) = app.current_album_art() {
         art.render(art_area, frame.buffer_mut());
     }
 
@@ -257,7 +319,10 @@ fn draw_track_info(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColor
         .map(|t| t.title.as_str())
         .unwrap_or("No track playing");
     let artist_album = track
-        .map(|t| format!("{} — {}", t.artist, t.album))
+        .map(|t| match t.year {
+            Some(year) => format!("{} — {} ({})", t.artist, t.album, year),
+            None => format!("{} — {}", t.artist, t.album),
+        })
         .unwrap_or_default();
 
     let mut lines = vec![
@@ -271,8 +336,19 @@ fn draw_track_info(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColor
             artist_album,
             Style::default().fg(colors.text_dim),
         )),
-        Line::from(""),
     ];
+    match app.current_chapter() {
+        Some(index) => lines.push(Line::from(Span::styled(
+            format!(
+                "Chapter {}/{}: {}",
+                index + 1,
+                app.chapters.len(),
+                app.chapters[index].title
+            ),
+            Style::default().fg(colors.accent_secondary),
+        ))),
+        None => lines.push(Line::from("")),
+    }
 
     // Playback controls with styled status badges
     let play_icon = if app.is_playing { "▶" } else { "■" };
@@ -287,7 +363,7 @@ fn draw_track_info(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColor
     if app.shuffle {
         control_spans.push(Span::raw("  "));
         control_spans.push(Span::styled(
-            " SHUF ",
+            format!(" {} ", app.shuffle_mode.badge()),
             Style::default().fg(colors.accent).bg(colors.status_bg).add_modifier(Modifier::BOLD),
         ));
     }
@@ -368,14 +444,30 @@ fn draw_track_info(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColor
             Span::raw(" "),
         ];
 
+        // Chapter starts become ticks on the bar
+        let ticks: Vec<usize> = app
+            .chapters
+            .iter()
+            .skip(1)
+            .map(|c| ((c.start / app.duration).clamp(0.0, 1.0) * bar_width as f64) as usize)
+            .collect();
+
         for i in 0..filled {
             let t = i as f32 / bar_width.max(1) as f32;
             let color = gradient_color_themed(t, colors);
-            progress_spans.push(Span::styled("━", Style::default().fg(color)));
+            let cell = if ticks.contains(&i) { "╋" } else { "━" };
+            progress_spans.push(Span::styled(cell, Style::default().fg(color)));
         }
 
         progress_spans.push(Span::styled("●", Style::default().fg(colors.text_primary)));
-        progress_spans.push(Span::styled("─".repeat(empty), Style::default().fg(colors.text_muted)));
+        if ticks.is_empty() {
+            progress_spans.push(Span::styled("─".repeat(empty), Style::default().fg(colors.text_muted)));
+        } else {
+            let rest: String = (filled + 1..=filled + empty)
+                .map(|i| if ticks.contains(&i) { '┼' } else { '─' })
+                .collect();
+            progress_spans.push(Span::styled(rest, Style::default().fg(colors.text_muted)));
+        }
         progress_spans.push(Span::raw(" "));
         progress_spans.push(Span::styled(total, Style::default().fg(colors.text_dim)));
         progress_spans.push(Span::styled(format!("  -{}", remaining), Style::default().fg(colors.text_muted)));
@@ -581,23 +673,86 @@ fn draw_waveform(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors)
 }
 
 fn draw_library(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
-    // Show track count in title
-    let track_count = app.filtered_indices.len();
-    let title = format!(" Library ({}) ", track_count);
+    // View tabs, then breadcrumb and row count
+    let mut title_spans = vec![Span::raw(" ")];
+    for view in BrowseView::ALL {
+        let style = if view == app.browser.view {
+            Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(colors.text_muted)
+        };
+        title_spans.push(Span::styled(view.label(), style));
+        title_spans.push(Span::styled(" │ ", Style::default().fg(colors.text_muted)));
+    }
+    title_spans.pop();
+    let mut crumb = app.browser.breadcrumb();
+    if !app.search_mode && !app.search_query.is_empty() {
+        // A search kept with Enter stays applied until Esc clears it
+        if !crumb.is_empty() {
+            crumb.push(' ');
+        }
+        crumb.push('/');
+        crumb.push_str(&app.search_query);
+    }
+    let location = if crumb.is_empty() {
+        format!("   ({}) ", app.visible_len())
+    } else {
+        format!("   {} ({}) ", crumb, app.visible_len())
+    };
+    title_spans.push(Span::styled(
+        location,
+        Style::default()
+            .fg(colors.text_primary)
+            .add_modifier(Modifier::BOLD),
+    ));
+    let marked = app.marked_tracks().len();
+    if marked > 0 || app.visual_anchor.is_some() {
+        let label = if app.visual_anchor.is_some() { "VISUAL · " } else { "" };
+        title_spans.push(Span::styled(
+            format!("{}{} marked ", label, marked),
+            Style::default().fg(colors.accent_secondary),
+        ));
+    }
 
     let block = Block::default()
-        .title(Span::styled(
-            title,
-            Style::default()
-                .fg(colors.text_primary)
-                .add_modifier(Modifier::BOLD),
-        ))
+        .title(Line::from(title_spans))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(colors.text_muted))
         .style(Style::default().bg(colors.bg_panel));
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
+    if app.in_grid() {
+        draw_album_grid(frame, app, inner, colors);
+        return;
+    }
+    if !app.browser.showing_tracks() {
+        draw_browse_entries(frame, app, inner, colors);
+        return;
+    }
+
+    // Column headers, marking the sort column
+    let widths = columns::layout(&app.columns, inner.width.saturating_sub(INDICATOR_WIDTH));
+    let sort = app.applied_sort();
+    let mut header = vec![Span::raw(" ".repeat(INDICATOR_WIDTH as usize))];
+    for (&column, &width) in app.columns.iter().zip(&widths) {
+        if width == 0 {
+            continue;
+        }
+        let (label, style) = match sort {
+            Some(sort) if sort.column == column => (
+                format!("{}{}", column.header, if sort.descending { "▼" } else { "▲" }),
+                Style::default().fg(colors.accent).add_modifier(Modifier::BOLD),
+            ),
+            _ => (column.header.to_string(), Style::default().fg(colors.text_muted)),
+        };
+        header.push(Span::styled(pad(&label, width as usize, column.right_align), style));
+        header.push(Span::raw(" "));
+    }
+    let [header_area, rows_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+    frame.render_widget(Paragraph::new(Line::from(header)), header_area);
+
     if app.filtered_indices.is_empty() {
         let msg = if app.search_mode {
             "No matches found"
@@ -605,20 +760,16 @@ fn draw_library(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors)
             "No audio files found"
         };
         let paragraph = Paragraph::new(Span::styled(msg, Style::default().fg(colors.text_muted)));
-        frame.render_widget(paragraph, inner);
+        frame.render_widget(paragraph, rows_area);
         return;
     }
 
-    let visible_height = inner.height as usize;
+    let visible_height = rows_area.height as usize;
 
     // Adjust scroll offset to keep selected visible
     let scroll = calculate_scroll(app.selected_index, visible_height, app.scroll_offset);
-
-    // Responsive column widths based on terminal width
-    let available_width = inner.width.saturating_sub(10) as usize; // 10 for indicator + duration + spacing
-    let title_width = (available_width * 50 / 100).max(15).min(50);
-    let artist_width = (available_width * 30 / 100).max(10).min(30);
-    let album_width = available_width.saturating_sub(title_width + artist_width).max(0).min(25);
+    let visual = app.visual_span();
+    let stats = Stats::new(&app.play_counts, &app.ratings);
 
     let items: Vec<ListItem> = app
         .filtered_indices
@@ -630,10 +781,19 @@ fn draw_library(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors)
             let track = &app.library[lib_idx];
             let is_playing = app.playing_index == Some(lib_idx);
             let is_selected = display_idx == app.selected_index;
+            let is_marked = app.marks.contains(&lib_idx)
+                || visual.is_some_and(|(start, end)| (start..=end).contains(&display_idx));
 
             // Animated playing indicator
-            let indicator = if is_playing { "▶ " } else { "  " };
-            let duration_str = format_time(track.duration);
+            let indicator = if is_playing {
+                "▶ "
+            } else if is_marked {
+                "● "
+            } else if track.cue.is_none() && app.positions.is_finished(&track.path) {
+                "✓ "
+            } else {
+                "  "
+            };
 
             let title_style = if is_playing {
                 Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
@@ -643,38 +803,35 @@ fn draw_library(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors)
                 Style::default().fg(colors.text_dim)
             };
 
-            let mut spans = vec![
-                Span::styled(
-                    indicator,
-                    Style::default().fg(if is_playing { colors.accent } else { colors.text_muted }),
-                ),
-                Span::styled(
-                    truncate_str(&track.title, title_width),
-                    title_style,
-                ),
-                Span::styled(" ", Style::default()),
-                Span::styled(
-                    truncate_str(&track.artist, artist_width),
-                    Style::default().fg(colors.text_muted),
-                ),
-            ];
-
-            // Add album if there's space
-            if album_width > 5 {
-                spans.push(Span::styled(" ", Style::default()));
-                spans.push(Span::styled(
-                    truncate_str(&track.album, album_width),
-                    Style::default().fg(colors.text_muted),
-                ));
+            let muted = Style::default().fg(colors.text_muted);
+            let indicator_color = if is_playing {
+                colors.accent
+            } else if is_marked {
+                colors.accent_secondary
+            } else {
+                colors.text_muted
+            };
+            let mut spans = vec![Span::styled(indicator, Style::default().fg(indicator_color))];
+            for (&column, &width) in app.columns.iter().zip(&widths) {
+                if width == 0 {
+                    continue;
+                }
+                let style = if column.name == "title" { title_style } else { muted };
+                let value = cell(column, track, &stats);
+                if column.right_align {
+                    spans.push(Span::styled(pad(&value, width as usize, true), style));
+                } else {
+                    spans.extend(highlighted(app, column.name, &value, width as usize, style, colors));
+                }
+                spans.push(Span::raw(" "));
             }
 
-            spans.push(Span::styled("  ", Style::default()));
-            spans.push(Span::styled(duration_str, Style::default().fg(colors.text_muted)));
-
             let line = Line::from(spans);
 
             let bg = if is_selected {
                 colors.status_bg
+            } else if is_marked {
+                colors.bg_dark
             } else {
                 colors.bg_panel
             };
@@ -684,7 +841,370 @@ fn draw_library(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors)
         .collect();
 
     let list = List::new(items);
-    frame.render_widget(list, inner);
+    frame.render_widget(list, rows_area);
+}
+
+/// Cells before the first column: the playing / marked indicator
+const INDICATOR_WIDTH: u16 = 2;
+
+/// What `column` shows for library track `index`
+fn cell(column: &Column, track: &Track, stats: &Stats) -> String {
+    let number = |n: Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+    match column.name {
+        "track" => number(track.track_number),
+        "title" => track.title.clone(),
+        "artist" => track.artist.clone(),
+        "album" => track.album.clone(),
+        "albumartist" => track.album_artist_or_artist().to_string(),
+        "composer" => track.composer.clone().unwrap_or_default(),
+        "genre" => track.genre.clone().unwrap_or_default(),
+        "year" => number(track.year),
+        "disc" => number(track.disc_number),
+        "format" => track.format.clone(),
+        "bitrate" => track.bitrate.map(|b| format!("{} kbps", b)).unwrap_or_default(),
+        "samplerate" => track
+            .sample_rate
+            .map(|r| format!("{}k", r as f64 / 1000.0))
+            .unwrap_or_default(),
+        "plays" => match stats.plays(track) {
+            0 => String::new(),
+            plays => plays.to_string(),
+        },
+        "rating" => stats.rating(track).map(ratings::stars).unwrap_or_default(),
+        "lastplayed" => stats.since_played(track).map(format_ago).unwrap_or_default(),
+        "duration" => format_time(track.duration),
+        _ => String::new(),
+    }
+}
+
+/// "5m ago", "3h ago", "2d ago", "6w ago", "1y ago"
+fn format_ago(secs: u64) -> String {
+    const UNITS: [(u64, &str); 5] = [
+        (365 * 86400, "y"),
+        (7 * 86400, "w"),
+        (86400, "d"),
+        (3600, "h"),
+        (60, "m"),
+    ];
+    UNITS
+        .iter()
+        .find(|&&(size, _)| secs >= size)
+        .map(|&(size, unit)| format!("{}{} ago", secs / size, unit))
+        .unwrap_or_else(|| "now".to_string())
+}
+
+/// `text` cut or padded to `width` characters
+fn pad(text: &str, width: usize, right_align: bool) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    if chars.len() > width {
+        chars.truncate(width.saturating_sub(1));
+        chars.push('…');
+    }
+    let text: String = chars.into_iter().collect();
+    if right_align {
+        format!("{:>width$}", text, width = width)
+    } else {
+        format!("{:<width$}", text, width = width)
+    }
+}
+
+/// The track list column whose header is at `x`, `y`, for mouse clicks
+pub fn column_at(size: Rect, app: &App, x: u16, y: u16) -> Option<&'static Column> {
+    if app.mini_mode || !app.browser.showing_tracks() {
+        return None;
+    }
+    let library = library_layout(main_layout(size, app)[2], app)[0];
+    let inner = Block::default().borders(Borders::ALL).inner(library);
+    if y != inner.y || x < inner.x + INDICATOR_WIDTH {
+        return None;
+    }
+    let widths = columns::layout(&app.columns, inner.width.saturating_sub(INDICATOR_WIDTH));
+    let mut left = inner.x + INDICATOR_WIDTH;
+    for (&column, &width) in app.columns.iter().zip(&widths) {
+        if width == 0 {
+            continue;
+        }
+        if x < left + width {
+            return Some(column);
+        }
+        left += width + 1;
+    }
+    None
+}
+
+fn draw_queue(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+    let title = Span::styled(
+        format!(" Queue ({}) ", app.queue.len()),
+        Style::default().fg(colors.accent).add_modifier(Modifier::BOLD),
+    );
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent))
+        .style(Style::default().bg(colors.bg_panel));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if app.queue.is_empty() {
+        let lines = vec![
+            Line::from(Span::styled("Queue is empty", Style::default().fg(colors.text_muted))),
+            Line::from(Span::styled(
+                "a adds the selection, A plays it next",
+                Style::default().fg(colors.text_muted),
+            )),
+        ];
+        frame.render_widget(Paragraph::new(lines), inner);
+        return;
+    }
+
+    // Last row is the key help
+    let visible_height = inner.height.saturating_sub(1) as usize;
+    let scroll = calculate_scroll(app.queue_cursor, visible_height, 0);
+    let duration_width = 6;
+    let label_width = (inner.width as usize).saturating_sub(duration_width + 6).max(5);
+
+    let mut items: Vec<ListItem> = app
+        .queue
+        .tracks()
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(visible_height)
+        .map(|(pos, &lib_idx)| {
+            let track = &app.library[lib_idx];
+            let is_selected = pos == app.queue_cursor;
+            let label = format!("{} – {}", track.title, track.artist);
+            let line = Line::from(vec![
+                Span::styled(format!("{:>3} ", pos + 1), Style::default().fg(colors.text_muted)),
+                Span::styled(
+                    truncate_str(&label, label_width),
+                    Style::default().fg(if is_selected {
+                        colors.text_primary
+                    } else {
+                        colors.text_dim
+                    }),
+                ),
+                Span::styled("  ", Style::default()),
+                Span::styled(format_time(track.duration), Style::default().fg(colors.text_muted)),
+            ]);
+            let bg = if is_selected { colors.status_bg } else { colors.bg_panel };
+            ListItem::new(line).style(Style::default().bg(bg))
+        })
+        .collect();
+    while items.len() < visible_height {
+        items.push(ListItem::new(""));
+    }
+    items.push(ListItem::new(Line::from(Span::styled(
+        "Enter play · J/K move · d remove · c clear · Esc close",
+        Style::default().fg(colors.text_muted),
+    ))));
+    frame.render_widget(List::new(items), inner);
+}
+
+fn draw_chapters(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+    let title = Span::styled(
+        format!(" Chapters ({}) ", app.chapters.len()),
+        Style::default().fg(colors.accent).add_modifier(Modifier::BOLD),
+    );
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent))
+        .style(Style::default().bg(colors.bg_panel));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if app.chapters.is_empty() {
+        let paragraph = Paragraph::new(Span::styled(
+            "This file has no chapters",
+            Style::default().fg(colors.text_muted),
+        ));
+        frame.render_widget(paragraph, inner);
+        return;
+    }
+
+    // Last row is the key help
+    let visible_height = inner.height.saturating_sub(1) as usize;
+    let scroll = calculate_scroll(app.chapter_cursor, visible_height, 0);
+    let time_width = 8;
+    let label_width = (inner.width as usize).saturating_sub(time_width + 4).max(5);
+    let current = app.current_chapter();
+
+    let mut items: Vec<ListItem> = app
+        .chapters
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(visible_height)
+        .map(|(i, chapter)| {
+            let is_selected = i == app.chapter_cursor;
+            let is_current = current == Some(i);
+            let label_style = if is_current {
+                Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
+            } else if is_selected {
+                Style::default().fg(colors.text_primary)
+            } else {
+                Style::default().fg(colors.text_dim)
+            };
+            let line = Line::from(vec![
+                Span::styled(
+                    if is_current { "▶ " } else { "  " },
+                    Style::default().fg(colors.accent),
+                ),
+                Span::styled(truncate_str(&chapter.title, label_width), label_style),
+                Span::styled("  ", Style::default()),
+                Span::styled(format_time(chapter.start), Style::default().fg(colors.text_muted)),
+            ]);
+            let bg = if is_selected { colors.status_bg } else { colors.bg_panel };
+            ListItem::new(line).style(Style::default().bg(bg))
+        })
+        .collect();
+    while items.len() < visible_height {
+        items.push(ListItem::new(""));
+    }
+    items.push(ListItem::new(Line::from(Span::styled(
+        "Enter jump · n/p next/prev chapter · Esc close",
+        Style::default().fg(colors.text_muted),
+    ))));
+    frame.render_widget(List::new(items), inner);
+}
+
+/// Group listing (artists, albums, genres, folders) in the library pane
+fn draw_browse_entries(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+    let Some(level) = app.browser.current_level() else {
+        return;
+    };
+
+    if app.browse_filtered.is_empty() {
+        let msg = if app.search_mode { "No matches found" } else { "Nothing here" };
+        let paragraph = Paragraph::new(Span::styled(msg, Style::default().fg(colors.text_muted)));
+        frame.render_widget(paragraph, area);
+        return;
+    }
+
+    let visible_height = area.height as usize;
+    let scroll = calculate_scroll(app.selected_index, visible_height, app.scroll_offset);
+    let detail_width = (area.width as usize * 40 / 100).clamp(10, 40);
+    let label_width = (area.width as usize).saturating_sub(detail_width + 4).max(10);
+
+    let items: Vec<ListItem> = app
+        .browse_filtered
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(visible_height)
+        .map(|(display_idx, &entry_idx)| {
+            let entry = &level.entries[entry_idx];
+            let is_selected = display_idx == app.selected_index;
+            let is_playing = app
+                .playing_index
+                .is_some_and(|p| entry.tracks.contains(&p));
+
+            let indicator = if is_playing {
+                "▶ "
+            } else if entry.has_children() {
+                "▸ "
+            } else {
+                "  "
+            };
+            let label_style = if is_playing {
+                Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
+            } else if is_selected {
+                Style::default().fg(colors.text_primary)
+            } else {
+                Style::default().fg(colors.text_dim)
+            };
+
+            let line = Line::from(vec![
+                Span::styled(
+                    indicator,
+                    Style::default().fg(if is_playing { colors.accent } else { colors.text_muted }),
+                ),
+                Span::styled(truncate_str(&entry.label, label_width), label_style),
+                Span::styled("  ", Style::default()),
+                Span::styled(
+                    truncate_str(&entry.detail, detail_width),
+                    Style::default().fg(colors.text_muted),
+                ),
+            ]);
+
+            let bg = if is_selected { colors.status_bg } else { colors.bg_panel };
+            ListItem::new(line).style(Style::default().bg(bg))
+        })
+        .collect();
+
+    frame.render_widget(List::new(items), area);
+}
+
+/// Album covers laid out in tiles, scrolled by whole rows
+fn draw_album_grid(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+    let Some(level) = app.browser.current_level() else {
+        return;
+    };
+    if app.browse_filtered.is_empty() {
+        let paragraph = Paragraph::new(Span::styled("No matches found", Style::default().fg(colors.text_muted)));
+        frame.render_widget(paragraph, area);
+        return;
+    }
+
+    let cols = app.grid_columns.max(1);
+    let rows = app.grid_rows.max(1);
+    let first_row = calculate_scroll(app.selected_index / cols, rows, 0);
+
+    for (slot, (display_idx, &entry_idx)) in app
+        .browse_filtered
+        .iter()
+        .enumerate()
+        .skip(first_row * cols)
+        .take(rows * cols)
+        .enumerate()
+    {
+        let entry = &level.entries[entry_idx];
+        let tile = Rect::new(
+            area.x + (slot % cols) as u16 * TILE_WIDTH,
+            area.y + (slot / cols) as u16 * TILE_HEIGHT,
+            TILE_WIDTH,
+            TILE_HEIGHT,
+        );
+        if tile.right() > area.right() || tile.bottom() > area.bottom() {
+            continue;
+        }
+        let is_selected = display_idx == app.selected_index;
+        let is_playing = app.playing_index.is_some_and(|p| entry.tracks.contains(&p));
+
+        if is_selected {
+            frame.render_widget(Block::default().style(Style::default().bg(colors.status_bg)), tile);
+        }
+
+        let art_area = Rect::new(tile.x + 1, tile.y, THUMB_WIDTH as u16, THUMB_HEIGHT as u16);
+        let thumb = entry
+            .tracks
+            .first()
+            .and_then(|&t| app.art.get(&app.library[t].album_key(), ArtSize::Thumb))
+            .flatten();
+        match thumb {
+            Some(art) => art.render(art_area, frame.buffer_mut()),
+            None => {
+                let empty = Paragraph::new(Line::from(Span::styled("♪", Style::default().fg(colors.text_muted))))
+                    .alignment(ratatui::layout::Alignment::Center)
+                    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(colors.text_muted)));
+                frame.render_widget(empty, art_area);
+            }
+        }
+
+        let name_style = if is_playing {
+            Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
+        } else if is_selected {
+            Style::default().fg(colors.text_primary).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(colors.text_dim)
+        };
+        let name_area = Rect::new(tile.x + 1, tile.y + THUMB_HEIGHT as u16, THUMB_WIDTH as u16, 1);
+        frame.render_widget(
+            Paragraph::new(Span::styled(truncate_str(&entry.label, THUMB_WIDTH as usize), name_style)),
+            name_area,
+        );
+    }
 }
 
 fn draw_footer(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
@@ -716,8 +1236,18 @@ fn draw_footer(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
     let vis_mode = format!("Vis: {}", app.visualizer.mode.label());
     let theme_name = format!("Theme: {}", app.theme.name());
 
+    // A fresh status message, or a prompt still waiting for a key, takes the
+    // place of the track details
+    let waiting = app.cover_prompt || app.pending_organize.is_some();
+    let (track_info, info_color) = match app.status_message {
+        Some((ref msg, at)) if waiting || at.elapsed() < STATUS_DURATION => {
+            (msg.clone(), colors.accent)
+        }
+        _ => (track_info, colors.text_muted),
+    };
+
     let info_line = Line::from(vec![
-        Span::styled(track_info, Style::default().fg(colors.text_muted)),
+        Span::styled(track_info, Style::default().fg(info_color)),
         Span::styled("     ", Style::default()),
         Span::styled(vis_mode, Style::default().fg(colors.text_dim)),
         Span::styled("  │  ", Style::default().fg(colors.text_muted)),
@@ -737,6 +1267,12 @@ fn draw_footer(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
         Span::styled(" Vol  ", Style::default().fg(colors.text_muted)),
         Span::styled("s", Style::default().fg(colors.accent)),
         Span::styled(" Shuf  ", Style::default().fg(colors.text_muted)),
+        Span::styled("Tab", Style::default().fg(colors.accent)),
+        Span::styled(" View  ", Style::default().fg(colors.text_muted)),
+        Span::styled("P/S/a", Style::default().fg(colors.accent)),
+        Span::styled(" Play/Shuf/Queue  ", Style::default().fg(colors.text_muted)),
+        Span::styled("e", Style::default().fg(colors.accent)),
+        Span::styled(" Edit  ", Style::default().fg(colors.text_muted)),
         Span::styled("v", Style::default().fg(colors.accent)),
         Span::styled(" Vis  ", Style::default().fg(colors.text_muted)),
         Span::styled("T", Style::default().fg(colors.accent)),
@@ -756,39 +1292,250 @@ fn draw_footer(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
     frame.render_widget(help_paragraph, footer_chunks[1]);
 }
 
-fn draw_search(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+/// One-line text field near the bottom of the screen
+fn draw_input(frame: &mut Frame, title: &str, text: String, area: Rect, colors: &ThemeColors) {
     let width = area.width.min(50);
     let x = area.x + (area.width.saturating_sub(width)) / 2;
     let y = area.bottom().saturating_sub(4);
 
-    let search_area = Rect::new(x, y, width, 3);
-    frame.render_widget(Clear, search_area);
+    let input_area = Rect::new(x, y, width, 3);
+    frame.render_widget(Clear, input_area);
 
     let block = Block::default()
-        .title(Span::styled(" Search ", Style::default().fg(colors.accent)))
+        .title(Span::styled(title, Style::default().fg(colors.accent)))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(colors.accent))
         .style(Style::default().bg(colors.bg_panel));
 
-    let inner = block.inner(search_area);
-    frame.render_widget(block, search_area);
+    let inner = block.inner(input_area);
+    frame.render_widget(block, input_area);
 
-    let search_text = format!("/{}", app.search_query);
+    // Keep the end, where typing happens, in view
+    let overflow = text.chars().count().saturating_sub(inner.width as usize);
     let paragraph = Paragraph::new(Span::styled(
-        search_text,
+        text.chars().skip(overflow).collect::<String>(),
         Style::default().fg(colors.text_primary),
     ));
     frame.render_widget(paragraph, inner);
 }
 
+fn draw_tag_editor(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+    let Some(editor) = app.tag_editor.as_ref() else {
+        return;
+    };
+
+    let width = area.width.min(60);
+    let height = (editor.fields.len() as u16 + 4).min(area.height);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let editor_area = Rect::new(x, y, width, height);
+    frame.render_widget(Clear, editor_area);
+
+    let title = match editor.tracks.len() {
+        1 => " Edit Tags ".to_string(),
+        n => format!(" Edit Tags · {} tracks ", n),
+    };
+    let block = Block::default()
+        .title(Span::styled(title, Style::default().fg(colors.accent)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent))
+        .style(Style::default().bg(colors.bg_panel));
+    let inner = block.inner(editor_area);
+    frame.render_widget(block, editor_area);
+
+    let mut lines: Vec<Line> = editor
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, state)| {
+            let selected = i == editor.selected;
+            let label_style = if selected {
+                Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(colors.text_dim)
+            };
+            let value = if state.mixed && !state.changed {
+                Span::styled("(keep existing)", Style::default().fg(colors.text_muted))
+            } else {
+                let cursor = if selected { "▏" } else { "" };
+                Span::styled(
+                    format!("{}{}", state.value, cursor),
+                    Style::default().fg(colors.text_primary),
+                )
+            };
+            Line::from(vec![
+                Span::styled(format!("{:<14}", state.field.label()), label_style),
+                value,
+            ])
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Enter save · Esc cancel · Tab next · ^U clear · ^R keep",
+        Style::default().fg(colors.text_muted),
+    )));
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+fn draw_column_editor(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
+    let Some(cursor) = app.column_editor else {
+        return;
+    };
+    let rows = app.column_editor_rows();
+
+    let width = area.width.min(44);
+    let height = (rows.len() as u16 + 4).min(area.height);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let editor_area = Rect::new(x, y, width, height);
+    frame.render_widget(Clear, editor_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Columns ", Style::default().fg(colors.accent)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent))
+        .style(Style::default().bg(colors.bg_panel));
+    let inner = block.inner(editor_area);
+    frame.render_widget(block, editor_area);
+
+    let sort = app.column_sort;
+    let mut lines: Vec<Line> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, &(column, shown))| {
+            let style = if i == cursor {
+                Style::default().fg(colors.accent).add_modifier(Modifier::BOLD)
+            } else if shown {
+                Style::default().fg(colors.text_primary)
+            } else {
+                Style::default().fg(colors.text_muted)
+            };
+            let arrow = match sort {
+                Some(sort) if sort.column == column && sort.descending => " ▼",
+                Some(sort) if sort.column == column => " ▲",
+                _ => "",
+            };
+            Line::from(Span::styled(
+                format!(
+                    "{} {:<14}{}",
+                    if shown { "[x]" } else { "[ ]" },
+                    column.header,
+                    arrow
+                ),
+                style,
+            ))
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Space show · J/K move · s sort · Esc close",
+        Style::default().fg(colors.text_muted),
+    )));
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
 fn draw_info_panel(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColors) {
     let track = match app.current_track() {
         Some(t) => t,
         None => return,
     };
 
-    let width = area.width.min(60);
-    let height = area.height.min(14);
+    let value_style = Style::default().fg(colors.text_primary);
+    let mut info_lines = vec![
+        info_row("Title:", track.title.clone(), value_style, colors),
+        info_row("Artist:", track.artist.clone(), value_style, colors),
+        info_row("Album:", track.album.clone(), value_style, colors),
+    ];
+    if track.album_artist.is_some() || track.compilation {
+        info_lines.push(info_row(
+            "Album Artist:",
+            track.album_artist_or_artist().to_string(),
+            value_style,
+            colors,
+        ));
+    }
+    if let Some(n) = track.track_number {
+        let value = match track.track_total {
+            Some(total) => format!("{} / {}", n, total),
+            None => n.to_string(),
+        };
+        info_lines.push(info_row("Track:", value, value_style, colors));
+    }
+    if let Some(n) = track.disc_number {
+        let value = match track.disc_total {
+            Some(total) => format!("{} / {}", n, total),
+            None => n.to_string(),
+        };
+        info_lines.push(info_row("Disc:", value, value_style, colors));
+    }
+    if let Some(date) = track.date.clone().or_else(|| track.year.map(|y| y.to_string())) {
+        info_lines.push(info_row("Date:", date, value_style, colors));
+    }
+    if let Some(ref genre) = track.genre {
+        info_lines.push(info_row("Genre:", genre.clone(), value_style, colors));
+    }
+    if let Some(ref composer) = track.composer {
+        info_lines.push(info_row("Composer:", composer.clone(), value_style, colors));
+    }
+    if track.compilation {
+        info_lines.push(info_row("Compilation:", "Yes".to_string(), value_style, colors));
+    }
+    if let Some(rating) = app.ratings.get(track) {
+        info_lines.push(info_row("Rating:", ratings::stars(rating), value_style, colors));
+    }
+    info_lines.push(info_row("Duration:", format_time(track.duration), value_style, colors));
+    info_lines.push(info_row("Format:", track.format.clone(), value_style, colors));
+    info_lines.push(info_row(
+        "Bitrate:",
+        track
+            .bitrate
+            .map(|b| format!("{} kbps", b))
+            .unwrap_or_else(|| "N/A".to_string()),
+        value_style,
+        colors,
+    ));
+    info_lines.push(info_row(
+        "Sample Rate:",
+        track
+            .sample_rate
+            .map(|s| format!("{} Hz", s))
+            .unwrap_or_else(|| "N/A".to_string()),
+        value_style,
+        colors,
+    ));
+    if let Some(depth) = track.bit_depth {
+        info_lines.push(info_row("Bit Depth:", format!("{}-bit", depth), value_style, colors));
+    }
+    info_lines.push(info_row("File Size:", format_file_size(track.file_size), value_style, colors));
+    if let Some(ref comment) = track.comment {
+        info_lines.push(info_row("Comment:", comment.clone(), Style::default().fg(colors.text_dim), colors));
+    }
+    if let Some(ref id) = track.musicbrainz.release_id {
+        info_lines.push(info_row("MB Release:", id.clone(), Style::default().fg(colors.text_dim), colors));
+    }
+    info_lines.push(info_row(
+        "Path:",
+        track.path.display().to_string(),
+        Style::default().fg(colors.text_dim),
+        colors,
+    ));
+    if let Some(cue) = &track.cue {
+        info_lines.push(info_row(
+            "CUE Range:",
+            format!(
+                "{} – {}",
+                format_time(cue.start),
+                cue.end.map(format_time).unwrap_or_else(|| "end".to_string())
+            ),
+            Style::default().fg(colors.text_dim),
+            colors,
+        ));
+    }
+
+    let width = area.width.min(70);
+    let height = area.height.min(info_lines.len() as u16 + 2);
     let x = area.x + (area.width.saturating_sub(width)) / 2;
     let y = area.y + (area.height.saturating_sub(height)) / 2;
 
@@ -807,70 +1554,18 @@ fn draw_info_panel(frame: &mut Frame, app: &App, area: Rect, colors: &ThemeColor
     let inner = block.inner(panel_area);
     frame.render_widget(block, panel_area);
 
-    let info_lines = vec![
-        Line::from(vec![
-            Span::styled("Title:       ", Style::default().fg(colors.text_muted)),
-            Span::styled(&track.title, Style::default().fg(colors.text_primary)),
-        ]),
-        Line::from(vec![
-            Span::styled("Artist:      ", Style::default().fg(colors.text_muted)),
-            Span::styled(&track.artist, Style::default().fg(colors.text_primary)),
-        ]),
-        Line::from(vec![
-            Span::styled("Album:       ", Style::default().fg(colors.text_muted)),
-            Span::styled(&track.album, Style::default().fg(colors.text_primary)),
-        ]),
-        Line::from(vec![
-            Span::styled("Duration:    ", Style::default().fg(colors.text_muted)),
-            Span::styled(
-                format_time(track.duration),
-                Style::default().fg(colors.text_primary),
-            ),
-        ]),
-        Line::from(vec![
-            Span::styled("Format:      ", Style::default().fg(colors.text_muted)),
-            Span::styled(&track.format, Style::default().fg(colors.text_primary)),
-        ]),
-        Line::from(vec![
-            Span::styled("Bitrate:     ", Style::default().fg(colors.text_muted)),
-            Span::styled(
-                track
-                    .bitrate
-                    .map(|b| format!("{} kbps", b))
-                    .unwrap_or_else(|| "N/A".to_string()),
-                Style::default().fg(colors.text_primary),
-            ),
-        ]),
-        Line::from(vec![
-            Span::styled("Sample Rate: ", Style::default().fg(colors.text_muted)),
-            Span::styled(
-                track
-                    .sample_rate
-                    .map(|s| format!("{} Hz", s))
-                    .unwrap_or_else(|| "N/A".to_string()),
-                Style::default().fg(colors.text_primary),
-            ),
-        ]),
-        Line::from(vec![
-            Span::styled("File Size:   ", Style::default().fg(colors.text_muted)),
-            Span::styled(
-                format_file_size(track.file_size),
-                Style::default().fg(colors.text_primary),
-            ),
-        ]),
-        Line::from(vec![
-            Span::styled("Path:        ", Style::default().fg(colors.text_muted)),
-            Span::styled(
-                track.path.display().to_string(),
-                Style::default().fg(colors.text_dim),
-            ),
-        ]),
-    ];
-
     let paragraph = Paragraph::new(info_lines);
     frame.render_widget(paragraph, inner);
 }
 
+/// A "Label:      value" row for the info panel, labels padded to one column
+fn info_row<'a>(label: &'a str, value: String, value_style: Style, colors: &ThemeColors) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(format!("{:<14}", label), Style::default().fg(colors.text_muted)),
+        Span::styled(value, value_style),
+    ])
+}
+
 fn gradient_color(t: f32) -> Color {
     // Cyan (#06B6D4) -> Blue (#3B82F6) -> Magenta (#A855F7)
     let (r, g, b) = if t < 0.5 {
@@ -1073,6 +1768,43 @@ fn format_file_size(bytes: u64) -> String {
     }
 }
 
+/// `text` padded or cut to `width` like `truncate_str`, with the characters
+/// the search matched in `field` picked out
+fn highlighted(
+    app: &App,
+    field: &str,
+    text: &str,
+    width: usize,
+    style: Style,
+    colors: &ThemeColors,
+) -> Vec<Span<'static>> {
+    let ranges = app.search_filter.highlights(field, text);
+    let mut chars: Vec<char> = text.chars().collect();
+    if chars.len() > width {
+        chars.truncate(width.saturating_sub(1));
+        chars.push('…');
+    }
+    chars.resize(width.max(chars.len()), ' ');
+
+    let hit_style = style.fg(colors.accent_secondary).add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_hit = false;
+    for (i, c) in chars.into_iter().enumerate() {
+        let hit = ranges.iter().any(|r| r.contains(&i));
+        if hit != run_hit && !run.is_empty() {
+            let style = if run_hit { hit_style } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_hit = hit;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_hit { hit_style } else { style }));
+    }
+    spans
+}
+
 fn truncate_str(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         format!("{:<width$}", s, width = max_len)
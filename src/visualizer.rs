@@ -1,11 +1,12 @@
 use rustfft::{num_complex::Complex, FftPlanner};
+use serde::{Deserialize, Serialize};
 
 const NUM_BANDS: usize = 64; // Increased from 40 for more detail
 const SMOOTHING_FACTOR: f32 = 0.35; // Slightly smoother
 const FFT_SIZE: usize = 2048;
 const DEFAULT_WAVEFORM_WIDTH: usize = 200; // Default, will be updated dynamically
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VisualizerMode {
     FrequencyBars,
     Waveform,